@@ -0,0 +1,894 @@
+//! Archive listing and safe extraction
+//!
+//! Lists the members of ZIP, TAR (plain or gzip-compressed) and 7z archives
+//! without unpacking them, optionally inspects each member with magic
+//! detection and an encoding scan, and extracts archives into a directory.
+//! Extraction checks every entry before writing anything: names that leave
+//! the target directory, symlinks pointing outside it and members with
+//! absurd compression ratios (zip bombs) are refused.
+
+use crate::encoding::scan::{self, ScanOptions};
+use crate::encoding::EncodingType;
+use crate::error::DecodeckError;
+use crate::metadata::magic;
+use crate::output::file::{write_output, Overwrite};
+use crate::util::rfc3339;
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::{Component, Path, PathBuf};
+
+/// Largest ratio of uncompressed to compressed size accepted
+const MAX_COMPRESSION_RATIO: u64 = 100;
+
+/// Largest total uncompressed size accepted
+const MAX_TOTAL_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Largest member read for inspection
+const MAX_INSPECT_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Most symlinks followed while resolving a link target, as in Linux
+const MAX_LINK_HOPS: usize = 40;
+
+/// 7z coder ID for AES-256 encryption
+const SEVENZ_AES: &[u8] = &[0x06, 0xf1, 0x07, 0x01];
+
+/// Archive container format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ArchiveFormat {
+    #[serde(rename = "zip")]
+    Zip,
+    #[serde(rename = "tar")]
+    Tar,
+    #[serde(rename = "tar.gz")]
+    TarGz,
+    #[serde(rename = "7z")]
+    SevenZip,
+}
+
+impl ArchiveFormat {
+    /// Short name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            ArchiveFormat::Zip => "zip",
+            ArchiveFormat::Tar => "tar",
+            ArchiveFormat::TarGz => "tar.gz",
+            ArchiveFormat::SevenZip => "7z",
+        }
+    }
+}
+
+/// Kind of archive entry
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+    Symlink,
+    /// Hard links, devices and other special entries, which are never
+    /// extracted
+    Other,
+}
+
+/// A member of an archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveEntry {
+    /// Path inside the archive
+    pub name: String,
+    pub kind: EntryKind,
+    /// Uncompressed size in bytes
+    pub size: u64,
+    /// Compressed size in bytes, where the format records it per entry
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compressed_size: Option<u64>,
+    /// Modification time (RFC 3339; ZIP times are local and have no zone)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub modified: Option<String>,
+    pub encrypted: bool,
+    /// Target of a symlink or hard link
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_target: Option<String>,
+    /// Detected type of the member's content (with inspection)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    /// Encoded values found in the member's content (with inspection)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub findings: Vec<MemberFinding>,
+}
+
+/// Encoded content found inside an archive member
+#[derive(Debug, Clone, Serialize)]
+pub struct MemberFinding {
+    /// Location of the value inside the member
+    pub path: String,
+    pub encoding: EncodingType,
+    pub decoded: String,
+}
+
+/// Entries of an archive
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveListing {
+    pub format: ArchiveFormat,
+    pub entries: Vec<ArchiveEntry>,
+}
+
+impl ArchiveListing {
+    /// Total uncompressed size of all entries
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+}
+
+/// Identify an archive format from its leading bytes
+pub fn detect_format(data: &[u8]) -> Option<ArchiveFormat> {
+    if data.starts_with(b"PK\x03\x04") || data.starts_with(b"PK\x05\x06") {
+        Some(ArchiveFormat::Zip)
+    } else if data.starts_with(b"7z\xbc\xaf\x27\x1c") {
+        Some(ArchiveFormat::SevenZip)
+    } else if is_tar(data) {
+        Some(ArchiveFormat::Tar)
+    } else if data.starts_with(b"\x1f\x8b") {
+        // Only the first header block is inflated to check for a tar
+        let mut header = Vec::with_capacity(512);
+        GzDecoder::new(data)
+            .take(512)
+            .read_to_end(&mut header)
+            .ok()?;
+        is_tar(&header).then_some(ArchiveFormat::TarGz)
+    } else {
+        None
+    }
+}
+
+/// List the entries of an archive, or `None` if the data is not one
+///
+/// With `inspect`, each readable file is run through magic detection and
+/// an encoding scan.
+pub fn list(data: &[u8], inspect: bool) -> Result<Option<ArchiveListing>, DecodeckError> {
+    let Some(format) = detect_format(data) else {
+        return Ok(None);
+    };
+    let inflated;
+    let data = match format {
+        ArchiveFormat::TarGz => {
+            inflated = gunzip(data)?;
+            &inflated[..]
+        }
+        _ => data,
+    };
+
+    let mut entries = Vec::new();
+    walk(data, format, inspect, |entry, content| {
+        let mut entry = entry.clone();
+        if let Some(content) = content {
+            inspect_member(&mut entry, content)?;
+        }
+        entries.push(entry);
+        Ok(())
+    })?;
+    Ok(Some(ArchiveListing { format, entries }))
+}
+
+/// Extract an archive into a directory and return the files written
+///
+/// All entries are checked before anything is written. A member below one
+/// of the archive's symlinks is written where the link leads, so symlinks
+/// can be created last (and only on Unix) and no entry is written through
+/// one; nothing is written below an existing symlink either. Existing files
+/// are only replaced with `overwrite`; without it, the files written so far
+/// are removed if writing fails part way.
+pub fn extract(data: &[u8], dir: &Path, overwrite: bool) -> Result<Vec<PathBuf>, DecodeckError> {
+    let listing = list(data, false)?.ok_or_else(|| DecodeckError::InvalidArchive {
+        message: "content is not a ZIP, TAR or 7z archive".to_string(),
+    })?;
+    let paths = check_entries(&listing, data.len() as u64)?;
+    if !overwrite {
+        for (entry, relative) in listing.entries.iter().zip(&paths) {
+            if entry.kind == EntryKind::Directory {
+                continue;
+            }
+            let path = dir.join(relative);
+            if path.symlink_metadata().is_ok() {
+                return Err(DecodeckError::OutputExists {
+                    path: path.display().to_string(),
+                });
+            }
+        }
+    }
+
+    let inflated;
+    let data = match listing.format {
+        ArchiveFormat::TarGz => {
+            inflated = gunzip(data)?;
+            &inflated[..]
+        }
+        _ => data,
+    };
+
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    let result = write_entries(data, &listing, &paths, dir, overwrite, &mut written);
+    if result.is_err() && !overwrite {
+        for path in &written {
+            let _ = fs::remove_file(path);
+        }
+    }
+    result.map(|()| written)
+}
+
+/// Write each entry to its checked path, recording the files written
+fn write_entries(
+    data: &[u8],
+    listing: &ArchiveListing,
+    paths: &[PathBuf],
+    dir: &Path,
+    overwrite: bool,
+    written: &mut Vec<PathBuf>,
+) -> Result<(), DecodeckError> {
+    let mut relative_paths = paths.iter();
+    let mut links = Vec::new();
+    walk(data, listing.format, true, |entry, content| {
+        let relative = relative_paths
+            .next()
+            .ok_or_else(|| invalid("entries changed while extracting"))?;
+        let path = dir.join(relative);
+        ensure_no_symlinks(dir, relative)?;
+        match entry.kind {
+            EntryKind::Directory => fs::create_dir_all(&path)?,
+            EntryKind::File => {
+                let Some(content) = content else {
+                    // Encrypted members cannot be extracted
+                    return Ok(());
+                };
+                let mut bytes = Vec::new();
+                content.take(entry.size + 1).read_to_end(&mut bytes)?;
+                if bytes.len() as u64 > entry.size {
+                    return Err(DecodeckError::UnsafeArchive {
+                        reason: format!("'{}' is larger than its declared size", entry.name),
+                    });
+                }
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mode = if overwrite {
+                    Overwrite::Replace
+                } else {
+                    Overwrite::Refuse
+                };
+                written.push(write_output(&path, &bytes, mode)?);
+            }
+            EntryKind::Symlink => {
+                if let Some(ref target) = entry.link_target {
+                    links.push((path, target.clone()));
+                }
+            }
+            EntryKind::Other => {}
+        }
+        Ok(())
+    })?;
+
+    #[cfg(unix)]
+    for (path, target) in links {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if path.symlink_metadata().is_ok() {
+            if !overwrite {
+                return Err(DecodeckError::OutputExists {
+                    path: path.display().to_string(),
+                });
+            }
+            fs::remove_file(&path)?;
+        }
+        std::os::unix::fs::symlink(&target, &path)?;
+        written.push(path);
+    }
+    #[cfg(not(unix))]
+    drop(links);
+
+    Ok(())
+}
+
+/// Refuse archives with unsafe names, escaping links or bomb-like sizes
+///
+/// Returns where each entry is written, relative to the target directory:
+/// its name with any of the archive's symlinks among its parents followed.
+fn check_entries(
+    listing: &ArchiveListing,
+    archive_size: u64,
+) -> Result<Vec<PathBuf>, DecodeckError> {
+    let unsafe_archive = |reason: String| Err(DecodeckError::UnsafeArchive { reason });
+
+    // Links are resolved through each other, as they will be once extracted,
+    // and keyed by where they are created
+    let mut named_links = HashMap::new();
+    for entry in &listing.entries {
+        if entry.kind == EntryKind::Symlink {
+            let target = entry.link_target.as_deref().unwrap_or_default();
+            named_links.insert(safe_relative_path(&entry.name)?, target);
+        }
+    }
+    let links: HashMap<PathBuf, &str> = named_links
+        .iter()
+        .filter_map(|(name, &target)| Some((entry_path(name, &named_links)?, target)))
+        .collect();
+
+    let mut paths = Vec::with_capacity(listing.entries.len());
+    for entry in &listing.entries {
+        let relative = safe_relative_path(&entry.name)?;
+        let Some(path) = entry_path(&relative, &links) else {
+            return unsafe_archive(format!(
+                "'{}' leaves the target directory through a symlink",
+                entry.name
+            ));
+        };
+        if entry.kind != EntryKind::Symlink && links.contains_key(&path) {
+            return unsafe_archive(format!(
+                "'{}' would be written through a symlink",
+                entry.name
+            ));
+        }
+        if let Some(compressed) = entry.compressed_size.filter(|&c| c > 0) {
+            if entry.size / compressed > MAX_COMPRESSION_RATIO {
+                return unsafe_archive(format!(
+                    "'{}' has a compression ratio of {}:1",
+                    entry.name,
+                    entry.size / compressed
+                ));
+            }
+        }
+        if entry.kind == EntryKind::Symlink {
+            let target = entry.link_target.as_deref().unwrap_or_default();
+            let parent = path.parent().unwrap_or(Path::new(""));
+            if Path::new(target).is_absolute() || resolve(&parent.join(target), &links).is_none() {
+                return unsafe_archive(format!(
+                    "symlink '{}' points outside the target directory ({})",
+                    entry.name, target
+                ));
+            }
+        }
+        paths.push(path);
+    }
+
+    let total = listing.total_size();
+    if total > MAX_TOTAL_SIZE || total / archive_size.max(1) > MAX_COMPRESSION_RATIO {
+        return unsafe_archive(format!(
+            "expands to {} bytes from {} bytes",
+            total, archive_size
+        ));
+    }
+    Ok(paths)
+}
+
+/// Entry name as a relative path that stays inside the target directory
+fn safe_relative_path(name: &str) -> Result<PathBuf, DecodeckError> {
+    let path = Path::new(name);
+    let mut relative = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => {
+                return Err(DecodeckError::UnsafeArchive {
+                    reason: format!("'{}' escapes the target directory", name),
+                })
+            }
+        }
+    }
+    if relative.as_os_str().is_empty() {
+        return Err(DecodeckError::UnsafeArchive {
+            reason: format!("'{}' has no file name", name),
+        });
+    }
+    Ok(relative)
+}
+
+/// Where an entry lands: its parent resolved through the archive's links,
+/// and its own name, which is not followed
+fn entry_path(relative: &Path, links: &HashMap<PathBuf, &str>) -> Option<PathBuf> {
+    let parent = resolve(relative.parent().unwrap_or(Path::new("")), links)?;
+    Some(parent.join(relative.file_name()?))
+}
+
+/// Resolve a relative path through the archive's own symlinks
+///
+/// `..` is resolved after following any link the path passes through, so
+/// `b -> a/..` escapes when `a -> .`. Returns `None` for paths that leave
+/// the target directory or follow too many links.
+fn resolve(path: &Path, links: &HashMap<PathBuf, &str>) -> Option<PathBuf> {
+    let mut resolved = PathBuf::new();
+    let mut pending: Vec<Component> = path.components().rev().collect();
+    let mut hops = 0;
+    while let Some(component) = pending.pop() {
+        match component {
+            Component::Normal(part) => {
+                resolved.push(part);
+                if let Some(target) = links.get(&resolved) {
+                    hops += 1;
+                    if hops > MAX_LINK_HOPS {
+                        return None;
+                    }
+                    resolved.pop();
+                    pending.extend(Path::new(*target).components().rev());
+                }
+            }
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !resolved.pop() {
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+    Some(resolved)
+}
+
+/// Refuse to write below a symlink inside the target directory
+fn ensure_no_symlinks(dir: &Path, relative: &Path) -> Result<(), DecodeckError> {
+    let mut current = dir.to_path_buf();
+    for component in relative.components() {
+        current.push(component);
+        if current
+            .symlink_metadata()
+            .is_ok_and(|m| m.file_type().is_symlink())
+        {
+            return Err(DecodeckError::UnsafeArchive {
+                reason: format!(
+                    "'{}' would be written through a symlink",
+                    relative.display()
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Read a member and record its type and any encoded values it holds
+fn inspect_member(entry: &mut ArchiveEntry, content: &mut dyn Read) -> Result<(), DecodeckError> {
+    let mut bytes = Vec::new();
+    content.take(MAX_INSPECT_SIZE).read_to_end(&mut bytes)?;
+    let metadata = magic::detect(&bytes);
+    entry.mime_type = Some(match metadata.charset {
+        Some(charset) => format!("{}; charset={}", metadata.mime_type, charset.name()),
+        None => metadata.mime_type,
+    });
+
+    let Ok(text) = std::str::from_utf8(&bytes) else {
+        return Ok(());
+    };
+    let format = scan::detect_format(text, Some(Path::new(&entry.name)));
+    entry.findings = match scan::scan_with(text, format, &ScanOptions::default()) {
+        Ok(result) => result
+            .findings
+            .into_iter()
+            .map(|f| MemberFinding {
+                path: f.path,
+                encoding: f.encoding,
+                decoded: f.decoded,
+            })
+            .collect(),
+        // Not a structured document: the whole member may be one value
+        Err(_) => scan::try_decode_value(text, &entry.name)
+            .map(|f| MemberFinding {
+                path: f.path,
+                encoding: f.encoding,
+                decoded: f.decoded,
+            })
+            .into_iter()
+            .collect(),
+    };
+    Ok(())
+}
+
+/// Visit each entry, with a reader over its content when `content` is set
+/// and the entry is a readable file
+fn walk<F>(data: &[u8], format: ArchiveFormat, content: bool, visit: F) -> Result<(), DecodeckError>
+where
+    F: FnMut(&ArchiveEntry, Option<&mut dyn Read>) -> Result<(), DecodeckError>,
+{
+    match format {
+        ArchiveFormat::Zip => walk_zip(data, content, visit),
+        ArchiveFormat::Tar | ArchiveFormat::TarGz => walk_tar(data, content, visit),
+        ArchiveFormat::SevenZip => walk_7z(data, content, visit),
+    }
+}
+
+fn walk_zip<F>(data: &[u8], content: bool, mut visit: F) -> Result<(), DecodeckError>
+where
+    F: FnMut(&ArchiveEntry, Option<&mut dyn Read>) -> Result<(), DecodeckError>,
+{
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(invalid)?;
+    for index in 0..archive.len() {
+        let mut entry = {
+            let file = archive.by_index_raw(index).map_err(invalid)?;
+            ArchiveEntry {
+                name: file.name().to_string(),
+                kind: if file.is_dir() {
+                    EntryKind::Directory
+                } else if file.is_symlink() {
+                    EntryKind::Symlink
+                } else {
+                    EntryKind::File
+                },
+                size: file.size(),
+                compressed_size: Some(file.compressed_size()),
+                modified: file.last_modified().map(|t| {
+                    format!(
+                        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                        t.year(),
+                        t.month(),
+                        t.day(),
+                        t.hour(),
+                        t.minute(),
+                        t.second()
+                    )
+                }),
+                encrypted: file.encrypted(),
+                link_target: None,
+                mime_type: None,
+                findings: vec![],
+            }
+        };
+
+        let readable = !entry.encrypted && entry.kind != EntryKind::Directory;
+        if !readable || !(content || entry.kind == EntryKind::Symlink) {
+            visit(&entry, None)?;
+            continue;
+        }
+        let mut file = archive.by_index(index).map_err(invalid)?;
+        if entry.kind == EntryKind::Symlink {
+            // A ZIP symlink stores its target as the content
+            let mut target = String::new();
+            file.by_ref().take(4096).read_to_string(&mut target)?;
+            entry.link_target = Some(target);
+            visit(&entry, None)?;
+        } else {
+            visit(&entry, Some(&mut file))?;
+        }
+    }
+    Ok(())
+}
+
+fn walk_tar<F>(data: &[u8], content: bool, mut visit: F) -> Result<(), DecodeckError>
+where
+    F: FnMut(&ArchiveEntry, Option<&mut dyn Read>) -> Result<(), DecodeckError>,
+{
+    let mut archive = tar::Archive::new(data);
+    for file in archive.entries().map_err(invalid)? {
+        let mut file = file.map_err(invalid)?;
+        let header = file.header();
+        let kind = match header.entry_type() {
+            tar::EntryType::Regular | tar::EntryType::Continuous => EntryKind::File,
+            tar::EntryType::Directory => EntryKind::Directory,
+            tar::EntryType::Symlink => EntryKind::Symlink,
+            _ => EntryKind::Other,
+        };
+        let entry = ArchiveEntry {
+            name: file.path().map_err(invalid)?.to_string_lossy().into_owned(),
+            kind,
+            size: header.size().map_err(invalid)?,
+            compressed_size: None,
+            modified: header.mtime().ok().map(rfc3339),
+            encrypted: false,
+            link_target: file
+                .link_name()
+                .ok()
+                .flatten()
+                .map(|target| target.to_string_lossy().into_owned()),
+            mime_type: None,
+            findings: vec![],
+        };
+        if content && kind == EntryKind::File {
+            visit(&entry, Some(&mut file))?;
+        } else {
+            visit(&entry, None)?;
+        }
+    }
+    Ok(())
+}
+
+fn walk_7z<F>(data: &[u8], content: bool, mut visit: F) -> Result<(), DecodeckError>
+where
+    F: FnMut(&ArchiveEntry, Option<&mut dyn Read>) -> Result<(), DecodeckError>,
+{
+    let mut cursor = Cursor::new(data);
+    let archive =
+        sevenz_rust::Archive::read(&mut cursor, data.len() as u64, &[]).map_err(invalid)?;
+
+    let encrypted_folders: Vec<bool> = archive
+        .folders
+        .iter()
+        .map(|folder| {
+            folder
+                .coders
+                .iter()
+                .any(|c| c.decompression_method_id() == SEVENZ_AES)
+        })
+        .collect();
+    let entries: Vec<ArchiveEntry> = archive
+        .files
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            let folder = archive.stream_map.file_folder_index[index];
+            ArchiveEntry {
+                name: file.name().to_string(),
+                kind: if file.is_directory() {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                },
+                size: file.size(),
+                // Solid archives compress several files as one block, whose
+                // size is recorded on its first file only
+                compressed_size: Some(file.compressed_size).filter(|&c| c > 0),
+                modified: file
+                    .has_last_modified_date
+                    .then(|| file.last_modified_date().to_unix_time())
+                    .and_then(|secs| u64::try_from(secs).ok())
+                    .map(rfc3339),
+                encrypted: folder.is_some_and(|f| encrypted_folders[f]),
+                link_target: None,
+                mime_type: None,
+                findings: vec![],
+            }
+        })
+        .collect();
+
+    if !content || entries.iter().any(|e| e.encrypted) {
+        for entry in &entries {
+            visit(entry, None)?;
+        }
+        return Ok(());
+    }
+
+    let mut reader = sevenz_rust::SevenZReader::from_archive(
+        archive,
+        Cursor::new(data),
+        sevenz_rust::Password::empty(),
+    );
+    // Entries come block by block, then the empty files
+    let mut visited = vec![false; entries.len()];
+    let mut result = Ok(());
+    reader
+        .for_each_entries(|file, file_content| {
+            let Some(index) =
+                (0..entries.len()).find(|&i| !visited[i] && entries[i].name == file.name())
+            else {
+                return Ok(true);
+            };
+            visited[index] = true;
+            let entry = &entries[index];
+            result = if entry.kind == EntryKind::File {
+                visit(entry, Some(file_content))
+            } else {
+                visit(entry, None)
+            };
+            Ok(result.is_ok())
+        })
+        .map_err(invalid)?;
+    result
+}
+
+fn gunzip(data: &[u8]) -> Result<Vec<u8>, DecodeckError> {
+    let mut inflated = Vec::new();
+    GzDecoder::new(data)
+        .take(MAX_TOTAL_SIZE + 1)
+        .read_to_end(&mut inflated)?;
+    if inflated.len() as u64 > MAX_TOTAL_SIZE {
+        return Err(DecodeckError::UnsafeArchive {
+            reason: format!("expands to more than {} bytes", MAX_TOTAL_SIZE),
+        });
+    }
+    Ok(inflated)
+}
+
+fn is_tar(data: &[u8]) -> bool {
+    data.get(257..262) == Some(b"ustar")
+}
+
+fn invalid(error: impl std::fmt::Display) -> DecodeckError {
+    DecodeckError::InvalidArchive {
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, content) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(content).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    fn tar_archive(build: impl FnOnce(&mut tar::Builder<Vec<u8>>)) -> Vec<u8> {
+        let mut builder = tar::Builder::new(Vec::new());
+        build(&mut builder);
+        builder.into_inner().unwrap()
+    }
+
+    fn tar_header(kind: tar::EntryType, size: u64) -> tar::Header {
+        let mut header = tar::Header::new_gnu();
+        header.set_entry_type(kind);
+        header.set_size(size);
+        header.set_mode(0o644);
+        header.set_mtime(1_700_000_000);
+        header
+    }
+
+    #[test]
+    fn test_list_zip_with_inspection() {
+        let data = zip_archive(&[
+            ("config.json", br#"{"token": "0x48656c6c6f"}"#),
+            ("readme.txt", b"hello"),
+        ]);
+        let listing = list(&data, true).unwrap().unwrap();
+        assert_eq!(listing.format, ArchiveFormat::Zip);
+        assert_eq!(listing.entries.len(), 2);
+        assert_eq!(listing.entries[0].name, "config.json");
+        assert_eq!(listing.entries[0].findings[0].decoded, "Hello");
+        assert_eq!(listing.entries[1].size, 5);
+        assert!(!listing.entries[1].encrypted);
+
+        assert!(list(b"not an archive", false).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_list_tar() {
+        let data = tar_archive(|builder| {
+            let mut header = tar_header(tar::EntryType::Regular, 2);
+            builder
+                .append_data(&mut header, "a.txt", &b"hi"[..])
+                .unwrap();
+        });
+        let listing = list(&data, false).unwrap().unwrap();
+        assert_eq!(listing.format, ArchiveFormat::Tar);
+        assert_eq!(listing.entries[0].name, "a.txt");
+        assert_eq!(
+            listing.entries[0].modified.as_deref(),
+            Some("2023-11-14T22:13:20Z")
+        );
+    }
+
+    #[test]
+    fn test_extract_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = zip_archive(&[("docs/a.txt", b"alpha"), ("b.txt", b"beta")]);
+
+        let written = extract(&data, dir.path(), false).unwrap();
+        assert_eq!(written.len(), 2);
+        assert_eq!(fs::read(dir.path().join("docs/a.txt")).unwrap(), b"alpha");
+
+        // Existing files are kept unless overwriting
+        assert!(matches!(
+            extract(&data, dir.path(), false),
+            Err(DecodeckError::OutputExists { .. })
+        ));
+        assert!(extract(&data, dir.path(), true).is_ok());
+    }
+
+    #[test]
+    fn test_extract_rejects_traversal() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = zip_archive(&[("ok.txt", b"fine"), ("../evil.txt", b"pwned")]);
+
+        let err = extract(&data, dir.path(), false).unwrap_err();
+        assert!(matches!(err, DecodeckError::UnsafeArchive { .. }));
+        assert!(!dir.path().join("ok.txt").exists());
+    }
+
+    #[test]
+    fn test_extract_rejects_escaping_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let data = tar_archive(|builder| {
+            let mut header = tar_header(tar::EntryType::Symlink, 0);
+            builder
+                .append_link(&mut header, "sub/link", "../../etc")
+                .unwrap();
+        });
+        let err = extract(&data, dir.path(), false).unwrap_err();
+        assert!(err.to_string().contains("points outside"));
+    }
+
+    #[test]
+    fn test_extract_rejects_chained_symlinks() {
+        let links = |pairs: &[(&str, &str)]| {
+            tar_archive(|builder| {
+                for (name, target) in pairs {
+                    let mut header = tar_header(tar::EntryType::Symlink, 0);
+                    builder.append_link(&mut header, name, target).unwrap();
+                }
+            })
+        };
+
+        // Each target stays inside on its own, but b resolves to the parent
+        for pairs in [
+            &[("a", "."), ("b", "a/..")][..],
+            &[("b", "a/.."), ("a", ".")][..],
+            &[("d/a", ".."), ("d/b", "a/../..")][..],
+            &[("a", "b"), ("b", "a")][..],
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            let err = extract(&links(pairs), dir.path(), false).unwrap_err();
+            assert!(err.to_string().contains("points outside"), "{}", err);
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let safe = links(&[("d/a", ".."), ("d/b", "a/d")]);
+        assert!(extract(&safe, dir.path(), false).is_ok());
+    }
+
+    #[test]
+    fn test_extract_follows_archive_symlinks() {
+        let archive = |members: &[(&str, Option<&str>)]| {
+            tar_archive(|builder| {
+                for (name, target) in members {
+                    match target {
+                        Some(target) => {
+                            let mut header = tar_header(tar::EntryType::Symlink, 0);
+                            builder.append_link(&mut header, name, target).unwrap();
+                        }
+                        None => {
+                            let mut header = tar_header(tar::EntryType::Regular, 2);
+                            builder.append_data(&mut header, name, &b"ok"[..]).unwrap();
+                        }
+                    }
+                }
+            })
+        };
+
+        // Members below a link are written where it leads
+        let dir = tempfile::tempdir().unwrap();
+        let data = archive(&[
+            ("d/a.txt", None),
+            ("inner", Some("d")),
+            ("inner/x.txt", None),
+        ]);
+        extract(&data, dir.path(), false).unwrap();
+        assert_eq!(fs::read(dir.path().join("d/x.txt")).unwrap(), b"ok");
+        assert!(dir.path().join("inner").is_symlink());
+
+        let dir = tempfile::tempdir().unwrap();
+        let data = archive(&[("d/up", Some("..")), ("d/up/ok", None)]);
+        extract(&data, dir.path(), false).unwrap();
+        assert_eq!(fs::read(dir.path().join("ok")).unwrap(), b"ok");
+
+        // Escapes are refused before anything is written
+        let dir = tempfile::tempdir().unwrap();
+        let data = archive(&[("a.txt", None), ("up", Some("..")), ("up/evil", None)]);
+        let err = extract(&data, dir.path(), false).unwrap_err();
+        assert!(err.to_string().contains("points outside"), "{}", err);
+        let data = archive(&[("a.txt", None), ("up/evil", None), ("up", Some(".."))]);
+        let err = extract(&data, dir.path(), false).unwrap_err();
+        assert!(err.to_string().contains("leaves the target"), "{}", err);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_extract_rejects_bomb() {
+        let dir = tempfile::tempdir().unwrap();
+        let zeros = vec![0u8; 8 * 1024 * 1024];
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated)
+            .compression_level(Some(9));
+        writer.start_file("zeros.bin", options).unwrap();
+        writer.write_all(&zeros).unwrap();
+        let data = writer.finish().unwrap().into_inner();
+
+        let err = extract(&data, dir.path(), false).unwrap_err();
+        assert!(err.to_string().contains(":1"), "{}", err);
+    }
+}
@@ -0,0 +1,610 @@
+//! Carving of files embedded in decoded data
+//!
+//! [`magic::detect`](crate::metadata::magic::detect) only looks at the start
+//! of the data. Carving sweeps every offset for known signatures and walks
+//! each format's structure to find where the embedded file ends. Formats
+//! without a usable structure (or cut off by the end of the data) run up to
+//! the next embedded file and are marked incomplete.
+
+use crate::error::DecodeckError;
+use crate::output::file::{write_output, Overwrite};
+use flate2::bufread::GzDecoder;
+use serde::Serialize;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Largest gzip member inflated to find its end
+const MAX_INFLATED: u64 = 256 * 1024 * 1024;
+
+/// A file found inside the data
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Carved {
+    /// Offset of the first byte
+    pub offset: usize,
+    /// Length in bytes
+    pub length: usize,
+    /// Format name (e.g. "png")
+    pub format: &'static str,
+    pub mime_type: &'static str,
+    /// Suggested file extension, including the dot
+    pub extension: &'static str,
+    /// Whether the end was found from the format's structure
+    pub complete: bool,
+}
+
+/// Where an embedded file ends
+enum Extent {
+    /// The signature matched by chance
+    Invalid,
+    /// Length from the start of the file
+    Known(usize),
+    /// The format is valid but its end cannot be determined
+    Unknown,
+}
+
+struct Signature {
+    format: &'static str,
+    magic: &'static [u8],
+    mime_type: &'static str,
+    extension: &'static str,
+    extent: fn(&[u8]) -> Extent,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature {
+        format: "png",
+        magic: b"\x89PNG\r\n\x1a\n",
+        mime_type: "image/png",
+        extension: ".png",
+        extent: png_extent,
+    },
+    Signature {
+        format: "jpeg",
+        magic: b"\xff\xd8\xff",
+        mime_type: "image/jpeg",
+        extension: ".jpg",
+        extent: jpeg_extent,
+    },
+    Signature {
+        format: "gif",
+        magic: b"GIF8",
+        mime_type: "image/gif",
+        extension: ".gif",
+        extent: gif_extent,
+    },
+    Signature {
+        format: "zip",
+        magic: b"PK\x03\x04",
+        mime_type: "application/zip",
+        extension: ".zip",
+        extent: zip_extent,
+    },
+    Signature {
+        format: "gzip",
+        magic: b"\x1f\x8b\x08",
+        mime_type: "application/gzip",
+        extension: ".gz",
+        extent: gzip_extent,
+    },
+    Signature {
+        format: "7z",
+        magic: b"7z\xbc\xaf\x27\x1c",
+        mime_type: "application/x-7z-compressed",
+        extension: ".7z",
+        extent: sevenzip_extent,
+    },
+    Signature {
+        format: "pe",
+        magic: b"MZ",
+        mime_type: "application/vnd.microsoft.portable-executable",
+        extension: ".exe",
+        extent: pe_extent,
+    },
+    Signature {
+        format: "elf",
+        magic: b"\x7fELF",
+        mime_type: "application/x-executable",
+        extension: ".elf",
+        extent: elf_extent,
+    },
+    Signature {
+        format: "pdf",
+        magic: b"%PDF-",
+        mime_type: "application/pdf",
+        extension: ".pdf",
+        extent: pdf_extent,
+    },
+];
+
+/// Find embedded files at any offset, in order
+///
+/// Data inside a file whose end is known is not searched again, so the
+/// images inside a stored ZIP are not listed separately.
+pub fn carve(data: &[u8]) -> Vec<Carved> {
+    let mut found: Vec<Carved> = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let rest = &data[offset..];
+        let hit = SIGNATURES.iter().find_map(|sig| {
+            if !rest.starts_with(sig.magic) {
+                return None;
+            }
+            let extent = match (sig.extent)(rest) {
+                Extent::Invalid => return None,
+                Extent::Known(length) if length <= rest.len() => Some(length),
+                _ => None,
+            };
+            Some(Carved {
+                offset,
+                length: extent.unwrap_or(rest.len()),
+                format: sig.format,
+                mime_type: sig.mime_type,
+                extension: sig.extension,
+                complete: extent.is_some(),
+            })
+        });
+
+        match hit {
+            Some(carved) => {
+                // An open-ended file stops where the next one starts
+                if let Some(last) = found.last_mut().filter(|c| !c.complete) {
+                    last.length = last.length.min(offset - last.offset);
+                }
+                offset += if carved.complete { carved.length } else { 1 };
+                found.push(carved);
+            }
+            None => offset += 1,
+        }
+    }
+    found
+}
+
+/// Write carved files to a directory as `<offset in hex><extension>`
+///
+/// Existing files are handled as `overwrite` says; the paths actually
+/// written are returned.
+pub fn extract(
+    data: &[u8],
+    carved: &[Carved],
+    dir: &Path,
+    overwrite: Overwrite,
+) -> Result<Vec<PathBuf>, DecodeckError> {
+    std::fs::create_dir_all(dir)?;
+    carved
+        .iter()
+        .map(|c| {
+            let path = dir.join(format!("{:08x}{}", c.offset, c.extension));
+            write_output(&path, &data[c.offset..c.offset + c.length], overwrite)
+        })
+        .collect()
+}
+
+fn png_extent(data: &[u8]) -> Extent {
+    let mut pos = 8;
+    let mut first = true;
+    while let (Some(length), Some(kind)) = (be32(data, pos), data.get(pos + 4..pos + 8)) {
+        if first && (kind != b"IHDR" || length != 13) {
+            return Extent::Invalid;
+        }
+        first = false;
+        pos += 12 + length as usize;
+        if kind == b"IEND" {
+            return Extent::Known(pos);
+        }
+    }
+    if first {
+        Extent::Invalid
+    } else {
+        Extent::Unknown
+    }
+}
+
+/// Walk the marker segments to the end of the first scan, then through the
+/// entropy-coded data to the end-of-image marker
+fn jpeg_extent(data: &[u8]) -> Extent {
+    let mut pos = 2;
+    let mut segments = 0;
+    loop {
+        let Some(&[0xff, marker]) = data.get(pos..pos + 2) else {
+            return if segments > 0 {
+                Extent::Unknown
+            } else {
+                Extent::Invalid
+            };
+        };
+        match marker {
+            0xd9 => return Extent::Known(pos + 2),
+            0xff => {
+                pos += 1;
+                continue;
+            }
+            0x01 | 0xd0..=0xd7 => {
+                pos += 2;
+                continue;
+            }
+            // Application, quantization, Huffman, frame and scan headers
+            0xc0..=0xcf | 0xda..=0xfe => {}
+            _ if segments == 0 => return Extent::Invalid,
+            _ => return Extent::Unknown,
+        }
+        let Some(length) = be16(data, pos + 2) else {
+            return Extent::Unknown;
+        };
+        pos += 2 + length as usize;
+        segments += 1;
+        if marker == 0xda {
+            // Skip entropy-coded data up to the next real marker
+            while let Some(&[byte, next]) = data.get(pos..pos + 2) {
+                if byte == 0xff && !matches!(next, 0x00 | 0xd0..=0xd7 | 0xff) {
+                    break;
+                }
+                pos += 1;
+            }
+        }
+    }
+}
+
+fn gif_extent(data: &[u8]) -> Extent {
+    if !matches!(data.get(4..6), Some(b"7a" | b"9a")) {
+        return Extent::Invalid;
+    }
+    let Some(&flags) = data.get(10) else {
+        return Extent::Unknown;
+    };
+    let mut pos = 13 + color_table_len(flags);
+    loop {
+        match data.get(pos) {
+            Some(0x3b) => return Extent::Known(pos + 1),
+            // Extension: label, then sub-blocks
+            Some(0x21) => pos = skip_sub_blocks(data, pos + 2),
+            // Image: descriptor, local color table, LZW code size, sub-blocks
+            Some(0x2c) => {
+                let Some(&flags) = data.get(pos + 9) else {
+                    return Extent::Unknown;
+                };
+                pos = skip_sub_blocks(data, pos + 11 + color_table_len(flags));
+            }
+            _ => return Extent::Unknown,
+        }
+    }
+}
+
+fn color_table_len(flags: u8) -> usize {
+    if flags & 0x80 != 0 {
+        3 << ((flags & 0x07) + 1)
+    } else {
+        0
+    }
+}
+
+fn skip_sub_blocks(data: &[u8], mut pos: usize) -> usize {
+    while let Some(&size) = data.get(pos) {
+        pos += 1 + size as usize;
+        if size == 0 {
+            break;
+        }
+    }
+    pos
+}
+
+/// A ZIP ends with its end-of-central-directory record and comment
+fn zip_extent(data: &[u8]) -> Extent {
+    match find(data, b"PK\x05\x06", 4) {
+        Some(eocd) => match le16(data, eocd + 20) {
+            Some(comment) => Extent::Known(eocd + 22 + comment as usize),
+            None => Extent::Unknown,
+        },
+        None => Extent::Unknown,
+    }
+}
+
+/// A gzip member ends after the deflate stream and an 8-byte trailer, which
+/// can only be found by inflating it
+fn gzip_extent(data: &[u8]) -> Extent {
+    if data.get(3).map_or(true, |flags| flags & 0xe0 != 0) {
+        return Extent::Invalid;
+    }
+    let mut decoder = GzDecoder::new(data);
+    let mut limited = (&mut decoder).take(MAX_INFLATED);
+    match io::copy(&mut limited, &mut io::sink()) {
+        Ok(n) if n < MAX_INFLATED => Extent::Known(data.len() - decoder.into_inner().len()),
+        _ => Extent::Unknown,
+    }
+}
+
+/// A 7z start header gives the position and size of the next header, which
+/// comes last
+fn sevenzip_extent(data: &[u8]) -> Extent {
+    match (le64(data, 12), le64(data, 20)) {
+        (Some(offset), Some(size)) => offset
+            .checked_add(size)
+            .and_then(|end| end.checked_add(32))
+            .map_or(Extent::Invalid, |end| Extent::Known(end as usize)),
+        _ => Extent::Unknown,
+    }
+}
+
+/// A PE image ends with its last section's raw data
+fn pe_extent(data: &[u8]) -> Extent {
+    let Some(header) = le32(data, 0x3c).map(|h| h as usize) else {
+        return Extent::Invalid;
+    };
+    if data.get(header..header + 4) != Some(b"PE\0\0") {
+        return Extent::Invalid;
+    }
+    let (Some(sections), Some(optional)) = (le16(data, header + 6), le16(data, header + 20)) else {
+        return Extent::Unknown;
+    };
+    let table = header + 24 + optional as usize;
+    let mut end = table + sections as usize * 40;
+    for section in 0..sections as usize {
+        let entry = table + section * 40;
+        let (Some(size), Some(pointer)) = (le32(data, entry + 16), le32(data, entry + 20)) else {
+            return Extent::Unknown;
+        };
+        if size > 0 {
+            end = end.max(pointer as usize + size as usize);
+        }
+    }
+    Extent::Known(end)
+}
+
+/// An ELF file ends with whichever comes last of its section headers,
+/// section contents and segments
+fn elf_extent(data: &[u8]) -> Extent {
+    let wide = match data.get(4) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Extent::Invalid,
+    };
+    let big = match data.get(5) {
+        Some(1) => false,
+        Some(2) => true,
+        _ => return Extent::Invalid,
+    };
+    let half = |pos| read_uint(data, pos, 2, big);
+    let word = |pos| read_uint(data, pos, if wide { 8 } else { 4 }, big);
+
+    let fields = if wide {
+        (
+            word(0x20),
+            word(0x28),
+            half(0x36),
+            half(0x38),
+            half(0x3a),
+            half(0x3c),
+        )
+    } else {
+        (
+            word(0x1c),
+            word(0x20),
+            half(0x2a),
+            half(0x2c),
+            half(0x2e),
+            half(0x30),
+        )
+    };
+    let (Some(phoff), Some(shoff), Some(phentsize), Some(phnum), Some(shentsize), Some(shnum)) =
+        fields
+    else {
+        return Extent::Unknown;
+    };
+
+    // Header fields are untrusted: any offset that overflows makes the
+    // extent unknown rather than wrapping around
+    let entry = |table: u64, index: u64, size: u64| -> Option<usize> {
+        usize::try_from(table.checked_add(index.checked_mul(size)?)?).ok()
+    };
+    let field = |entry: usize, at: usize| word(entry.checked_add(at)?);
+
+    let Some(mut end) = shnum
+        .checked_mul(shentsize)
+        .and_then(|size| shoff.checked_add(size))
+    else {
+        return Extent::Unknown;
+    };
+    for i in 0..phnum {
+        let Some(entry) = entry(phoff, i, phentsize) else {
+            return Extent::Unknown;
+        };
+        let segment = if wide {
+            (field(entry, 8), field(entry, 0x20))
+        } else {
+            (field(entry, 4), field(entry, 0x10))
+        };
+        let (Some(offset), Some(size)) = segment else {
+            return Extent::Unknown;
+        };
+        let Some(segment_end) = offset.checked_add(size) else {
+            return Extent::Unknown;
+        };
+        end = end.max(segment_end);
+    }
+    for i in 0..shnum {
+        let Some(entry) = entry(shoff, i, shentsize) else {
+            return Extent::Unknown;
+        };
+        let section = if wide {
+            (
+                entry
+                    .checked_add(4)
+                    .and_then(|pos| read_uint(data, pos, 4, big)),
+                field(entry, 0x18),
+                field(entry, 0x20),
+            )
+        } else {
+            (
+                entry
+                    .checked_add(4)
+                    .and_then(|pos| read_uint(data, pos, 4, big)),
+                field(entry, 0x10),
+                field(entry, 0x14),
+            )
+        };
+        let (Some(kind), Some(offset), Some(size)) = section else {
+            return Extent::Unknown;
+        };
+        // SHT_NOBITS (.bss) takes no space in the file
+        if kind != 8 {
+            let Some(section_end) = offset.checked_add(size) else {
+                return Extent::Unknown;
+            };
+            end = end.max(section_end);
+        }
+    }
+    usize::try_from(end).map_or(Extent::Unknown, Extent::Known)
+}
+
+/// A PDF ends at its last `%%EOF` marker before the next PDF header
+/// (incremental updates append further markers)
+fn pdf_extent(data: &[u8]) -> Extent {
+    let limit = find(data, b"%PDF-", 1).unwrap_or(data.len());
+    let mut end = None;
+    let mut pos = 0;
+    while let Some(marker) = find(&data[..limit], b"%%EOF", pos) {
+        pos = marker + 5;
+        end = Some(pos);
+    }
+    match end {
+        Some(mut end) => {
+            // Include the line ending after the marker
+            while matches!(data.get(end), Some(b'\r' | b'\n')) && end - pos < 2 {
+                end += 1;
+            }
+            Extent::Known(end)
+        }
+        None => Extent::Unknown,
+    }
+}
+
+fn find(data: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    data.get(from..)?
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| p + from)
+}
+
+fn read_uint(data: &[u8], pos: usize, len: usize, big_endian: bool) -> Option<u64> {
+    let bytes = data.get(pos..pos.checked_add(len)?)?;
+    let fold = |acc: u64, &b: &u8| acc << 8 | b as u64;
+    Some(if big_endian {
+        bytes.iter().fold(0, fold)
+    } else {
+        bytes.iter().rev().fold(0, fold)
+    })
+}
+
+fn le16(data: &[u8], pos: usize) -> Option<u16> {
+    read_uint(data, pos, 2, false).map(|v| v as u16)
+}
+
+fn le32(data: &[u8], pos: usize) -> Option<u32> {
+    read_uint(data, pos, 4, false).map(|v| v as u32)
+}
+
+fn le64(data: &[u8], pos: usize) -> Option<u64> {
+    read_uint(data, pos, 8, false)
+}
+
+fn be16(data: &[u8], pos: usize) -> Option<u16> {
+    read_uint(data, pos, 2, true).map(|v| v as u16)
+}
+
+fn be32(data: &[u8], pos: usize) -> Option<u32> {
+    read_uint(data, pos, 4, true).map(|v| v as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    /// Smallest valid PNG: a 1x1 grayscale image
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\
+        \0\0\0\x0dIHDR\0\0\0\x01\0\0\0\x01\x08\0\0\0\0\x3a\x7e\x9b\x55\
+        \0\0\0\x0aIDAT\x78\x9c\x63\x60\0\0\0\x02\0\x01\xe5\x27\xde\xfc\
+        \0\0\0\0IEND\xae\x42\x60\x82";
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_carve_embedded_files() {
+        let gz = gzip(b"hello hello hello hello");
+        let mut blob = b"firmware header\0\0\0\0".to_vec();
+        blob.extend_from_slice(PNG);
+        blob.extend_from_slice(b"padding");
+        blob.extend_from_slice(&gz);
+        blob.extend_from_slice(&[0xaa; 16]);
+
+        let carved = carve(&blob);
+        assert_eq!(carved.len(), 2);
+        assert_eq!((carved[0].format, carved[0].offset), ("png", 19));
+        assert_eq!(carved[0].length, PNG.len());
+        assert!(carved[0].complete);
+        assert_eq!(carved[1].format, "gzip");
+        assert_eq!(carved[1].offset, 19 + PNG.len() + 7);
+        assert_eq!(carved[1].length, gz.len());
+    }
+
+    #[test]
+    fn test_false_signatures_ignored() {
+        assert!(carve(b"MZ is not a PE header, and \xff\xd8\xff\x00 is not a JPEG").is_empty());
+    }
+
+    #[test]
+    fn test_truncated_file_runs_to_next() {
+        let mut blob = PNG[..40].to_vec();
+        blob.extend_from_slice(PNG);
+
+        let carved = carve(&blob);
+        assert_eq!(carved.len(), 2);
+        assert_eq!(carved[0].length, 40);
+        assert!(!carved[0].complete);
+        assert!(carved[1].complete);
+    }
+
+    #[test]
+    fn test_pdf_and_zip_extent() {
+        let pdf = b"%PDF-1.4\n1 0 obj\nendobj\n%%EOF\nupdate\n%%EOF\n";
+        let mut blob = pdf.to_vec();
+        blob.extend_from_slice(b"trailing");
+        let carved = carve(&blob);
+        assert_eq!(carved[0].length, pdf.len());
+
+        let mut zip = b"PK\x03\x04".to_vec();
+        zip.extend_from_slice(&[0; 26]);
+        zip.extend_from_slice(b"PK\x05\x06");
+        zip.extend_from_slice(&[0; 16]);
+        zip.extend_from_slice(&[3, 0]);
+        zip.extend_from_slice(b"abcdef");
+        let carved = carve(&zip);
+        assert_eq!(carved[0].length, zip.len() - 3);
+    }
+
+    #[test]
+    fn test_hostile_elf_header() {
+        // 64-bit little-endian header with one program header and one
+        // section header at offsets that wrap around
+        let header = |phoff: u64, shoff: u64| {
+            let mut elf = b"\x7fELF\x02\x01\x01".to_vec();
+            elf.resize(0x40, 0);
+            elf[0x20..0x28].copy_from_slice(&phoff.to_le_bytes());
+            elf[0x28..0x30].copy_from_slice(&shoff.to_le_bytes());
+            elf[0x36..0x38].copy_from_slice(&56u16.to_le_bytes());
+            elf[0x38..0x3a].copy_from_slice(&1u16.to_le_bytes());
+            elf[0x3a..0x3c].copy_from_slice(&64u16.to_le_bytes());
+            elf[0x3c..0x3e].copy_from_slice(&1u16.to_le_bytes());
+            elf
+        };
+        for (phoff, shoff) in [(0xffff_ffff_ffff_fff0, 0), (0, 0xffff_ffff_ffff_fff0)] {
+            let elf = header(phoff, shoff);
+            assert!(matches!(elf_extent(&elf), Extent::Unknown));
+            assert!(carve(&elf).iter().all(|c| !c.complete));
+        }
+    }
+}
@@ -0,0 +1,860 @@
+//! Deobfuscation of scripts that wrap encoded payloads
+//!
+//! Droppers and legacy scripts hide their payload behind a decoding call:
+//! `eval(atob("..."))`, `echo ... | base64 -d | sh`,
+//! `[Convert]::FromBase64String(...)`, `String.fromCharCode(72,101,...)`,
+//! `exec(base64.b64decode(...))` or `Chr(72) & Chr(101)`. Each recognised
+//! wrapper is replaced by a string literal holding the decoded text, so the
+//! script can be read without running it. Adjacent literals joined by the
+//! concatenation operator are folded first, which also reassembles payloads
+//! split into pieces.
+
+use crate::encoding::{chain, powershell, EncodingType};
+use crate::metadata::charset;
+use clap::ValueEnum;
+use regex::{Captures, Regex};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// Maximum nesting of scripts decoded out of other scripts
+const MAX_NESTED_DEPTH: usize = 4;
+
+/// A quoted string literal, or a bare word for shell arguments
+///
+/// Only one alternative matches, so the contents are in exactly one of the
+/// `dq`, `sq`, `bq` and `word` groups.
+const LITERAL: &str = r#"(?:[bBrRuU]?"(?P<dq>[^"\n]*)"|[bBrRuU]?'(?P<sq>[^'\n]*)'|`(?P<bq>[^`]*)`|(?P<word>[A-Za-z0-9+/=_-]+))"#;
+
+/// A character code in decimal, C-style hex or VBA hex
+const CHAR_CODE: &str = r"(?i)0x[0-9a-f]+|&h[0-9a-f]+|\d+";
+
+/// Script language, which decides the wrappers looked for and how decoded
+/// literals are written back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ScriptLanguage {
+    /// JavaScript (including HTML-embedded and JScript)
+    #[value(name = "javascript", alias = "js")]
+    JavaScript,
+    /// POSIX shell and Bash
+    #[value(alias = "sh", alias = "bash")]
+    Shell,
+    /// PowerShell
+    #[value(name = "powershell", alias = "ps1")]
+    PowerShell,
+    /// Python
+    #[value(alias = "py")]
+    Python,
+    /// VBA and VBScript
+    #[value(alias = "vbs")]
+    Vba,
+}
+
+impl ScriptLanguage {
+    const ALL: [ScriptLanguage; 5] = [
+        ScriptLanguage::JavaScript,
+        ScriptLanguage::Shell,
+        ScriptLanguage::PowerShell,
+        ScriptLanguage::Python,
+        ScriptLanguage::Vba,
+    ];
+
+    /// Short lowercase name
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptLanguage::JavaScript => "javascript",
+            ScriptLanguage::Shell => "shell",
+            ScriptLanguage::PowerShell => "powershell",
+            ScriptLanguage::Python => "python",
+            ScriptLanguage::Vba => "vba",
+        }
+    }
+
+    /// Language implied by a file name
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "js" | "mjs" | "cjs" | "jse" | "html" | "htm" | "hta" => {
+                Some(ScriptLanguage::JavaScript)
+            }
+            "sh" | "bash" | "zsh" | "ksh" => Some(ScriptLanguage::Shell),
+            "ps1" | "psm1" | "psd1" => Some(ScriptLanguage::PowerShell),
+            "py" | "pyw" => Some(ScriptLanguage::Python),
+            "vba" | "vbs" | "vbe" | "bas" | "cls" | "frm" => Some(ScriptLanguage::Vba),
+            _ => None,
+        }
+    }
+
+    /// Start of a line comment
+    fn comment(&self) -> &'static str {
+        match self {
+            ScriptLanguage::JavaScript => "//",
+            ScriptLanguage::Shell | ScriptLanguage::PowerShell | ScriptLanguage::Python => "#",
+            ScriptLanguage::Vba => "'",
+        }
+    }
+
+    /// Operator joining string literals, if the language has one
+    fn concat_operator(&self) -> Option<char> {
+        match self {
+            ScriptLanguage::JavaScript | ScriptLanguage::PowerShell | ScriptLanguage::Python => {
+                Some('+')
+            }
+            ScriptLanguage::Vba => Some('&'),
+            ScriptLanguage::Shell => None,
+        }
+    }
+
+    /// Write text as a string literal of this language
+    fn quote(&self, text: &str) -> String {
+        match self {
+            ScriptLanguage::JavaScript | ScriptLanguage::Python => {
+                serde_json::to_string(text).unwrap_or_default()
+            }
+            ScriptLanguage::Shell => format!("'{}'", text.replace('\'', r"'\''")),
+            ScriptLanguage::PowerShell => format!("'{}'", text.replace('\'', "''")),
+            ScriptLanguage::Vba => format!(
+                "\"{}\"",
+                text.replace('"', "\"\"")
+                    .replace("\r\n", "\" & vbCrLf & \"")
+                    .replace('\n', "\" & vbLf & \"")
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptLanguage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How a wrapper's payload is decoded
+#[derive(Debug, Clone, Copy)]
+enum Payload {
+    /// A literal in a known encoding, possibly with further layers inside
+    Encoded(EncodingType),
+    /// A sequence of character codes
+    CharCodes,
+}
+
+/// A decoding construct recognised in scripts
+struct Wrapper {
+    /// Name reported in annotations
+    name: &'static str,
+    language: ScriptLanguage,
+    /// Pattern, with `{lit}` standing for the encoded literal
+    pattern: &'static str,
+    payload: Payload,
+    /// Replacement, with `{}` standing for the decoded literal
+    replacement: &'static str,
+}
+
+const WRAPPERS: &[Wrapper] = &[
+    // JavaScript
+    Wrapper {
+        name: "atob",
+        language: ScriptLanguage::JavaScript,
+        pattern: r"\batob\(\s*{lit}\s*\)",
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "Buffer.from",
+        language: ScriptLanguage::JavaScript,
+        pattern: r#"\bBuffer\.from\(\s*{lit}\s*,\s*["']base64["']\s*\)(?:\.toString\(\s*(?:["'][\w-]*["'])?\s*\))?"#,
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "Buffer.from",
+        language: ScriptLanguage::JavaScript,
+        pattern: r#"\bBuffer\.from\(\s*{lit}\s*,\s*["']hex["']\s*\)(?:\.toString\(\s*(?:["'][\w-]*["'])?\s*\))?"#,
+        payload: Payload::Encoded(EncodingType::Hex),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "unescape",
+        language: ScriptLanguage::JavaScript,
+        pattern: r"\b(?:unescape|decodeURIComponent|decodeURI)\(\s*{lit}\s*\)",
+        payload: Payload::Encoded(EncodingType::Url),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "String.fromCharCode",
+        language: ScriptLanguage::JavaScript,
+        pattern: r"\bString\.fromCharCode\(\s*(?:0x[0-9a-fA-F]+|\d+)(?:\s*,\s*(?:0x[0-9a-fA-F]+|\d+))*\s*\)",
+        payload: Payload::CharCodes,
+        replacement: "{}",
+    },
+    // Shell
+    Wrapper {
+        name: "base64 -d",
+        language: ScriptLanguage::Shell,
+        pattern: r#"\b(?:echo(?:\s+-[neE]+)*|printf(?:\s+["']%s["'])?)\s+{lit}\s*\|\s*base64\s+(?:-d|-D|--decode)\b"#,
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "printf '%s' {}",
+    },
+    Wrapper {
+        name: "base64 -d",
+        language: ScriptLanguage::Shell,
+        pattern: r"\bbase64\s+(?:-d|-D|--decode)\s*<<<\s*{lit}",
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "printf '%s' {}",
+    },
+    Wrapper {
+        name: "base32 -d",
+        language: ScriptLanguage::Shell,
+        pattern: r#"\b(?:echo(?:\s+-[neE]+)*|printf(?:\s+["']%s["'])?)\s+{lit}\s*\|\s*base32\s+(?:-d|--decode)\b"#,
+        payload: Payload::Encoded(EncodingType::Base32),
+        replacement: "printf '%s' {}",
+    },
+    Wrapper {
+        name: "xxd -r -p",
+        language: ScriptLanguage::Shell,
+        pattern: r#"\b(?:echo(?:\s+-[neE]+)*|printf(?:\s+["']%s["'])?)\s+{lit}\s*\|\s*xxd\s+(?:-r\s+-p|-p\s+-r|-rp|-pr)\b"#,
+        payload: Payload::Encoded(EncodingType::Hex),
+        replacement: "printf '%s' {}",
+    },
+    // PowerShell
+    Wrapper {
+        name: "FromBase64String",
+        language: ScriptLanguage::PowerShell,
+        pattern: r"(?i)\[(?:System\.)?Text\.Encoding\]::\w+\.GetString\(\s*\[(?:System\.)?Convert\]::FromBase64String\(\s*{lit}\s*\)\s*\)",
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "FromBase64String",
+        language: ScriptLanguage::PowerShell,
+        pattern: r"(?i)\[(?:System\.)?Convert\]::FromBase64String\(\s*{lit}\s*\)",
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "[char]",
+        language: ScriptLanguage::PowerShell,
+        pattern: r"(?i)\[char\]\s*(?:0x[0-9a-f]+|\d+)(?:\s*\+\s*\[char\]\s*(?:0x[0-9a-f]+|\d+))*",
+        payload: Payload::CharCodes,
+        replacement: "{}",
+    },
+    // Python
+    Wrapper {
+        name: "b64decode",
+        language: ScriptLanguage::Python,
+        pattern: r#"\b(?:base64\.)?(?:b64decode|standard_b64decode|urlsafe_b64decode|decodebytes)\(\s*{lit}\s*\)(?:\.decode\(\s*(?:["'][\w-]*["'])?\s*\))?"#,
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "b32decode",
+        language: ScriptLanguage::Python,
+        pattern: r#"\b(?:base64\.)?b32decode\(\s*{lit}\s*\)(?:\.decode\(\s*(?:["'][\w-]*["'])?\s*\))?"#,
+        payload: Payload::Encoded(EncodingType::Base32),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "fromhex",
+        language: ScriptLanguage::Python,
+        pattern: r#"\b(?:bytes\.fromhex|binascii\.unhexlify|binascii\.a2b_hex|(?:base64\.)?b16decode)\(\s*{lit}\s*\)(?:\.decode\(\s*(?:["'][\w-]*["'])?\s*\))?"#,
+        payload: Payload::Encoded(EncodingType::Hex),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "codecs.decode",
+        language: ScriptLanguage::Python,
+        pattern: r#"\bcodecs\.decode\(\s*{lit}\s*,\s*["']hex["']\s*\)"#,
+        payload: Payload::Encoded(EncodingType::Hex),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "codecs.decode",
+        language: ScriptLanguage::Python,
+        pattern: r#"\bcodecs\.decode\(\s*{lit}\s*,\s*["']base64["']\s*\)"#,
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "unquote",
+        language: ScriptLanguage::Python,
+        pattern: r"\b(?:urllib\.parse\.)?unquote(?:_plus)?\(\s*{lit}\s*\)",
+        payload: Payload::Encoded(EncodingType::Url),
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "chr",
+        language: ScriptLanguage::Python,
+        pattern: r"\bchr\(\s*(?:0x[0-9a-fA-F]+|\d+)\s*\)(?:\s*\+\s*chr\(\s*(?:0x[0-9a-fA-F]+|\d+)\s*\))*",
+        payload: Payload::CharCodes,
+        replacement: "{}",
+    },
+    // VBA
+    Wrapper {
+        name: "Chr",
+        language: ScriptLanguage::Vba,
+        pattern: r"(?i)\bChr[WB]?\$?\(\s*(?:&H[0-9a-f]+|\d+)\s*\)(?:\s*&\s*Chr[WB]?\$?\(\s*(?:&H[0-9a-f]+|\d+)\s*\))*",
+        payload: Payload::CharCodes,
+        replacement: "{}",
+    },
+    Wrapper {
+        name: "Base64Decode",
+        language: ScriptLanguage::Vba,
+        pattern: r"(?i)\b(?:Base64Decode|DecodeBase64|B64Decode)\(\s*{lit}\s*\)",
+        payload: Payload::Encoded(EncodingType::Base64),
+        replacement: "{}",
+    },
+];
+
+/// A wrapper that was decoded and replaced
+#[derive(Debug, Clone, Serialize)]
+pub struct Replacement {
+    /// 1-based line of the wrapper in the script
+    pub line: usize,
+    /// Wrapper construct, e.g. `atob` or `concatenation`
+    pub wrapper: &'static str,
+    /// Source text that was replaced
+    pub original: String,
+    /// Decoded text
+    pub decoded: String,
+    /// Encodings peeled off, outermost first
+    pub chain: Vec<EncodingType>,
+    /// Nesting level; replacements inside decoded scripts are at depth 1+
+    pub depth: usize,
+}
+
+/// Result of deobfuscating a script
+#[derive(Debug, Clone, Serialize)]
+pub struct Deobfuscated {
+    /// Language the script was treated as
+    pub language: ScriptLanguage,
+    /// Script with decoded literals substituted
+    pub script: String,
+    /// Replacements made, in source order
+    pub replacements: Vec<Replacement>,
+}
+
+/// Detect the language of a script from its file name, shebang or the
+/// wrappers it contains
+///
+/// Falls back to JavaScript when nothing points elsewhere.
+pub fn detect_language(source: &str, path: Option<&Path>) -> ScriptLanguage {
+    if let Some(language) = path.and_then(ScriptLanguage::from_path) {
+        return language;
+    }
+
+    if let Some(shebang) = source.lines().next().filter(|l| l.starts_with("#!")) {
+        for (needle, language) in [
+            ("python", ScriptLanguage::Python),
+            ("pwsh", ScriptLanguage::PowerShell),
+            ("node", ScriptLanguage::JavaScript),
+            ("sh", ScriptLanguage::Shell),
+        ] {
+            if shebang.contains(needle) {
+                return language;
+            }
+        }
+    }
+
+    let matches = |language: ScriptLanguage| {
+        let command_lines = match language {
+            ScriptLanguage::PowerShell => source
+                .lines()
+                .filter(|line| powershell::extract_encoded_command(line).is_some())
+                .count(),
+            _ => 0,
+        };
+        wrappers()
+            .iter()
+            .filter(|(w, _)| w.language == language)
+            .map(|(_, re)| re.find_iter(source).count())
+            .sum::<usize>()
+            + command_lines
+    };
+    ScriptLanguage::ALL
+        .into_iter()
+        .map(|language| (language, matches(language)))
+        .filter(|&(_, count)| count > 0)
+        .max_by_key(|&(language, count)| (count, std::cmp::Reverse(language as usize)))
+        .map_or(ScriptLanguage::JavaScript, |(language, _)| language)
+}
+
+/// Replace encoded payload wrappers in a script by their decoded text
+///
+/// Decoded text that is itself a script is deobfuscated in turn. With
+/// `annotate`, a comment naming the wrapper and encodings is appended to
+/// every changed line.
+pub fn deobfuscate(source: &str, language: ScriptLanguage, annotate: bool) -> Deobfuscated {
+    let (script, replacements) = deobfuscate_at(source, language, annotate, 0);
+    Deobfuscated {
+        language,
+        script,
+        replacements,
+    }
+}
+
+/// A decoded wrapper, before overlaps are resolved
+struct Candidate {
+    start: usize,
+    end: usize,
+    wrapper: &'static str,
+    /// Replacement template, with `{}` standing for the decoded literal
+    replacement: &'static str,
+    text: String,
+    chain: Vec<EncodingType>,
+}
+
+/// A pending edit of the source
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+    note: String,
+}
+
+fn deobfuscate_at(
+    source: &str,
+    language: ScriptLanguage,
+    annotate: bool,
+    depth: usize,
+) -> (String, Vec<Replacement>) {
+    let (source, mut replacements) = fold_concatenations(source, language, depth);
+    let mut candidates = Vec::new();
+
+    for (wrapper, re) in wrappers().iter().filter(|(w, _)| w.language == language) {
+        for caps in re.captures_iter(&source) {
+            let whole = caps.get(0).expect("match has group 0");
+            let decoded = match wrapper.payload {
+                Payload::Encoded(encoding) => literal(&caps).and_then(|l| decode(l, encoding)),
+                Payload::CharCodes => from_char_codes(whole.as_str()).map(|text| (text, vec![])),
+            };
+            if let Some((text, chain)) = decoded {
+                candidates.push(Candidate {
+                    start: whole.start(),
+                    end: whole.end(),
+                    wrapper: wrapper.name,
+                    replacement: wrapper.replacement,
+                    text,
+                    chain,
+                });
+            }
+        }
+    }
+
+    // PowerShell command lines carry an encoded script as an argument
+    if matches!(language, ScriptLanguage::Shell | ScriptLanguage::PowerShell) {
+        let mut offset = 0;
+        for line in source.split_inclusive('\n') {
+            if let Some(argument) = powershell::extract_encoded_command(line) {
+                let start = offset + (argument.as_ptr() as usize - line.as_ptr() as usize);
+                if let Some((text, chain)) = decode(argument, EncodingType::Base64) {
+                    candidates.push(Candidate {
+                        start,
+                        end: start + argument.len(),
+                        wrapper: "-EncodedCommand",
+                        replacement: "{}",
+                        text,
+                        chain,
+                    });
+                }
+            }
+            offset += line.len();
+        }
+    }
+
+    // Outer constructs win over the calls nested inside them
+    candidates.sort_by_key(|c| (c.start, std::cmp::Reverse(c.end)));
+    let mut edits: Vec<Edit> = Vec::new();
+    for candidate in candidates {
+        if edits.last().is_some_and(|last| candidate.start < last.end) {
+            continue;
+        }
+
+        let line = line_of(&source, candidate.start);
+        let inner = if depth < MAX_NESTED_DEPTH {
+            let (inner, nested) = deobfuscate_at(&candidate.text, language, false, depth + 1);
+            replacements.extend(nested.into_iter().map(|r| Replacement { line, ..r }));
+            inner
+        } else {
+            candidate.text.clone()
+        };
+
+        edits.push(Edit {
+            start: candidate.start,
+            end: candidate.end,
+            text: candidate.replacement.replace("{}", &language.quote(&inner)),
+            note: describe(candidate.wrapper, &candidate.chain),
+        });
+        replacements.push(Replacement {
+            line,
+            wrapper: candidate.wrapper,
+            original: source[candidate.start..candidate.end].to_string(),
+            decoded: candidate.text,
+            chain: candidate.chain,
+            depth,
+        });
+    }
+
+    replacements.sort_by_key(|r| (r.depth, r.line));
+    (
+        apply_edits(&source, &edits, language, annotate),
+        replacements,
+    )
+}
+
+/// Join adjacent string literals separated only by the concatenation
+/// operator
+fn fold_concatenations(
+    source: &str,
+    language: ScriptLanguage,
+    depth: usize,
+) -> (String, Vec<Replacement>) {
+    let Some(operator) = language.concat_operator() else {
+        return (source.to_string(), vec![]);
+    };
+    let literals = string_literals(source, language);
+
+    let mut edits = Vec::new();
+    let mut replacements = Vec::new();
+    let mut i = 0;
+    while i < literals.len() {
+        let quote = &source[literals[i].0..literals[i].0 + 1];
+        let mut j = i;
+        while j + 1 < literals.len()
+            && source[literals[j + 1].0..].starts_with(quote)
+            && source[literals[j].1..literals[j + 1].0].trim() == operator.to_string()
+        {
+            j += 1;
+        }
+
+        if j > i {
+            let (start, end) = (literals[i].0, literals[j].1);
+            let joined: String = literals[i..=j]
+                .iter()
+                .map(|&(start, end)| &source[start + 1..end - 1])
+                .collect();
+            replacements.push(Replacement {
+                line: line_of(source, start),
+                wrapper: "concatenation",
+                original: source[start..end].to_string(),
+                decoded: joined.clone(),
+                chain: vec![],
+                depth,
+            });
+            edits.push(Edit {
+                start,
+                end,
+                text: format!("{}{}{}", quote, joined, quote),
+                note: String::new(),
+            });
+        }
+        i = j + 1;
+    }
+
+    (apply_edits(source, &edits, language, false), replacements)
+}
+
+/// Spans of the string literals in source, in order, skipping comments
+///
+/// Escapes follow the language: backslashes in JavaScript, Python and
+/// double-quoted shell strings, backticks and doubled quotes in PowerShell,
+/// doubled quotes in VBA. JavaScript template literals are skipped whole.
+fn string_literals(source: &str, language: ScriptLanguage) -> Vec<(usize, usize)> {
+    use ScriptLanguage::{JavaScript, PowerShell, Python, Shell, Vba};
+
+    let bytes = source.as_bytes();
+    let skip_to = |from: usize, end: &str| {
+        source[from..]
+            .find(end)
+            .map_or(source.len(), |offset| from + offset + end.len())
+    };
+    let mut spans = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if rest.starts_with(language.comment().as_bytes()) {
+            i = skip_to(i, "\n");
+            continue;
+        }
+        if language == JavaScript && rest.starts_with(b"/*") {
+            i = skip_to(i + 2, "*/");
+            continue;
+        }
+        if language == PowerShell && rest.starts_with(b"<#") {
+            i = skip_to(i + 2, "#>");
+            continue;
+        }
+
+        let quote = bytes[i];
+        let is_template = language == JavaScript && quote == b'`';
+        let opens = match language {
+            Vba => quote == b'"',
+            _ => quote == b'"' || quote == b'\'',
+        };
+        if !opens && !is_template {
+            i += 1;
+            continue;
+        }
+
+        let backslash =
+            matches!(language, JavaScript | Python) || (language == Shell && quote == b'"');
+        let mut j = i + 1;
+        let mut end = None;
+        while j < bytes.len() {
+            let b = bytes[j];
+            if b == b'\n' && !is_template {
+                break;
+            }
+            if (b == b'\\' && backslash) || (b == b'`' && language == PowerShell && quote == b'"') {
+                j += 2;
+                continue;
+            }
+            if b == quote {
+                if matches!(language, PowerShell | Vba) && bytes.get(j + 1) == Some(&quote) {
+                    j += 2;
+                    continue;
+                }
+                end = Some(j + 1);
+                break;
+            }
+            j += 1;
+        }
+        match end {
+            Some(end) => {
+                if !is_template {
+                    spans.push((i, end));
+                }
+                i = end;
+            }
+            // An unterminated literal ends at the line
+            None => i = j.max(i + 1),
+        }
+    }
+    spans
+}
+
+/// Apply non-overlapping edits in order, appending their notes as a comment
+/// at the end of each changed line
+fn apply_edits(source: &str, edits: &[Edit], language: ScriptLanguage, annotate: bool) -> String {
+    let comment =
+        |notes: &[&str]| format!("  {} decodeck: {}", language.comment(), notes.join("; "));
+    let mut output = String::with_capacity(source.len());
+    let mut notes: Vec<&str> = Vec::new();
+    let mut pos = 0;
+    let mut edits = edits.iter().peekable();
+
+    loop {
+        let next_start = edits.peek().map_or(source.len(), |e| e.start);
+
+        // Close the line of earlier edits before moving past it
+        if !notes.is_empty() {
+            if let Some(newline) = source[pos..next_start].find('\n') {
+                let eol = pos + newline;
+                let eol = eol - usize::from(source[..eol].ends_with('\r'));
+                output.push_str(&source[pos..eol]);
+                output.push_str(&comment(&notes));
+                notes.clear();
+                pos = eol;
+                continue;
+            }
+        }
+
+        let Some(edit) = edits.next() else {
+            output.push_str(&source[pos..]);
+            if !notes.is_empty() {
+                output.push_str(&comment(&notes));
+            }
+            return output;
+        };
+        output.push_str(&source[pos..edit.start]);
+        output.push_str(&edit.text);
+        if annotate {
+            notes.push(&edit.note);
+        }
+        pos = edit.end;
+    }
+}
+
+/// Decode a literal whose outer encoding is known, peeling inner layers
+fn decode(literal: &str, encoding: EncodingType) -> Option<(String, Vec<EncodingType>)> {
+    let result = chain::decode_chain_as(literal, encoding, None).ok()?;
+    let (_, text) = charset::decode_text(&result.data)?;
+    let chain = result.chain.iter().map(|info| info.encoding_type).collect();
+    Some((text, chain))
+}
+
+/// Turn a run of character code expressions into text
+fn from_char_codes(expression: &str) -> Option<String> {
+    static CODE: OnceLock<Regex> = OnceLock::new();
+    let code = CODE.get_or_init(|| Regex::new(CHAR_CODE).unwrap());
+
+    code.find_iter(expression)
+        .map(|m| {
+            let digits = m.as_str().to_lowercase();
+            let value = match digits.strip_prefix("0x").or(digits.strip_prefix("&h")) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => digits.parse().ok()?,
+            };
+            char::from_u32(value)
+        })
+        .collect()
+}
+
+/// Contents of the literal captured by a wrapper pattern
+fn literal<'a>(caps: &Captures<'a>) -> Option<&'a str> {
+    ["dq", "sq", "bq", "word"]
+        .into_iter()
+        .find_map(|name| caps.name(name))
+        .map(|m| m.as_str())
+        .filter(|s| !s.is_empty())
+}
+
+fn describe(wrapper: &str, chain: &[EncodingType]) -> String {
+    if chain.is_empty() {
+        return wrapper.to_string();
+    }
+    let names: Vec<_> = chain.iter().map(|e| e.display_name()).collect();
+    format!("{} ({})", wrapper, names.join(" → "))
+}
+
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
+}
+
+fn wrappers() -> &'static [(&'static Wrapper, Regex)] {
+    static COMPILED: OnceLock<Vec<(&'static Wrapper, Regex)>> = OnceLock::new();
+    COMPILED.get_or_init(|| {
+        WRAPPERS
+            .iter()
+            .map(|w| {
+                let pattern = w.pattern.replace("{lit}", LITERAL);
+                (w, Regex::new(&pattern).expect("wrapper pattern is valid"))
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_javascript_atob() {
+        let result = deobfuscate(
+            "var x = 1;\neval(atob(\"YWxlcnQoMSk=\"));\n",
+            ScriptLanguage::JavaScript,
+            true,
+        );
+        assert_eq!(
+            result.script,
+            "var x = 1;\neval(\"alert(1)\");  // decodeck: atob (base64)\n"
+        );
+        assert_eq!(result.replacements[0].line, 2);
+    }
+
+    #[test]
+    fn test_javascript_concatenation_and_char_codes() {
+        let result = deobfuscate(
+            "eval(atob('YWxl' + 'cnQoMSk=')); x = String.fromCharCode(72, 0x69);",
+            ScriptLanguage::JavaScript,
+            false,
+        );
+        assert_eq!(result.script, "eval(\"alert(1)\"); x = \"Hi\";");
+        assert_eq!(result.replacements[0].wrapper, "concatenation");
+    }
+
+    #[test]
+    fn test_concatenation_skips_comments_and_escapes() {
+        let result = deobfuscate(
+            "var s = \"a\" + \"b\"; // see \"c\" + \"d\"\n/* \"e\" + \"f\" */ t = \"g\\\"\" + \"h\";",
+            ScriptLanguage::JavaScript,
+            false,
+        );
+        assert_eq!(
+            result.script,
+            "var s = \"ab\"; // see \"c\" + \"d\"\n/* \"e\" + \"f\" */ t = \"g\\\"h\";"
+        );
+
+        let result = deobfuscate(
+            "$a = 'it''s' + ' ok' # 'x' + 'y'",
+            ScriptLanguage::PowerShell,
+            false,
+        );
+        assert_eq!(result.script, "$a = 'it''s ok' # 'x' + 'y'");
+    }
+
+    #[test]
+    fn test_shell_pipe() {
+        let result = deobfuscate(
+            "#!/bin/sh\necho ZWNobyBoaQ== | base64 -d | sh\n",
+            ScriptLanguage::Shell,
+            true,
+        );
+        assert_eq!(
+            result.script,
+            "#!/bin/sh\nprintf '%s' 'echo hi' | sh  # decodeck: base64 -d (base64)\n"
+        );
+    }
+
+    #[test]
+    fn test_powershell_nested() {
+        // Outer Base64 decodes to a script that calls FromBase64String again
+        let inner = "[Convert]::FromBase64String('SGk=')";
+        let outer =
+            crate::encoding::encode::encode(inner.as_bytes(), EncodingType::Base64).unwrap();
+        let source = format!(
+            "IEX ([Text.Encoding]::UTF8.GetString([System.Convert]::FromBase64String(\"{}\")))",
+            outer
+        );
+        let result = deobfuscate(&source, ScriptLanguage::PowerShell, false);
+        assert_eq!(result.script, "IEX ('''Hi''')");
+        assert_eq!(result.replacements.len(), 2);
+        assert_eq!(result.replacements[1].depth, 1);
+    }
+
+    #[test]
+    fn test_python_and_vba() {
+        let python = deobfuscate(
+            "exec(base64.b64decode(b'cHJpbnQoMSk=').decode())\nprint(chr(72)+chr(105))",
+            ScriptLanguage::Python,
+            false,
+        );
+        assert_eq!(python.script, "exec(\"print(1)\")\nprint(\"Hi\")");
+
+        let vba = deobfuscate(
+            "Shell Chr(99) & Chr(&H6D) & Chr(100) ' run\nx = \"a\" & \"b\"",
+            ScriptLanguage::Vba,
+            false,
+        );
+        assert_eq!(vba.script, "Shell \"cmd\" ' run\nx = \"ab\"");
+    }
+
+    #[test]
+    fn test_detect_language() {
+        assert_eq!(
+            detect_language("x = 1", Some(Path::new("a.ps1"))),
+            ScriptLanguage::PowerShell
+        );
+        assert_eq!(
+            detect_language("#!/usr/bin/env python3\nprint(1)", None),
+            ScriptLanguage::Python
+        );
+        assert_eq!(
+            detect_language("echo SGk= | base64 -d", None),
+            ScriptLanguage::Shell
+        );
+        assert_eq!(
+            detect_language("pwsh -enc SGk=", None),
+            ScriptLanguage::PowerShell
+        );
+        assert_eq!(detect_language("plain", None), ScriptLanguage::JavaScript);
+    }
+
+    #[test]
+    fn test_leaves_unknown_literals() {
+        let source = "eval(atob(\"!!!\"))";
+        let result = deobfuscate(source, ScriptLanguage::JavaScript, true);
+        assert_eq!(result.script, source);
+        assert!(result.replacements.is_empty());
+    }
+}
@@ -0,0 +1,453 @@
+//! Digests and fingerprints of decoded content
+//!
+//! Cryptographic digests identify exact content for threat intelligence
+//! lookups. The ssdeep fuzzy hash matches similar content, and the imphash
+//! groups PE files that import the same functions.
+
+use crate::error::DecodeckError;
+use crate::metadata::executable;
+use crate::util::hex;
+use clap::ValueEnum;
+use md5::Md5;
+use serde::Serialize;
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Hash that can be computed over decoded content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HashAlgorithm {
+    Md5,
+    Sha1,
+    Sha256,
+    Sha512,
+    Blake3,
+    /// Context-triggered piecewise hash for similarity matching
+    Ssdeep,
+    /// MD5 of the import table of a PE file
+    Imphash,
+}
+
+impl HashAlgorithm {
+    /// Name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Sha1 => "SHA-1",
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Sha512 => "SHA-512",
+            HashAlgorithm::Blake3 => "BLAKE3",
+            HashAlgorithm::Ssdeep => "ssdeep",
+            HashAlgorithm::Imphash => "imphash",
+        }
+    }
+}
+
+/// Hashes of decoded content; only the requested ones are set
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Hashes {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub md5: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sha512: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blake3: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ssdeep: Option<String>,
+    /// Absent for content that is not a PE file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub imphash: Option<String>,
+}
+
+impl Hashes {
+    /// Compute the given hashes of data
+    pub fn compute(data: &[u8], algorithms: &[HashAlgorithm]) -> Self {
+        let mut hashes = Self::default();
+        for algorithm in algorithms {
+            let value = compute(data, *algorithm);
+            *hashes.slot(*algorithm) = value;
+        }
+        hashes
+    }
+
+    /// Whether no hash was computed
+    pub fn is_empty(&self) -> bool {
+        self.entries().next().is_none()
+    }
+
+    /// Computed hashes with their algorithms, in a fixed order
+    pub fn entries(&self) -> impl Iterator<Item = (HashAlgorithm, &str)> {
+        [
+            (HashAlgorithm::Md5, &self.md5),
+            (HashAlgorithm::Sha1, &self.sha1),
+            (HashAlgorithm::Sha256, &self.sha256),
+            (HashAlgorithm::Sha512, &self.sha512),
+            (HashAlgorithm::Blake3, &self.blake3),
+            (HashAlgorithm::Ssdeep, &self.ssdeep),
+            (HashAlgorithm::Imphash, &self.imphash),
+        ]
+        .into_iter()
+        .filter_map(|(algorithm, value)| Some((algorithm, value.as_deref()?)))
+    }
+
+    fn slot(&mut self, algorithm: HashAlgorithm) -> &mut Option<String> {
+        match algorithm {
+            HashAlgorithm::Md5 => &mut self.md5,
+            HashAlgorithm::Sha1 => &mut self.sha1,
+            HashAlgorithm::Sha256 => &mut self.sha256,
+            HashAlgorithm::Sha512 => &mut self.sha512,
+            HashAlgorithm::Blake3 => &mut self.blake3,
+            HashAlgorithm::Ssdeep => &mut self.ssdeep,
+            HashAlgorithm::Imphash => &mut self.imphash,
+        }
+    }
+}
+
+/// One hash of data, or `None` if it does not apply to the content
+pub fn compute(data: &[u8], algorithm: HashAlgorithm) -> Option<String> {
+    match algorithm {
+        HashAlgorithm::Md5 => Some(hex(&Md5::digest(data))),
+        HashAlgorithm::Sha1 => Some(hex(&Sha1::digest(data))),
+        HashAlgorithm::Sha256 => Some(hex(&Sha256::digest(data))),
+        HashAlgorithm::Sha512 => Some(hex(&Sha512::digest(data))),
+        HashAlgorithm::Blake3 => Some(blake3::hash(data).to_hex().to_string()),
+        HashAlgorithm::Ssdeep => Some(ssdeep(data)),
+        HashAlgorithm::Imphash => imphash(data),
+    }
+}
+
+/// Check data against an expected hex digest
+///
+/// The digest may name its algorithm (`sha1:…`); otherwise the algorithm is
+/// inferred from its length, with 64 digits matching SHA-256 or BLAKE3.
+/// Returns the algorithm that matched.
+pub fn verify(data: &[u8], expected: &str) -> Result<HashAlgorithm, DecodeckError> {
+    let invalid = |reason: &str| DecodeckError::InvalidHash {
+        hash: expected.to_string(),
+        reason: reason.to_string(),
+    };
+    let (candidates, digest) = match expected.split_once(':') {
+        Some((name, digest)) => {
+            let algorithm = HashAlgorithm::from_str(name.trim(), true)
+                .map_err(|_| invalid("unknown algorithm"))?;
+            if algorithm == HashAlgorithm::Ssdeep {
+                return Err(invalid("fuzzy hashes cannot be verified exactly"));
+            }
+            (vec![algorithm], digest.trim())
+        }
+        None => {
+            let digest = expected.trim();
+            let candidates = match digest.len() {
+                32 => vec![HashAlgorithm::Md5],
+                40 => vec![HashAlgorithm::Sha1],
+                64 => vec![HashAlgorithm::Sha256, HashAlgorithm::Blake3],
+                128 => vec![HashAlgorithm::Sha512],
+                _ => return Err(invalid("length does not match a known digest")),
+            };
+            (candidates, digest)
+        }
+    };
+    if digest.is_empty() || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(invalid("not a hex digest"));
+    }
+
+    let mut actual = None;
+    for algorithm in &candidates {
+        let value = compute(data, *algorithm);
+        if value
+            .as_deref()
+            .is_some_and(|v| v.eq_ignore_ascii_case(digest))
+        {
+            return Ok(*algorithm);
+        }
+        actual.get_or_insert((*algorithm, value));
+    }
+    let (algorithm, actual) = actual.expect("at least one candidate");
+    Err(DecodeckError::HashMismatch {
+        algorithm: algorithm.name().to_string(),
+        expected: digest.to_lowercase(),
+        actual: actual.unwrap_or_else(|| "none".to_string()),
+    })
+}
+
+/// Import hash of a PE file, as computed by `pefile`
+///
+/// Libraries are lower-cased without their `.dll`, `.ocx` or `.sys`
+/// extension and joined with their function names. Functions imported by
+/// ordinal are written `ord<N>`; `pefile` resolves a few well-known
+/// ordinals of `ws2_32` and `oleaut32` to names instead.
+pub fn imphash(data: &[u8]) -> Option<String> {
+    let imports = executable::pe_imports(data)?;
+    if imports.is_empty() {
+        return None;
+    }
+    let names: Vec<String> = imports
+        .iter()
+        .map(|(library, function)| {
+            let library = library.to_lowercase();
+            let library = match library.rsplit_once('.') {
+                Some((stem, "dll" | "ocx" | "sys")) => stem,
+                _ => &library,
+            };
+            format!("{}.{}", library, function.to_lowercase())
+        })
+        .collect();
+    Some(hex(&Md5::digest(names.join(","))))
+}
+
+/// Bytes in the rolling hash window
+const ROLLING_WINDOW: usize = 7;
+
+/// Smallest ssdeep block size
+const MIN_BLOCK_SIZE: u32 = 3;
+
+/// Characters in a full ssdeep digest
+const SPAMSUM_LENGTH: usize = 64;
+
+/// Initial value of the per-piece hash
+const HASH_INIT: u32 = 0x2802_1967;
+
+const B64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// ssdeep fuzzy hash, `blocksize:digest:double-blocksize-digest`
+///
+/// Output is compatible with ssdeep 2.13 and later, so values can be
+/// compared with `ssdeep -m` or other spamsum implementations.
+pub fn ssdeep(data: &[u8]) -> String {
+    let mut block_size = MIN_BLOCK_SIZE;
+    while (block_size as usize) * SPAMSUM_LENGTH < data.len() {
+        block_size *= 2;
+    }
+    loop {
+        let (first, second, roll) = piecewise(data, block_size);
+        if block_size > MIN_BLOCK_SIZE && first.chars.len() < SPAMSUM_LENGTH / 2 {
+            block_size /= 2;
+            continue;
+        }
+
+        let mut digest = String::new();
+        let end = first.chars.len().min(SPAMSUM_LENGTH - 1);
+        digest.extend(first.chars[..end].iter().map(|&c| char::from(c)));
+        if roll != 0 {
+            digest.push(char::from(B64[(first.h % 64) as usize]));
+        } else if let Some(&c) = first.chars.get(end) {
+            digest.push(char::from(c));
+        }
+        digest.push(':');
+        // The second digest is cut short, its last character covering the rest
+        let end = second.chars.len().min(SPAMSUM_LENGTH / 2 - 1);
+        digest.extend(second.chars[..end].iter().map(|&c| char::from(c)));
+        if roll != 0 {
+            digest.push(char::from(B64[(second.half % 64) as usize]));
+        } else if let Some(c) = second.half_char {
+            digest.push(char::from(c));
+        }
+        return format!("{}:{}", block_size, digest);
+    }
+}
+
+/// Digest characters for one block size
+struct Piecewise {
+    chars: Vec<u8>,
+    /// Hash of the current piece
+    h: u32,
+    /// Hash since the digest reached half its length
+    half: u32,
+    /// Character for `half` at the last piece end, once the digest reached
+    /// half its length
+    half_char: Option<u8>,
+}
+
+impl Piecewise {
+    fn new() -> Self {
+        Self {
+            chars: Vec::new(),
+            h: HASH_INIT,
+            half: HASH_INIT,
+            half_char: None,
+        }
+    }
+
+    fn update(&mut self, byte: u8) {
+        self.h = self.h.wrapping_mul(0x0100_0193) ^ u32::from(byte);
+        self.half = self.half.wrapping_mul(0x0100_0193) ^ u32::from(byte);
+    }
+
+    /// End the current piece; once the digest is full, its last character
+    /// keeps being replaced
+    fn emit(&mut self) {
+        let c = B64[(self.h % 64) as usize];
+        self.half_char = Some(B64[(self.half % 64) as usize]);
+        if self.chars.len() < SPAMSUM_LENGTH - 1 {
+            self.chars.push(c);
+            self.h = HASH_INIT;
+            if self.chars.len() < SPAMSUM_LENGTH / 2 {
+                self.half = HASH_INIT;
+                self.half_char = None;
+            }
+        } else {
+            self.chars.truncate(SPAMSUM_LENGTH - 1);
+            self.chars.push(c);
+        }
+    }
+}
+
+/// Digests at a block size and at twice it, and the final rolling hash
+fn piecewise(data: &[u8], block_size: u32) -> (Piecewise, Piecewise, u32) {
+    let mut first = Piecewise::new();
+    let mut second = Piecewise::new();
+    let mut window = [0u8; ROLLING_WINDOW];
+    let (mut h1, mut h2, mut h3) = (0u32, 0u32, 0u32);
+
+    for (n, &byte) in data.iter().enumerate() {
+        let c = u32::from(byte);
+        h2 = h2.wrapping_sub(h1).wrapping_add(ROLLING_WINDOW as u32 * c);
+        h1 = h1
+            .wrapping_add(c)
+            .wrapping_sub(u32::from(window[n % ROLLING_WINDOW]));
+        window[n % ROLLING_WINDOW] = byte;
+        h3 = (h3 << 5) ^ c;
+        let roll = h1.wrapping_add(h2).wrapping_add(h3);
+
+        first.update(byte);
+        second.update(byte);
+        if roll % block_size == block_size - 1 {
+            first.emit();
+        }
+        if roll % (2 * block_size) == 2 * block_size - 1 {
+            second.emit();
+        }
+    }
+    (first, second, h1.wrapping_add(h2).wrapping_add(h3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_digests() {
+        let hashes = Hashes::compute(
+            b"abc",
+            &[
+                HashAlgorithm::Md5,
+                HashAlgorithm::Sha1,
+                HashAlgorithm::Sha256,
+                HashAlgorithm::Blake3,
+            ],
+        );
+        assert_eq!(
+            hashes.md5.as_deref(),
+            Some("900150983cd24fb0d6963f7d28e17f72")
+        );
+        assert_eq!(
+            hashes.sha1.as_deref(),
+            Some("a9993e364706816aba3e25717850c26c9cd0d89d")
+        );
+        assert_eq!(
+            hashes.sha256.as_deref(),
+            Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+        );
+        assert_eq!(
+            hashes.blake3.as_deref(),
+            Some("6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85")
+        );
+        assert!(hashes.sha512.is_none());
+        assert_eq!(hashes.entries().count(), 4);
+        assert!(Hashes::default().is_empty());
+    }
+
+    #[test]
+    fn test_ssdeep() {
+        assert_eq!(ssdeep(b""), "3::");
+        let text: Vec<u8> = (0..4096u32)
+            .flat_map(|i| format!("line {} of the sample\n", i * 7919 % 1000).into_bytes())
+            .collect();
+        let hash = ssdeep(&text);
+        let (block_size, rest) = hash.split_once(':').unwrap();
+        let (first, second) = rest.split_once(':').unwrap();
+        assert!(block_size.parse::<u32>().unwrap() * 64 >= text.len() as u32 / 2);
+        assert!((32..=64).contains(&first.len()));
+        assert!(second.len() <= 32);
+
+        // Similar content keeps the block size and shares runs of its digest
+        let mut edited = text.clone();
+        edited[100..110].copy_from_slice(b"0123456789");
+        let other = ssdeep(&edited);
+        assert_ne!(other, hash);
+        assert_eq!(other.split(':').next(), Some(block_size));
+        let other_first = other.split(':').nth(1).unwrap();
+        assert!(first
+            .as_bytes()
+            .windows(7)
+            .any(|w| other_first.as_bytes().windows(7).any(|o| o == w)));
+    }
+
+    #[test]
+    fn test_ssdeep_known_answers() {
+        // Reference values from the fuzzyhash crate, a port of ssdeep
+        let fox = b"The quick brown fox jumps over the lazy dog\n".repeat(100);
+        let lines: Vec<u8> = (0..4096u32)
+            .flat_map(|i| format!("line {} of the sample\n", i * 7919 % 1000).into_bytes())
+            .collect();
+        let bytes: Vec<u8> = (0..20000u32)
+            .map(|i| (i.wrapping_mul(2_654_435_761) >> 13) as u8)
+            .collect();
+        let padded = |data: &[u8], zeros: usize| [data, &vec![0; zeros]].concat();
+
+        assert_eq!(
+            ssdeep(&fox),
+            "6:FHIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIA:FV"
+        );
+        assert_eq!(
+            ssdeep(&padded(&fox, 16)),
+            "6:FHIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIGCIA:Fdl"
+        );
+        assert_eq!(
+            ssdeep(&lines),
+            "1536:MVxalI9be2xNTMGzVxalI9be2xNTMGzVxalI9be2xNTMGzVxalI9be2xNTMGzb:\
+             QxalI9a2xNTjZxalI9a2xNTjZxalI9aG"
+        );
+        // The final rolling hash is 0, so the last character of the second
+        // digest is the one for the hash since it reached half its length
+        assert_eq!(
+            ssdeep(&padded(&lines, 16)),
+            "1536:MVxalI9be2xNTMGzVxalI9be2xNTMGzVxalI9be2xNTMGzVxalI9be2xNTMGz:\
+             QxalI9a2xNTjZxalI9a2xNTjZxalI9aq"
+        );
+        assert_eq!(
+            ssdeep(&bytes),
+            "24:IxzxpxlDlZNn3hv3Zhbp/17DlX759hplbFS9PdvUbtxhZh/:IxzxpPFt3Z3/ZpdkrcxxhZx"
+        );
+        assert_eq!(
+            ssdeep(&padded(&bytes, 64)),
+            "24:IxzxpxlDlZNn3hv3Zhbp/17DlX759hplbFS9PdvUbtxhZh:IxzxpPFt3Z3/ZpdkrcxxhZ"
+        );
+    }
+
+    #[test]
+    fn test_verify() {
+        let md5 = "900150983CD24FB0D6963F7D28E17F72";
+        assert_eq!(verify(b"abc", md5).unwrap(), HashAlgorithm::Md5);
+        let blake3 = "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85";
+        assert_eq!(verify(b"abc", blake3).unwrap(), HashAlgorithm::Blake3);
+        let sha1 = "sha1:a9993e364706816aba3e25717850c26c9cd0d89d";
+        assert_eq!(verify(b"abc", sha1).unwrap(), HashAlgorithm::Sha1);
+
+        assert!(matches!(
+            verify(b"abd", md5),
+            Err(DecodeckError::HashMismatch { .. })
+        ));
+        assert!(matches!(
+            verify(b"abc", "abc123"),
+            Err(DecodeckError::InvalidHash { .. })
+        ));
+        assert!(matches!(
+            verify(b"abc", "ssdeep:3:abc:def"),
+            Err(DecodeckError::InvalidHash { .. })
+        ));
+    }
+}
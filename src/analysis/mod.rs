@@ -0,0 +1,13 @@
+//! Analysis of decoded content
+//!
+//! Helpers that look inside decoded payloads rather than at how they were
+//! encoded.
+
+pub mod archive;
+pub mod carve;
+pub mod deobfuscate;
+pub mod hashes;
+pub mod iocs;
+pub mod stats;
+pub mod strings;
+pub mod xor;
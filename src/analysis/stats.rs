@@ -0,0 +1,290 @@
+//! Entropy and byte statistics
+//!
+//! Tells at a glance what decoded bytes are: text has a skewed distribution
+//! of printable bytes, compressed or encrypted data is close to uniform, and
+//! structured binary (executables, images, serialized data) sits in between.
+
+use serde::Serialize;
+
+/// Default size of the windows for windowed entropy
+pub const DEFAULT_WINDOW: usize = 1024;
+
+/// Share of printable bytes at or above which data is taken to be text
+const TEXT_PRINTABLE: f64 = 0.95;
+
+/// Entropy, as a share of the maximum for the data size, above which data
+/// is taken to be compressed or encrypted
+const RANDOM_ENTROPY: f64 = 0.9;
+
+/// Inputs with at least this many windows get an entropy graph
+const GRAPH_MIN_WINDOWS: usize = 4;
+
+/// Width of the entropy graph in columns
+const GRAPH_WIDTH: usize = 64;
+
+/// What the byte distribution suggests the data is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Classification {
+    /// No data
+    Empty,
+    /// Mostly printable ASCII and whitespace
+    Text,
+    /// Near-uniform distribution
+    Random,
+    /// Binary with a skewed distribution
+    Structured,
+}
+
+impl Classification {
+    /// Human-readable hint
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Classification::Empty => "empty",
+            Classification::Text => "likely text",
+            Classification::Random => "likely encrypted/compressed",
+            Classification::Structured => "structured binary",
+        }
+    }
+}
+
+/// Byte statistics of a buffer
+#[derive(Debug, Clone, Serialize)]
+pub struct ByteStats {
+    /// Number of bytes
+    pub size: usize,
+    /// Shannon entropy in bits per byte (0 to 8)
+    pub entropy: f64,
+    /// Size of the windows in `windowed_entropy`
+    pub window_size: usize,
+    /// Entropy of consecutive windows; a trailing partial window is ignored
+    pub windowed_entropy: Vec<f64>,
+    /// Count of each byte value
+    pub histogram: Vec<usize>,
+    /// Share of printable ASCII and whitespace bytes
+    pub printable_ratio: f64,
+    /// Chi-square statistic against a uniform distribution (about 255 for
+    /// random data, 255 degrees of freedom)
+    pub chi_square: f64,
+    /// Length of the longest run of printable bytes
+    pub longest_printable_run: usize,
+    pub classification: Classification,
+}
+
+impl ByteStats {
+    /// Compute statistics with windows of `window_size` bytes
+    pub fn compute(data: &[u8], window_size: usize) -> Self {
+        let window_size = window_size.max(1);
+        let counts = histogram(data);
+        let overall = entropy_of(&counts, data.len());
+        let windowed_entropy = data.chunks_exact(window_size).map(entropy).collect();
+
+        let expected = data.len() as f64 / 256.0;
+        let chi_square = if data.is_empty() {
+            0.0
+        } else {
+            counts
+                .iter()
+                .map(|&n| (n as f64 - expected).powi(2) / expected)
+                .sum()
+        };
+
+        let printable = data.iter().filter(|&&b| is_printable(b)).count();
+        let printable_ratio = if data.is_empty() {
+            0.0
+        } else {
+            printable as f64 / data.len() as f64
+        };
+        let longest_printable_run = data
+            .split(|&b| !is_printable(b))
+            .map(<[u8]>::len)
+            .max()
+            .unwrap_or(0);
+
+        ByteStats {
+            size: data.len(),
+            entropy: overall,
+            window_size,
+            windowed_entropy,
+            printable_ratio,
+            chi_square,
+            longest_printable_run,
+            classification: classify(data, printable_ratio, overall),
+            histogram: counts,
+        }
+    }
+
+    /// Most frequent byte values with their counts, most frequent first
+    pub fn most_common(&self, count: usize) -> Vec<(u8, usize)> {
+        let mut bytes: Vec<(u8, usize)> = (0..=255u8)
+            .zip(self.histogram.iter().copied())
+            .filter(|&(_, n)| n > 0)
+            .collect();
+        bytes.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        bytes.truncate(count);
+        bytes
+    }
+
+    /// ASCII graph of the windowed entropy, one row per bit
+    ///
+    /// Returns `None` for inputs too small to have a meaningful profile.
+    /// Windows are averaged to fit the graph width.
+    pub fn entropy_graph(&self) -> Option<String> {
+        let windows = &self.windowed_entropy;
+        if windows.len() < GRAPH_MIN_WINDOWS {
+            return None;
+        }
+
+        let columns: Vec<f64> = windows
+            .chunks(windows.len().div_ceil(GRAPH_WIDTH))
+            .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+            .collect();
+
+        let mut graph = String::new();
+        for level in (1..=8).rev() {
+            let row: String = columns
+                .iter()
+                .map(|&e| if e >= level as f64 - 0.5 { '#' } else { ' ' })
+                .collect();
+            graph.push_str(&format!("{} |{}\n", level, row.trim_end()));
+        }
+        graph.push_str(&format!("  +{}\n", "-".repeat(columns.len())));
+        let end = format!("{} bytes", self.windowed_entropy.len() * self.window_size);
+        graph.push_str(&format!(
+            "  0{:>width$}",
+            end,
+            width = columns.len().max(end.len() + 1)
+        ));
+        Some(graph)
+    }
+}
+
+/// Shannon entropy of data in bits per byte
+pub fn entropy(data: &[u8]) -> f64 {
+    entropy_of(&histogram(data), data.len())
+}
+
+fn histogram(data: &[u8]) -> Vec<usize> {
+    let mut counts = vec![0usize; 256];
+    for &b in data {
+        counts[b as usize] += 1;
+    }
+    counts
+}
+
+fn entropy_of(histogram: &[usize], len: usize) -> f64 {
+    if len == 0 {
+        return 0.0;
+    }
+    histogram
+        .iter()
+        .filter(|&&n| n > 0)
+        .map(|&n| {
+            let p = n as f64 / len as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Classify by the share of printable bytes, then by entropy relative to
+/// the most a buffer of this size can have
+fn classify(data: &[u8], printable_ratio: f64, entropy: f64) -> Classification {
+    if data.is_empty() {
+        return Classification::Empty;
+    }
+    if printable_ratio >= TEXT_PRINTABLE {
+        return Classification::Text;
+    }
+    let max_entropy = (data.len().min(256) as f64).log2();
+    if max_entropy > 0.0 && entropy / max_entropy >= RANDOM_ENTROPY {
+        Classification::Random
+    } else {
+        Classification::Structured
+    }
+}
+
+fn is_printable(b: u8) -> bool {
+    b.is_ascii_graphic() || matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Deterministic pseudo-random bytes
+    fn noise(len: usize) -> Vec<u8> {
+        let mut state = 0x2545_f491_4f6c_dd1du64;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_entropy_bounds() {
+        assert_eq!(entropy(b""), 0.0);
+        assert_eq!(entropy(b"aaaa"), 0.0);
+        assert_eq!(entropy(b"abab"), 1.0);
+        let all: Vec<u8> = (0..=255).collect();
+        assert_eq!(entropy(&all), 8.0);
+    }
+
+    #[test]
+    fn test_classification() {
+        let text = b"Plain text, as it would appear in a decoded config file.\n".repeat(20);
+        assert_eq!(
+            ByteStats::compute(&text, DEFAULT_WINDOW).classification,
+            Classification::Text
+        );
+
+        let random = ByteStats::compute(&noise(4096), DEFAULT_WINDOW);
+        assert_eq!(random.classification, Classification::Random);
+        assert!(random.entropy > 7.9);
+        assert!(random.chi_square < 350.0);
+
+        let mut binary = b"\x7fELF\x02\x01\x01".to_vec();
+        binary.extend(std::iter::repeat(0u8).take(512));
+        binary.extend_from_slice(b"/lib64/ld-linux-x86-64.so.2");
+        let stats = ByteStats::compute(&binary, DEFAULT_WINDOW);
+        assert_eq!(stats.classification, Classification::Structured);
+        assert_eq!(stats.longest_printable_run, 27);
+        assert_eq!(stats.most_common(1), vec![(0, 512)]);
+    }
+
+    #[test]
+    fn test_classification_ignores_single_byte_charsets() {
+        // Every byte decodes in some single-byte charset; that alone must
+        // not make binary look like text
+        let high: Vec<u8> = (0xa0..=0xff).cycle().take(1024).collect();
+        assert_ne!(
+            ByteStats::compute(&high, DEFAULT_WINDOW).classification,
+            Classification::Text
+        );
+        let nul_padded = b"key=value\0\0\0\0\0\0\0\0";
+        assert_eq!(
+            ByteStats::compute(nul_padded, DEFAULT_WINDOW).classification,
+            Classification::Structured
+        );
+    }
+
+    #[test]
+    fn test_entropy_graph() {
+        let mut data = vec![0u8; 4096];
+        data.extend(noise(4096));
+        let stats = ByteStats::compute(&data, DEFAULT_WINDOW);
+        assert_eq!(stats.windowed_entropy.len(), 8);
+
+        let graph = stats.entropy_graph().unwrap();
+        assert!(graph.starts_with("8 |    ####\n"));
+        assert!(graph.contains("1 |    ####\n"));
+        assert!(graph.trim_end().ends_with("8192 bytes"));
+
+        assert!(ByteStats::compute(&noise(100), DEFAULT_WINDOW)
+            .entropy_graph()
+            .is_none());
+    }
+}
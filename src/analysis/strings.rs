@@ -0,0 +1,263 @@
+//! Printable string extraction from binary data
+//!
+//! Like `strings`, finds runs of printable ASCII, and also UTF-16LE text as
+//! used by Windows binaries. Each string can be run through encoding
+//! detection, so a Base64 blob hidden in an executable shows up as a finding.
+
+use crate::encoding::scan;
+use crate::encoding::{DetectionConfidence, EncodingType};
+use crate::metadata::{charset, magic};
+use regex::Regex;
+use serde::Serialize;
+
+/// Default minimum string length in characters
+pub const DEFAULT_MIN_LEN: usize = 4;
+
+/// Shortest string checked as bare Base64, which detection alone only
+/// reports with low confidence
+const MIN_BASE64_LEN: usize = 16;
+
+/// Character encoding of an extracted string
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StringEncoding {
+    #[serde(rename = "ascii")]
+    Ascii,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+}
+
+impl StringEncoding {
+    /// Short name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            StringEncoding::Ascii => "ascii",
+            StringEncoding::Utf16Le => "utf-16le",
+        }
+    }
+}
+
+/// A string found in the data
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtractedString {
+    /// Byte offset of the first character
+    pub offset: usize,
+    pub encoding: StringEncoding,
+    pub text: String,
+    /// Encoded content detected in the string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoded: Option<EncodedString>,
+}
+
+/// Encoded content found in an extracted string
+#[derive(Debug, Clone, Serialize)]
+pub struct EncodedString {
+    #[serde(rename = "type")]
+    pub encoding_type: EncodingType,
+    pub confidence: DetectionConfidence,
+    /// Decoded value, or a `(binary: ...)` description of binary content
+    pub decoded: String,
+    pub is_text: bool,
+}
+
+/// What to extract
+#[derive(Debug, Clone)]
+pub struct StringsOptions {
+    /// Minimum length in characters
+    pub min_len: usize,
+    /// Only keep strings matching this pattern
+    pub pattern: Option<Regex>,
+    /// Run encoding detection on each string
+    pub detect: bool,
+}
+
+impl Default for StringsOptions {
+    fn default() -> Self {
+        Self {
+            min_len: DEFAULT_MIN_LEN,
+            pattern: None,
+            detect: false,
+        }
+    }
+}
+
+/// Extract ASCII and UTF-16LE strings, in order of offset
+pub fn extract(data: &[u8], options: &StringsOptions) -> Vec<ExtractedString> {
+    let min_len = options.min_len.max(1);
+    let mut strings = ascii_strings(data, min_len);
+    for alignment in 0..2 {
+        strings.extend(utf16le_strings(data, alignment, min_len));
+    }
+    strings.sort_by_key(|s| s.offset);
+
+    if let Some(ref pattern) = options.pattern {
+        strings.retain(|s| pattern.is_match(&s.text));
+    }
+    if options.detect {
+        for string in &mut strings {
+            string.encoded = detect(&string.text, string.offset);
+        }
+    }
+    strings
+}
+
+/// Run encoding detection on a string
+///
+/// A detection is only reported when the string decodes to text or a
+/// recognised file format; random identifiers rarely do, but often fit an
+/// alphabet such as Base32. Bare Base64 is also tried when it is long and
+/// correctly padded.
+fn detect(text: &str, offset: usize) -> Option<EncodedString> {
+    let location = format!("0x{:08x}", offset);
+    if let Some(finding) = scan::try_decode_value(text, &location) {
+        let decoded = finding.encoding.decoder().decode(&finding.original).ok()?;
+        let (decoded, is_text) = describe(&decoded)?;
+        return Some(EncodedString {
+            encoding_type: finding.encoding,
+            confidence: finding.confidence,
+            decoded,
+            is_text,
+        });
+    }
+
+    let base64_alphabet = text
+        .bytes()
+        .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'+' | b'/' | b'-' | b'_' | b'='));
+    if text.len() < MIN_BASE64_LEN || text.len() % 4 != 0 || !base64_alphabet {
+        return None;
+    }
+    let decoded = EncodingType::Base64.decoder().decode(text).ok()?;
+    let (decoded, is_text) = describe(&decoded)?;
+    Some(EncodedString {
+        encoding_type: EncodingType::Base64,
+        confidence: DetectionConfidence::Medium,
+        decoded,
+        is_text,
+    })
+}
+
+/// Decoded text, or a summary of a recognised binary format
+fn describe(decoded: &[u8]) -> Option<(String, bool)> {
+    if let Some((_, text)) = charset::decode_text(decoded) {
+        return Some((text, true));
+    }
+    let metadata = magic::detect(decoded);
+    if metadata.mime_type == "application/octet-stream" {
+        return None;
+    }
+    let summary = format!("(binary: {}, {} bytes)", metadata.mime_type, decoded.len());
+    Some((summary, false))
+}
+
+fn ascii_strings(data: &[u8], min_len: usize) -> Vec<ExtractedString> {
+    let mut strings = Vec::new();
+    let mut start = 0;
+    for (i, &b) in data.iter().chain(std::iter::once(&0)).enumerate() {
+        if is_string_byte(b) {
+            continue;
+        }
+        if i - start >= min_len {
+            strings.push(ExtractedString {
+                offset: start,
+                encoding: StringEncoding::Ascii,
+                text: String::from_utf8_lossy(&data[start..i]).into_owned(),
+                encoded: None,
+            });
+        }
+        start = i + 1;
+    }
+    strings
+}
+
+/// Runs of printable ASCII characters each followed by a NUL byte, starting
+/// at even (`alignment` 0) or odd offsets
+fn utf16le_strings(data: &[u8], alignment: usize, min_len: usize) -> Vec<ExtractedString> {
+    let Some(data_aligned) = data.get(alignment..) else {
+        return vec![];
+    };
+    let mut strings = Vec::new();
+    let mut run = String::new();
+    let mut start = alignment;
+    let units = data_aligned.chunks_exact(2).map(Some).chain([None]);
+    for (i, unit) in units.enumerate() {
+        if let Some(&[b, 0]) = unit {
+            if is_string_byte(b) {
+                if run.is_empty() {
+                    start = alignment + i * 2;
+                }
+                run.push(b as char);
+                continue;
+            }
+        }
+        if run.len() >= min_len {
+            strings.push(ExtractedString {
+                offset: start,
+                encoding: StringEncoding::Utf16Le,
+                text: std::mem::take(&mut run),
+                encoded: None,
+            });
+        }
+        run.clear();
+    }
+    strings
+}
+
+fn is_string_byte(b: u8) -> bool {
+    b.is_ascii_graphic() || b == b' ' || b == b'\t'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_ascii_and_utf16() {
+        let mut data = b"\x00\x01hello world\x00\xff".to_vec();
+        data.extend("kernel32.dll".encode_utf16().flat_map(u16::to_le_bytes));
+        data.extend_from_slice(b"\x00\x00ab\x00");
+
+        let strings = extract(&data, &StringsOptions::default());
+        assert_eq!(strings.len(), 2);
+        assert_eq!(strings[0].offset, 2);
+        assert_eq!(strings[0].text, "hello world");
+        assert_eq!(strings[0].encoding, StringEncoding::Ascii);
+        assert_eq!(strings[1].offset, 15);
+        assert_eq!(strings[1].text, "kernel32.dll");
+        assert_eq!(strings[1].encoding, StringEncoding::Utf16Le);
+    }
+
+    #[test]
+    fn test_pattern_and_detection() {
+        let data = b"\x00\x00GetProcAddress\x00aHR0cDovL2V2aWwuZXhhbXBsZS9hLnBzMQ==\x00\x90\x90";
+        let options = StringsOptions {
+            pattern: Some(Regex::new("^[A-Za-z0-9+/=]{16,}$").unwrap()),
+            detect: true,
+            ..Default::default()
+        };
+
+        let strings = extract(data, &options);
+        assert_eq!(strings.len(), 1);
+        let encoded = strings[0].encoded.as_ref().unwrap();
+        assert_eq!(encoded.encoding_type, EncodingType::Base64);
+        assert_eq!(encoded.decoded, "http://evil.example/a.ps1");
+
+        let plain = extract(b"\x00\x00ThisIsAnIdentifier\x00", &options);
+        assert!(plain[0].encoded.is_none());
+    }
+
+    #[test]
+    fn test_identifiers_not_reported() {
+        let identifiers = [
+            "GetModuleHandleA",
+            "ConfigurationKey",
+            "RegisterClassExW",
+            "GetProcAddressForCaller1",
+            "ProcessConfigurationKey1",
+            "InitializeCriticalSectionAndSpin",
+            "GetSystemTimePreciseAsFileTimeEx",
+        ];
+        for identifier in identifiers {
+            let found = detect(identifier, 0);
+            assert!(found.is_none(), "{} reported as {:?}", identifier, found);
+        }
+    }
+}
@@ -0,0 +1,445 @@
+//! Repeating-key XOR key recovery
+//!
+//! The key length is estimated from the normalised Hamming distance between
+//! ciphertext blocks (blocks encrypted with the same key differ as little as
+//! their plaintexts do) and the index of coincidence of the key columns (each
+//! column is single-byte XOR, which keeps the plaintext's byte distribution).
+//! Each column is then solved as single-byte XOR by how likely its bytes are
+//! under a model of text or of binary data, whichever explains the whole key
+//! better. A known plaintext prefix such as `MZ` or `%PDF` gives key bytes
+//! directly.
+
+use crate::encoding::cipher::ENGLISH_FREQUENCIES;
+use crate::util;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+/// Default longest key length tried
+pub const DEFAULT_MAX_KEY_LEN: usize = 40;
+
+/// Number of blocks compared pairwise for the Hamming distance
+const HAMMING_BLOCKS: usize = 8;
+
+/// A shorter key length within this share of the best score is preferred,
+/// since multiples of the key length score about as well as the key length
+const DIVISOR_TOLERANCE: f64 = 0.85;
+
+/// Minimum number of key lengths tried when cracking
+const MIN_LENGTHS_TRIED: usize = 5;
+
+/// Length of the decrypted preview
+const PREVIEW_LEN: usize = 64;
+
+/// Log-probability of each byte value in some kind of plaintext
+type ByteModel = [f64; 256];
+
+/// Statistics for one candidate key length
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyLength {
+    pub length: usize,
+    /// Average bit distance per byte between blocks
+    pub hamming: f64,
+    /// Average index of coincidence of the key columns
+    pub ioc: f64,
+    /// Combined ranking score (higher is better)
+    pub score: f64,
+}
+
+/// A recovered key with the plaintext it produces
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyCandidate {
+    #[serde(serialize_with = "serialize_hex")]
+    pub key: Vec<u8>,
+    /// Plaintext score of the decrypted data less the cost of the key's
+    /// length (see [`plaintext_score`])
+    pub score: f64,
+    /// Start of the decrypted data, with unprintable bytes shown as `.`
+    pub preview: String,
+}
+
+impl KeyCandidate {
+    fn new(data: &[u8], key: Vec<u8>) -> Self {
+        let plaintext = decrypt(data, &key);
+        // Every key byte costs as much as a random byte, so a longer key must
+        // explain the data better than a shorter one to outscore it
+        let key_cost = key.len() as f64 * 256f64.ln();
+        let log_likelihood = models()
+            .iter()
+            .map(|model| log_likelihood(&plaintext, model))
+            .fold(f64::NEG_INFINITY, f64::max);
+        KeyCandidate {
+            score: relative_score(log_likelihood - key_cost, plaintext.len()),
+            preview: preview(&plaintext),
+            key,
+        }
+    }
+
+    /// Key as text if it is printable ASCII
+    pub fn key_text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.key)
+            .ok()
+            .filter(|k| k.bytes().all(|b| b.is_ascii_graphic() || b == b' '))
+    }
+
+    /// Key as lowercase hex
+    pub fn key_hex(&self) -> String {
+        util::hex(&self.key)
+    }
+}
+
+/// XOR data with a repeating key
+pub fn decrypt(data: &[u8], key: &[u8]) -> Vec<u8> {
+    if key.is_empty() {
+        return data.to_vec();
+    }
+    data.iter()
+        .zip(key.iter().cycle())
+        .map(|(b, k)| b ^ k)
+        .collect()
+}
+
+/// Number of differing bits between two byte strings
+pub fn hamming_distance(a: &[u8], b: &[u8]) -> u32 {
+    a.iter().zip(b).map(|(x, y)| (x ^ y).count_ones()).sum()
+}
+
+/// Rank key lengths from 1 to `max_len`, most likely first
+///
+/// Lengths need at least two full blocks of data.
+pub fn estimate_key_lengths(data: &[u8], max_len: usize) -> Vec<KeyLength> {
+    let max_len = max_len.min(data.len() / 2);
+    let mut lengths: Vec<KeyLength> = (1..=max_len)
+        .map(|length| {
+            let hamming = normalised_hamming(data, length);
+            let ioc = column_ioc(data, length);
+            KeyLength {
+                length,
+                hamming,
+                ioc,
+                // Fewer differing bits and more coincidences both point at
+                // the right length
+                score: ioc / hamming.max(0.01),
+            }
+        })
+        .collect();
+    lengths.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    // Prefer the shortest divisor of the winner that scores nearly as well
+    if let Some(best) = lengths.first().cloned() {
+        let divisor = lengths
+            .iter()
+            .filter(|l| best.length % l.length == 0 && l.score >= best.score * DIVISOR_TOLERANCE)
+            .min_by_key(|l| l.length)
+            .map(|l| l.length);
+        if let Some(position) = divisor.and_then(|d| lengths.iter().position(|l| l.length == d)) {
+            let preferred = lengths.remove(position);
+            lengths.insert(0, preferred);
+        }
+    }
+
+    lengths
+}
+
+/// Recover a key of the given length, solving each column as single-byte XOR
+///
+/// Each column takes the key byte that makes it most likely plaintext. This
+/// is done once assuming text and once assuming binary data, and the key
+/// under which the data is more likely wins. Key bytes given in `fixed`
+/// (e.g. from known plaintext) are kept.
+pub fn recover_key(data: &[u8], length: usize, fixed: &[Option<u8>]) -> Vec<u8> {
+    let columns: Vec<Vec<u8>> = (0..length)
+        .map(|column| data.iter().skip(column).step_by(length).copied().collect())
+        .collect();
+
+    models()
+        .iter()
+        .map(|model| {
+            let solved: Vec<(u8, f64)> = columns
+                .iter()
+                .enumerate()
+                .map(|(column, bytes)| {
+                    let keys = match fixed.get(column) {
+                        Some(&Some(byte)) => byte..=byte,
+                        _ => 0..=255,
+                    };
+                    keys.map(|key| {
+                        let plain: Vec<u8> = bytes.iter().map(|b| b ^ key).collect();
+                        (key, log_likelihood(&plain, model))
+                    })
+                    .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+                    .unwrap_or((0, 0.0))
+                })
+                .collect();
+            let total: f64 = solved.iter().map(|(_, ll)| ll).sum();
+            (solved.into_iter().map(|(key, _)| key).collect(), total)
+        })
+        .max_by(|a: &(Vec<u8>, f64), b| a.1.total_cmp(&b.1))
+        .map(|(key, _)| key)
+        .unwrap_or_default()
+}
+
+/// Score how likely data is plaintext, between 0 (random bytes) and 1
+///
+/// Data is scored by the better fitting of a model of text and a model of
+/// binary files, so executables and images score as well as prose, and
+/// decryptions that differ anywhere score differently.
+pub fn plaintext_score(data: &[u8]) -> f64 {
+    let log_likelihood = models()
+        .iter()
+        .map(|model| log_likelihood(data, model))
+        .fold(f64::NEG_INFINITY, f64::max);
+    relative_score(log_likelihood, data.len())
+}
+
+/// Scale a log-likelihood to the information saved per byte compared to
+/// random bytes, between 0 and 1
+fn relative_score(log_likelihood: f64, len: usize) -> f64 {
+    if len == 0 {
+        return 0.0;
+    }
+    (1.0 + log_likelihood / len as f64 / 256f64.ln()).clamp(0.0, 1.0)
+}
+
+fn log_likelihood(data: &[u8], model: &ByteModel) -> f64 {
+    data.iter().map(|&b| model[b as usize]).sum()
+}
+
+/// Byte models of text and of binary data
+fn models() -> &'static [ByteModel; 2] {
+    static MODELS: OnceLock<[ByteModel; 2]> = OnceLock::new();
+    MODELS.get_or_init(|| [text_model(), binary_model()])
+}
+
+/// Mostly lowercase English with spaces, some capitals, digits and
+/// punctuation, and hardly any control or high bytes
+fn text_model() -> ByteModel {
+    let mut model = [0.01 / 161.0; 256];
+    for (letter, freq) in ENGLISH_FREQUENCIES.iter().enumerate() {
+        model[b'a' as usize + letter] = 0.55 * freq;
+        model[b'A' as usize + letter] = 0.05 * freq;
+    }
+    for digit in b'0'..=b'9' {
+        model[digit as usize] = 0.02 / 10.0;
+    }
+    for b in (b'!'..=b'~').filter(|b| !b.is_ascii_alphanumeric()) {
+        model[b as usize] = 0.17 / 32.0;
+    }
+    for b in [b'\t', b'\n', b'\r'] {
+        model[b as usize] = 0.03 / 3.0;
+    }
+    model[b' ' as usize] = 0.17;
+    model.map(f64::ln)
+}
+
+/// Executables and other binary files: many zero bytes, some `0xff` padding,
+/// embedded ASCII strings and anything else
+fn binary_model() -> ByteModel {
+    let mut model = [0.37 / 159.0; 256];
+    for b in b' '..=b'~' {
+        model[b as usize] = 0.3 / 95.0;
+    }
+    model[0x00] = 0.3;
+    model[0xff] = 0.03;
+    model.map(f64::ln)
+}
+
+/// Recover candidate keys for the most likely key lengths, best first
+///
+/// With `known` plaintext, key bytes under the prefix are derived directly.
+/// If the prefix covers the key at least twice its period gives the key;
+/// otherwise the remaining bytes are solved by frequency scoring.
+pub fn crack(
+    data: &[u8],
+    max_key_len: usize,
+    count: usize,
+    known: Option<&[u8]>,
+) -> Vec<KeyCandidate> {
+    let keystream: Vec<u8> = known
+        .map(|k| data.iter().zip(k).map(|(c, p)| c ^ p).collect())
+        .unwrap_or_default();
+
+    let mut candidates = Vec::new();
+    if let Some(period) = shortest_period(&keystream) {
+        candidates.push(KeyCandidate::new(data, keystream[..period].to_vec()));
+    }
+
+    let tried = count.max(MIN_LENGTHS_TRIED);
+    for length in estimate_key_lengths(data, max_key_len)
+        .into_iter()
+        .map(|l| l.length)
+        .filter(|&l| keystream.is_empty() || l > keystream.len() / 2)
+    {
+        let fixed: Vec<Option<u8>> = (0..length).map(|i| keystream.get(i).copied()).collect();
+        let key = minimal_key(recover_key(data, length, &fixed));
+        if !candidates.iter().any(|c: &KeyCandidate| c.key == key) {
+            candidates.push(KeyCandidate::new(data, key));
+        }
+        if candidates.len() >= tried {
+            break;
+        }
+    }
+
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates.truncate(count);
+    candidates
+}
+
+/// Shortest period that repeats at least twice in a keystream
+fn shortest_period(keystream: &[u8]) -> Option<usize> {
+    (1..=keystream.len() / 2)
+        .find(|&p| (p..keystream.len()).all(|i| keystream[i] == keystream[i - p]))
+}
+
+/// Reduce a key that repeats a shorter key (e.g. `keykey` to `key`)
+fn minimal_key(key: Vec<u8>) -> Vec<u8> {
+    let period = (1..=key.len())
+        .find(|&p| key.len() % p == 0 && (p..key.len()).all(|i| key[i] == key[i - p]))
+        .unwrap_or(key.len());
+    key[..period].to_vec()
+}
+
+fn normalised_hamming(data: &[u8], length: usize) -> f64 {
+    let blocks: Vec<&[u8]> = data.chunks_exact(length).take(HAMMING_BLOCKS).collect();
+    let (mut total, mut pairs) = (0u32, 0usize);
+    for (i, a) in blocks.iter().enumerate() {
+        for b in &blocks[i + 1..] {
+            total += hamming_distance(a, b);
+            pairs += 1;
+        }
+    }
+    if pairs == 0 {
+        return f64::MAX;
+    }
+    total as f64 / (pairs * length) as f64
+}
+
+fn column_ioc(data: &[u8], length: usize) -> f64 {
+    let iocs: Vec<f64> = (0..length)
+        .filter_map(|column| {
+            let mut counts = [0usize; 256];
+            let mut n = 0;
+            for &b in data.iter().skip(column).step_by(length) {
+                counts[b as usize] += 1;
+                n += 1;
+            }
+            (n >= 2).then(|| {
+                let same: usize = counts.iter().map(|&c| c * c.saturating_sub(1)).sum();
+                same as f64 / (n * (n - 1)) as f64
+            })
+        })
+        .collect();
+    if iocs.is_empty() {
+        return 0.0;
+    }
+    iocs.iter().sum::<f64>() / iocs.len() as f64
+}
+
+fn preview(data: &[u8]) -> String {
+    data.iter()
+        .take(PREVIEW_LEN)
+        .map(|&b| match b {
+            b' '..=b'~' => b as char,
+            _ => '.',
+        })
+        .collect()
+}
+
+fn serialize_hex<S: serde::Serializer>(key: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&util::hex(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEXT: &[u8] = b"It was the best of times, it was the worst of times, it was the age of \
+        wisdom, it was the age of foolishness, it was the epoch of belief, it was the epoch of \
+        incredulity, it was the season of Light, it was the season of Darkness, it was the spring \
+        of hope, it was the winter of despair.";
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(b"this is a test", b"wokka wokka!!!"), 37);
+    }
+
+    #[test]
+    fn test_estimate_key_length() {
+        let data = decrypt(TEXT, b"secret");
+        assert_eq!(
+            estimate_key_lengths(&data, DEFAULT_MAX_KEY_LEN)[0].length,
+            6
+        );
+    }
+
+    #[test]
+    fn test_crack_repeating_key() {
+        let data = decrypt(TEXT, b"secret");
+        let candidates = crack(&data, DEFAULT_MAX_KEY_LEN, 3, None);
+        assert_eq!(candidates[0].key, b"secret");
+        assert_eq!(candidates[0].key_text(), Some("secret"));
+        assert!(candidates[0].preview.starts_with("It was the best"));
+    }
+
+    #[test]
+    fn test_crack_prefers_shorter_key() {
+        let data = decrypt(&TEXT[..170], b"hunter2");
+        let candidates = crack(&data, DEFAULT_MAX_KEY_LEN, 2, None);
+        assert_eq!(candidates[0].key, b"hunter2");
+    }
+
+    #[test]
+    fn test_known_plaintext() {
+        let mut plain = b"%PDF-1.7\n".to_vec();
+        plain.extend_from_slice(&[0u8; 32]);
+        let data = decrypt(&plain, &[0xde, 0xad, 0xbe, 0xef]);
+
+        let candidates = crack(&data, DEFAULT_MAX_KEY_LEN, 3, Some(b"%PDF-1.7"));
+        assert_eq!(candidates[0].key, [0xde, 0xad, 0xbe, 0xef]);
+        assert_eq!(candidates[0].key_hex(), "deadbeef");
+        // Every candidate decrypts the prefix, but only one the rest
+        assert!(candidates[0].score > candidates[1].score);
+    }
+
+    #[test]
+    fn test_crack_binary() {
+        // DOS header and stub of an executable, followed by its PE header
+        let mut plain = vec![0u8; 512];
+        plain[..16].copy_from_slice(&[
+            b'M', b'Z', 0x90, 0, 3, 0, 0, 0, 4, 0, 0, 0, 0xff, 0xff, 0, 0,
+        ]);
+        plain[0x18] = 0x40;
+        plain[0x3c] = 0x80;
+        let stub = b"\x0e\x1f\xba\x0e\x00\xb4\x09\xcd\x21\xb8\x01\x4c\xcd\x21\
+            This program cannot be run in DOS mode.\r\r\n$";
+        plain[0x40..0x40 + stub.len()].copy_from_slice(stub);
+        plain[0x80..0x88].copy_from_slice(b"PE\0\0\x64\x86\x03\0");
+        plain[0x188..0x190].copy_from_slice(b".text\0\0\0");
+        plain[0x1b0..0x1b8].copy_from_slice(b".data\0\0\0");
+        let data = decrypt(&plain, &[0x13, 0x37, 0xc0, 0xde, 0x42]);
+
+        let candidates = crack(&data, DEFAULT_MAX_KEY_LEN, 3, None);
+        assert_eq!(candidates[0].key, [0x13, 0x37, 0xc0, 0xde, 0x42]);
+        assert!(candidates[0].preview.starts_with("MZ"));
+        assert!(candidates
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+
+        let known = crack(&data, DEFAULT_MAX_KEY_LEN, 1, Some(b"MZ"));
+        assert_eq!(known[0].key, [0x13, 0x37, 0xc0, 0xde, 0x42]);
+    }
+
+    #[test]
+    fn test_plaintext_score() {
+        assert!(plaintext_score(TEXT) > 0.3);
+        assert!(plaintext_score(&[0u8; 64]) > 0.5);
+        let noise: Vec<u8> = (0..=255u8).collect();
+        assert!(plaintext_score(&noise) < 0.1);
+        assert_eq!(plaintext_score(b""), 0.0);
+    }
+
+    #[test]
+    fn test_minimal_key() {
+        assert_eq!(minimal_key(b"keykey".to_vec()), b"key");
+        assert_eq!(minimal_key(b"abc".to_vec()), b"abc");
+    }
+}
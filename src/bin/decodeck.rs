@@ -1,24 +1,36 @@
 //! Decodeck CLI - Multi-encoding decoder with metadata display and interactive viewing
 
 use anyhow::{Context, Result};
-use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap::{CommandFactory, Parser, Subcommand};
 use clap_complete::{generate, Shell};
+use decodeck::analysis::{archive, carve};
+use decodeck::analysis::deobfuscate::{self, ScriptLanguage};
+use decodeck::analysis::hashes::{self as hash, HashAlgorithm, Hashes};
+use decodeck::analysis::iocs::{self as ioc, IocFormat};
+use decodeck::analysis::stats::{self, ByteStats};
+use decodeck::analysis::strings::{self as strings_view, StringsOptions};
+use decodeck::analysis::xor;
 use decodeck::decoder::EncodedData;
-use decodeck::encoding::{detect::detect_encoding, scan, EncodingInfo, EncodingType};
-
-/// Format for scanning structured content
-#[derive(Debug, Clone, Copy, ValueEnum)]
-enum ScanFormat {
-    /// JSON format
-    Json,
-    /// XML format
-    Xml,
-}
+use decodeck::encoding::encode::{
+    Base32Alphabet, EncodeOptions, HexFormat, LineEnding, UrlCharset,
+};
+use decodeck::encoding::literal::{self, Language, LiteralOptions};
+use decodeck::encoding::recipe::{Recipe, Step};
+use decodeck::encoding::scan::{path::PathStyle, ScanFormat, ScanOptions};
+use decodeck::encoding::{
+    data_uri, detect::detect_encoding, powershell, scan, DetectionConfidence, EncodingInfo,
+    EncodingType,
+};
 use decodeck::error::{exit_codes, DecodeckError};
 use decodeck::input::{InputSource, SourceType};
 use decodeck::interactive::InteractivePrompt;
-use decodeck::metadata::magic;
+use decodeck::metadata::signatures::SignatureTable;
+use decodeck::metadata::{charset, magic, risk};
+use decodeck::output::file::{write_output, Overwrite};
+use decodeck::output::history::{self, HistoryEntry};
+use decodeck::output::naming::{self, NameContext};
 use decodeck::output::{format_size, DecodeResult, OutputFile};
+use regex::Regex;
 use std::fs;
 use std::io::{self, IsTerminal, Read};
 use std::path::PathBuf;
@@ -41,6 +53,8 @@ struct Cli {
     command: Commands,
 }
 
+// Parsed once per run, so the size of the decode variant does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     /// Decode encoded data to file
@@ -60,6 +74,14 @@ enum Commands {
         #[arg(short, long)]
         output: Option<PathBuf>,
 
+        /// Write the output into this directory, named by --name-template
+        #[arg(long, value_name = "DIR", conflicts_with = "output")]
+        output_dir: Option<PathBuf>,
+
+        /// File name for --output-dir (default: "{source_stem}_{sha256:8}{ext}"; also {md5}, {timestamp}, {encoding}, {chain})
+        #[arg(long, value_name = "TEMPLATE", requires = "output_dir")]
+        name_template: Option<String>,
+
         /// Encoding type (auto-detected if not specified)
         #[arg(short, long, value_enum)]
         encoding: Option<EncodingType>,
@@ -72,6 +94,14 @@ enum Commands {
         #[arg(long, default_value = "10")]
         max_depth: usize,
 
+        /// Also try cipher layers (ROT13, ROT47, Atbash, Caesar, XOR) between encodings
+        #[arg(long, requires = "chain")]
+        ciphers: bool,
+
+        /// Layers to peel, outermost first (e.g. "base64,xor:0x2a"; "xor" or "caesar" alone brute-forces the key)
+        #[arg(long, conflicts_with_all = ["encoding", "chain"])]
+        recipe: Option<Recipe>,
+
         /// Output in JSON format
         #[arg(short, long)]
         json: bool,
@@ -88,9 +118,69 @@ enum Commands {
         #[arg(short = 'F', long)]
         force: bool,
 
+        /// Keep an existing output file as "<name>.bak" when overwriting it
+        #[arg(long)]
+        backup: bool,
+
+        /// Write to "name (1).ext" instead of failing when the output file exists
+        #[arg(long, conflicts_with_all = ["force", "backup"])]
+        no_clobber_suffix: bool,
+
         /// Maximum input size (e.g., "100MB")
         #[arg(long, default_value = "100MB")]
         max_size: String,
+
+        /// Extract indicators of compromise (URLs, IPs, hashes, ...) from the decoded content
+        #[arg(long)]
+        extract_iocs: bool,
+
+        /// Write extracted indicators to a file (requires --extract-iocs)
+        #[arg(long, value_name = "PATH", requires = "extract_iocs")]
+        export_iocs: Option<PathBuf>,
+
+        /// Format for --export-iocs
+        #[arg(long, value_enum, default_value = "csv", requires = "export_iocs")]
+        ioc_format: IocFormat,
+
+        /// Show entropy and byte statistics of the decoded content
+        #[arg(long)]
+        stats: bool,
+
+        /// List printable ASCII and UTF-16LE strings in the decoded content
+        #[arg(long)]
+        strings: bool,
+
+        /// Minimum length of listed strings
+        #[arg(long, default_value = "4", requires = "strings")]
+        min_len: usize,
+
+        /// Only list strings matching this regular expression
+        #[arg(long, value_name = "REGEX", requires = "strings")]
+        strings_match: Option<Regex>,
+
+        /// Run encoding detection on each listed string
+        #[arg(long, requires = "strings")]
+        strings_detect: bool,
+
+        /// Run magic detection and an encoding scan on each member of a decoded archive
+        #[arg(long)]
+        inspect_archive: bool,
+
+        /// Unpack a decoded ZIP, TAR or 7z archive into this directory
+        #[arg(long, value_name = "DIR")]
+        extract: Option<PathBuf>,
+
+        /// TOML file of extra file signatures for type detection
+        #[arg(long, value_name = "FILE")]
+        signatures: Option<PathBuf>,
+
+        /// Hashes of the decoded content to report (comma-separated)
+        #[arg(long, value_enum, value_delimiter = ',', value_name = "ALGORITHMS")]
+        hash: Vec<HashAlgorithm>,
+
+        /// Fail unless the decoded content has this hex digest (e.g. "sha256:9f86...")
+        #[arg(long, value_name = "HEX")]
+        verify_hash: Option<String>,
     },
     /// Encode data to specified format
     Encode {
@@ -109,6 +199,67 @@ enum Commands {
         #[arg(short, long, value_enum, default_value = "base64")]
         encoding: EncodingType,
 
+        /// Build a data URI typed by the detected content (same as -e data-uri)
+        #[arg(long, conflicts_with = "encoding")]
+        data_uri: bool,
+
+        /// Emit a byte-array literal for a programming language
+        #[arg(long = "as", value_enum, value_name = "LANG", conflicts_with_all = ["encoding", "data_uri"])]
+        language: Option<Language>,
+
+        /// Variable name of the literal (upper-cased for Rust)
+        #[arg(long, default_value = "data", requires = "language")]
+        name: String,
+
+        /// Bytes per line of the literal
+        #[arg(long, default_value = "12", value_parser = clap::value_parser!(u16).range(1..), requires = "language")]
+        width: u16,
+
+        /// Also declare a constant holding the data length
+        #[arg(long, requires = "language")]
+        length_const: bool,
+
+        /// Use the URL-safe Base64 alphabet (- and _ instead of + and /)
+        #[arg(long)]
+        url_safe: bool,
+
+        /// Omit = padding (Base64, Base32)
+        #[arg(long)]
+        no_padding: bool,
+
+        /// Wrap output at this many columns (Base64, Base32, hex, Base85); hex
+        /// lines only break between groups
+        #[arg(long, value_name = "COLS", value_parser = clap::value_parser!(u16).range(1..))]
+        wrap: Option<u16>,
+
+        /// Line ending when wrapping
+        #[arg(long, value_enum, default_value = "lf", requires = "wrap")]
+        line_ending: LineEnding,
+
+        /// Uppercase hex digits
+        #[arg(long)]
+        uppercase: bool,
+
+        /// Hex layout
+        #[arg(long, value_enum, default_value = "plain")]
+        hex_format: HexFormat,
+
+        /// Bytes per hex group, separated like --hex-format (0 for one group)
+        #[arg(long, value_name = "N")]
+        hex_group: Option<usize>,
+
+        /// Base32 alphabet
+        #[arg(long, value_enum, default_value = "standard")]
+        base32_alphabet: Base32Alphabet,
+
+        /// Omit the <~ ~> Ascii85 delimiters
+        #[arg(long)]
+        no_delimiters: bool,
+
+        /// Characters URL encoding leaves as they are
+        #[arg(long, value_enum, default_value = "all")]
+        url_charset: UrlCharset,
+
         /// Copy result to clipboard
         #[arg(long)]
         copy: bool,
@@ -117,9 +268,9 @@ enum Commands {
         #[arg(short, long)]
         json: bool,
     },
-    /// Scan JSON/XML for encoded content
+    /// Scan JSON, XML, config files, CSV, HAR or raw HTTP for encoded content
     Scan {
-        /// JSON/XML content to scan (or use --file)
+        /// Content to scan (or use --file)
         data: Option<String>,
 
         /// Read from file
@@ -130,14 +281,112 @@ enum Commands {
         #[arg(long)]
         clipboard: bool,
 
-        /// Format hint (auto-detected if not specified)
+        /// Format hint (detected from the file name or content if not specified)
         #[arg(long, value_enum)]
         format: Option<ScanFormat>,
 
+        /// Output the document with encoded values replaced by placeholders
+        #[arg(long, conflicts_with = "json")]
+        redact: bool,
+
+        /// Only redact findings that look like secrets (requires --redact)
+        #[arg(long, requires = "redact")]
+        secrets_only: bool,
+
+        /// Output the document with encoded values replaced by their decoded text
+        #[arg(long, conflicts_with_all = ["json", "redact"])]
+        expand: bool,
+
+        /// Annotate expanded values with the encoding used (requires --expand)
+        #[arg(long, requires = "expand")]
+        annotate: bool,
+
+        /// Maximum depth for scanning into decoded payloads (0 disables)
+        #[arg(long, default_value = "3")]
+        max_depth: usize,
+
+        /// Syntax for reported JSON locations
+        #[arg(long, value_enum, default_value = "jsonpath")]
+        path_style: PathStyle,
+
+        /// Only scan values at this location (e.g. "$.a.b", "/a/b", "/root/item", "db.password")
+        #[arg(long)]
+        select: Option<String>,
+
+        /// Extract indicators of compromise (URLs, IPs, hashes, ...) from decoded values
+        #[arg(long)]
+        extract_iocs: bool,
+
+        /// Write extracted indicators to a file (requires --extract-iocs)
+        #[arg(long, value_name = "PATH", requires = "extract_iocs")]
+        export_iocs: Option<PathBuf>,
+
+        /// Format for --export-iocs
+        #[arg(long, value_enum, default_value = "csv", requires = "export_iocs")]
+        ioc_format: IocFormat,
+
+        /// Replace an existing --export-iocs file
+        #[arg(short = 'F', long, requires = "export_iocs")]
+        force: bool,
+
+        /// Print only the decoded values, one per line
+        #[arg(short = 'r', long, conflicts_with_all = ["json", "redact", "expand"])]
+        raw: bool,
+
         /// Output in JSON format
         #[arg(short, long)]
         json: bool,
     },
+    /// Decode encoded payloads wrapped in script code (atob, base64 -d, FromBase64String, ...)
+    Deobfuscate {
+        /// Script source to deobfuscate (or use --file)
+        data: Option<String>,
+
+        /// Read the script from file
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Read the script from clipboard
+        #[arg(long)]
+        clipboard: bool,
+
+        /// Script language (detected from the file name or content if not specified)
+        #[arg(short, long, value_enum)]
+        language: Option<ScriptLanguage>,
+
+        /// Do not append comments describing each replacement
+        #[arg(long)]
+        no_annotate: bool,
+
+        /// Output in JSON format
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Analyze decoded data
+    Analyze {
+        #[command(subcommand)]
+        command: AnalyzeCommands,
+    },
+    /// List previous decode outputs
+    History {
+        /// Show only the N most recent outputs
+        #[arg(short = 'n', long, value_name = "N")]
+        limit: Option<usize>,
+
+        /// Output in JSON format
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Remove old temporary decode outputs
+    Clean {
+        /// Remove outputs older than this (e.g. "12h", "7d", "2w")
+        #[arg(long, value_name = "AGE", default_value = "7d")]
+        older_than: String,
+
+        /// List the files that would be removed without removing them
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// Generate shell completion scripts
     #[command(after_help = r#"INSTALLATION EXAMPLES:
   # Bash - add to ~/.bashrc
@@ -158,6 +407,129 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum AnalyzeCommands {
+    /// Show entropy and byte statistics
+    Stats {
+        /// Encoded data to analyze (or use --file)
+        data: Option<String>,
+
+        /// Read data from file
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Read data from clipboard
+        #[arg(long)]
+        clipboard: bool,
+
+        /// Decode the input with this encoding first (auto-detected if not specified)
+        #[arg(short, long, value_enum, conflicts_with_all = ["recipe", "raw_input"])]
+        encoding: Option<EncodingType>,
+
+        /// Decode the input with a recipe first (e.g. "base64,hex")
+        #[arg(long, conflicts_with = "raw_input")]
+        recipe: Option<Recipe>,
+
+        /// Analyze the input bytes as they are, without decoding
+        #[arg(long)]
+        raw_input: bool,
+
+        /// Window size in bytes for the entropy profile
+        #[arg(short, long, default_value = "1024", value_parser = clap::value_parser!(u64).range(1..))]
+        window: u64,
+
+        /// Output in JSON format
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Find and extract files embedded at any offset
+    Carve {
+        /// Encoded data to analyze (or use --file)
+        data: Option<String>,
+
+        /// Read data from file
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Read data from clipboard
+        #[arg(long)]
+        clipboard: bool,
+
+        /// Decode the input with this encoding first (auto-detected if not specified)
+        #[arg(short, long, value_enum, conflicts_with_all = ["recipe", "raw_input"])]
+        encoding: Option<EncodingType>,
+
+        /// Decode the input with a recipe first (e.g. "base64,hex")
+        #[arg(long, conflicts_with = "raw_input")]
+        recipe: Option<Recipe>,
+
+        /// Analyze the input bytes as they are, without decoding
+        #[arg(long)]
+        raw_input: bool,
+
+        /// Extract the embedded files into this directory
+        #[arg(short, long)]
+        output_dir: Option<PathBuf>,
+
+        /// Force overwrite existing files
+        #[arg(short = 'F', long)]
+        force: bool,
+
+        /// Output in JSON format
+        #[arg(short, long)]
+        json: bool,
+    },
+    /// Recover a repeating XOR key and decrypt the data
+    Xor {
+        /// Encoded data to analyze (or use --file)
+        data: Option<String>,
+
+        /// Read data from file
+        #[arg(short, long)]
+        file: Option<PathBuf>,
+
+        /// Read data from clipboard
+        #[arg(long)]
+        clipboard: bool,
+
+        /// Decode the input with this encoding first (auto-detected if not specified)
+        #[arg(short, long, value_enum, conflicts_with_all = ["recipe", "raw_input"])]
+        encoding: Option<EncodingType>,
+
+        /// Decode the input with a recipe first (e.g. "base64,hex")
+        #[arg(long, conflicts_with = "raw_input")]
+        recipe: Option<Recipe>,
+
+        /// Analyze the input bytes as they are, without decoding
+        #[arg(long)]
+        raw_input: bool,
+
+        /// Known plaintext at the start of the data (e.g. "MZ", "%PDF", "{\"")
+        #[arg(long)]
+        known: Option<String>,
+
+        /// Longest key length to try
+        #[arg(long, default_value = "40")]
+        max_key_len: usize,
+
+        /// Number of candidate keys to report
+        #[arg(short = 'n', long, default_value = "3")]
+        candidates: usize,
+
+        /// Write the data decrypted with the best key to a file
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Force overwrite existing files
+        #[arg(short = 'F', long)]
+        force: bool,
+
+        /// Output in JSON format
+        #[arg(short, long)]
+        json: bool,
+    },
+}
+
 fn main() -> ExitCode {
     let cli = Cli::parse();
 
@@ -171,44 +543,263 @@ fn main() -> ExitCode {
             file,
             clipboard,
             output,
+            output_dir,
+            name_template,
             encoding,
             chain,
             max_depth,
+            ciphers,
+            recipe,
             json,
             raw,
             no_interactive,
             force,
+            backup,
+            no_clobber_suffix,
             max_size,
+            extract_iocs,
+            export_iocs,
+            ioc_format,
+            stats,
+            strings,
+            min_len,
+            strings_match,
+            strings_detect,
+            inspect_archive,
+            extract,
+            signatures,
+            hash,
+            verify_hash,
         } => run_decode(
             data,
             file,
             clipboard,
             output,
+            output_dir.map(|dir| OutputDir {
+                dir,
+                template: name_template.unwrap_or_else(|| naming::DEFAULT_TEMPLATE.to_string()),
+            }),
             encoding,
-            chain,
-            max_depth,
+            Layers {
+                chain,
+                max_depth,
+                ciphers,
+                recipe,
+            },
             json,
             raw,
             no_interactive,
             force,
+            overwrite_mode(force, backup, no_clobber_suffix),
             cli.quiet,
             max_size,
+            IocOptions::new(
+                extract_iocs,
+                export_iocs,
+                ioc_format,
+                overwrite_mode(force, backup, no_clobber_suffix),
+            ),
+            stats,
+            strings.then_some(StringsOptions {
+                min_len,
+                pattern: strings_match,
+                detect: strings_detect,
+            }),
+            ArchiveOptions {
+                inspect: inspect_archive,
+                extract,
+            },
+            signatures,
+            HashOptions {
+                algorithms: hash,
+                verify: verify_hash,
+            },
         ),
         Commands::Encode {
             data,
             file,
             clipboard,
             encoding,
+            data_uri,
+            language,
+            name,
+            width,
+            length_const,
+            url_safe,
+            no_padding,
+            wrap,
+            line_ending,
+            uppercase,
+            hex_format,
+            hex_group,
+            base32_alphabet,
+            no_delimiters,
+            url_charset,
             copy,
             json,
-        } => run_encode(data, file, clipboard, encoding, copy, json, cli.quiet),
+        } => {
+            let literal = language.map(|language| LiteralOptions {
+                language,
+                name,
+                width: width.into(),
+                length: length_const,
+            });
+            let encoding = if data_uri {
+                EncodingType::DataUri
+            } else if literal.is_some() {
+                EncodingType::Literal
+            } else {
+                encoding
+            };
+            let options = EncodeOptions {
+                url_safe,
+                padding: !no_padding,
+                wrap: wrap.map(usize::from),
+                line_ending,
+                uppercase,
+                hex_format,
+                hex_group,
+                base32_alphabet,
+                base85_delimiters: !no_delimiters,
+                url_charset,
+            };
+            run_encode(
+                data, file, clipboard, encoding, options, literal, copy, json, cli.quiet,
+            )
+        }
         Commands::Scan {
             data,
             file,
             clipboard,
             format,
+            redact,
+            secrets_only,
+            expand,
+            annotate,
+            max_depth,
+            path_style,
+            select,
+            extract_iocs,
+            export_iocs,
+            ioc_format,
+            force,
+            raw,
             json,
-        } => run_scan(data, file, clipboard, format, json, cli.quiet),
+        } => run_scan(
+            data,
+            file,
+            clipboard,
+            format,
+            redact,
+            secrets_only,
+            expand,
+            annotate,
+            ScanOptions {
+                max_depth,
+                path_style,
+                select,
+            },
+            IocOptions::new(
+                extract_iocs,
+                export_iocs,
+                ioc_format,
+                overwrite_mode(force, false, false),
+            ),
+            raw,
+            json,
+            cli.quiet,
+        ),
+        Commands::Deobfuscate {
+            data,
+            file,
+            clipboard,
+            language,
+            no_annotate,
+            json,
+        } => run_deobfuscate(data, file, clipboard, language, !no_annotate, json, cli.quiet),
+        Commands::Analyze { command } => match command {
+            AnalyzeCommands::Stats {
+                data,
+                file,
+                clipboard,
+                encoding,
+                recipe,
+                raw_input,
+                window,
+                json,
+            } => run_analyze_stats(
+                data,
+                file,
+                clipboard,
+                AnalysisInput {
+                    encoding,
+                    recipe,
+                    raw_input,
+                },
+                window as usize,
+                json,
+                cli.quiet,
+            ),
+            AnalyzeCommands::Carve {
+                data,
+                file,
+                clipboard,
+                encoding,
+                recipe,
+                raw_input,
+                output_dir,
+                force,
+                json,
+            } => run_analyze_carve(
+                data,
+                file,
+                clipboard,
+                AnalysisInput {
+                    encoding,
+                    recipe,
+                    raw_input,
+                },
+                output_dir,
+                force,
+                json,
+                cli.quiet,
+            ),
+            AnalyzeCommands::Xor {
+                data,
+                file,
+                clipboard,
+                encoding,
+                recipe,
+                raw_input,
+                known,
+                max_key_len,
+                candidates,
+                output,
+                force,
+                json,
+            } => run_analyze_xor(
+                data,
+                file,
+                clipboard,
+                AnalysisInput {
+                    encoding,
+                    recipe,
+                    raw_input,
+                },
+                known,
+                max_key_len,
+                candidates,
+                output,
+                force,
+                json,
+                cli.quiet,
+            ),
+        },
+        Commands::History { limit, json } => run_history(limit, json, cli.quiet),
+        Commands::Clean {
+            older_than,
+            dry_run,
+        } => run_clean(&older_than, dry_run, cli.quiet),
         Commands::Completions { shell } => {
             run_completions(shell);
             Ok(())
@@ -217,6 +808,8 @@ fn main() -> ExitCode {
 
     match result {
         Ok(_) => ExitCode::from(exit_codes::SUCCESS as u8),
+        // Output cut short by a closed pipe (e.g. `| head`) is not an error
+        Err(e) if is_broken_pipe(&e) => ExitCode::from(exit_codes::SUCCESS as u8),
         Err(e) => {
             eprintln!("Error: {}", e);
             if e.downcast_ref::<DecodeckError>().is_some() {
@@ -228,26 +821,50 @@ fn main() -> ExitCode {
     }
 }
 
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+    })
+}
+
 #[allow(clippy::too_many_arguments)]
 fn run_decode(
     data: Option<String>,
     file: Option<PathBuf>,
     clipboard: bool,
     output: Option<PathBuf>,
+    output_dir: Option<OutputDir>,
     encoding: Option<EncodingType>,
-    chain: bool,
-    max_depth: usize,
+    layers: Layers,
     json: bool,
     raw: bool,
     no_interactive: bool,
     force: bool,
+    overwrite: Overwrite,
     quiet: bool,
     max_size: String,
+    iocs: Option<IocOptions>,
+    stats: bool,
+    strings: Option<StringsOptions>,
+    archive_options: ArchiveOptions,
+    signatures: Option<PathBuf>,
+    hashes: HashOptions,
 ) -> Result<()> {
     let start = Instant::now();
+    let signatures = signatures
+        .map(|path| SignatureTable::load(&path))
+        .transpose()?;
 
     // Get input source (priority: argument > clipboard > file > stdin)
     let input = get_input(data, file, clipboard)?;
+    let source = describe_source(&input);
+    let source_stem = input
+        .path
+        .as_ref()
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().into_owned());
 
     // Validate input size
     input.validate_size(&max_size)?;
@@ -256,20 +873,53 @@ fn run_decode(
     let input_str =
         String::from_utf8(input.raw_data.clone()).context("Input is not valid UTF-8")?;
 
-    // Decode - either chain mode or single
-    let (decoded, encoding_info, legacy_encoded, chain_info) = if chain {
+    // A PowerShell command line carries its script as Base64 (UTF-16LE)
+    let powershell_argument = match encoding {
+        None => powershell::extract_encoded_command(&input_str).map(str::to_string),
+        Some(_) => None,
+    };
+    let input_str = powershell_argument.clone().unwrap_or(input_str);
+
+    // Decode - recipe, chain mode or single
+    let (decoded, encoding_info, legacy_encoded, chain_info) = if let Some(ref recipe) =
+        layers.recipe
+    {
+        let result = recipe.apply(&input_str)?;
+        let last_encoding = result
+            .steps
+            .iter()
+            .rev()
+            .find_map(|step| match step {
+                Step::Decode(encoding) => Some(EncodingInfo::explicit(*encoding)),
+                _ => None,
+            })
+            .ok_or_else(|| DecodeckError::InvalidRecipe {
+                step: recipe_string(&recipe.steps),
+                reason: "needs at least one encoding step".to_string(),
+            })?;
+        (result.data, last_encoding, None, Some(result.steps))
+    } else if layers.chain {
         // Chain decoding mode
-        let result = decodeck::encoding::chain::decode_chain(&input_str, Some(max_depth))?;
+        let result = if layers.ciphers {
+            decodeck::encoding::chain::decode_chain_with_ciphers(
+                &input_str,
+                Some(layers.max_depth),
+            )?
+        } else {
+            decodeck::encoding::chain::decode_chain(&input_str, Some(layers.max_depth))?
+        };
         let last_encoding = result
             .chain
             .last()
             .cloned()
             .unwrap_or_else(|| EncodingInfo::explicit(EncodingType::Base64));
-        (result.data, last_encoding, None, Some(result.chain))
+        (result.data, last_encoding, None, Some(result.layers))
     } else {
         // Single encoding mode
         let encoding_info = if let Some(enc_type) = encoding {
             EncodingInfo::explicit(enc_type)
+        } else if powershell_argument.is_some() {
+            EncodingInfo::detected(EncodingType::Base64, DetectionConfidence::High)
         } else {
             detect_encoding(&input_str)
         };
@@ -287,6 +937,27 @@ fn run_decode(
         (decoded, encoding_info, legacy_encoded, None)
     };
 
+    // Check the expected digest before anything is written
+    let mut hash_algorithms = hashes.algorithms;
+    if let Some(ref expected) = hashes.verify {
+        let algorithm = hash::verify(&decoded, expected)?;
+        if !hash_algorithms.contains(&algorithm) {
+            hash_algorithms.push(algorithm);
+        }
+    }
+
+    // Indicators are matched on the text, whatever charset it was in
+    let iocs = match iocs {
+        Some(options) => {
+            let found = charset::decode_text(&decoded)
+                .map(|(_, text)| ioc::extract(&text))
+                .unwrap_or_default();
+            options.export(&found)?;
+            found
+        }
+        None => vec![],
+    };
+
     // Raw mode - write directly to stdout and exit
     if raw {
         use std::io::Write;
@@ -296,7 +967,10 @@ fn run_decode(
     }
 
     // Detect content metadata
-    let metadata = magic::detect(&decoded);
+    let metadata = magic::detect_with(
+        &decoded,
+        signatures.as_ref().unwrap_or(SignatureTable::builtin()),
+    );
 
     // Check for stdout output (output="-")
     let write_to_stdout = output.as_ref().map(|p| p.as_os_str() == "-").unwrap_or(false);
@@ -308,27 +982,72 @@ fn run_decode(
         return Ok(());
     }
 
-    // Determine output path
-    let is_temp = output.is_none();
+    // Write output file; temporary outputs get a fresh, private file
+    let is_temp = output.is_none() && output_dir.is_none();
     let output_path = if let Some(path) = output {
-        // Check if file exists and handle overwrite
-        if path.exists() && !force {
-            return Err(DecodeckError::OutputExists {
-                path: path.display().to_string(),
-            }
-            .into());
-        }
-        path
+        write_output(&path, &decoded, overwrite)?
+    } else if let Some(output_dir) = output_dir {
+        let layers = match chain_info {
+            Some(ref steps) => steps.iter().map(Step::to_string).collect(),
+            None => vec![encoding_info.encoding_type.to_string()],
+        };
+        let name = naming::render(
+            &output_dir.template,
+            &NameContext {
+                source_stem: source_stem.as_deref(),
+                extension: &metadata.extension,
+                data: &decoded,
+                timestamp: history::now(),
+                layers,
+            },
+        )?;
+        fs::create_dir_all(&output_dir.dir)?;
+        write_output(&output_dir.dir.join(name), &decoded, overwrite)?
     } else {
-        // Create temporary file with appropriate extension
-        let temp_dir = std::env::temp_dir().join("decodeck");
-        fs::create_dir_all(&temp_dir)?;
-        let filename = format!("output{}", metadata.extension);
-        temp_dir.join(filename)
+        history::write_temp_output(&history::temp_dir(), &decoded, &metadata.extension)?
+    };
+
+    let mut warnings = vec![];
+
+    // A data URI declares its type; say so when the content disagrees
+    if chain_info.is_none() {
+        if let Some(declared) = data_uri::media_type(&input_str) {
+            warnings.extend(data_uri::mismatch_warning(&declared, &metadata));
+        }
+    }
+
+    // Flag content that runs code when opened
+    let risk = risk::classify(&decoded);
+    if let Some(ref risk) = risk {
+        warnings.push(risk.warning());
+    }
+
+    // List (and optionally unpack) archives; a damaged archive is still written
+    let archive = match archive::list(&decoded, archive_options.inspect) {
+        Ok(listing) => listing,
+        Err(e) => {
+            warnings.push(e.to_string());
+            None
+        }
+    };
+    let extracted = match archive_options.extract {
+        Some(ref dir) => archive::extract(&decoded, dir, force)?,
+        None => vec![],
     };
 
-    // Write output file
-    fs::write(&output_path, &decoded)?;
+    // A history that cannot be written does not fail the decode
+    let entry = HistoryEntry {
+        path: fs::canonicalize(&output_path).unwrap_or_else(|_| output_path.clone()),
+        created: history::now(),
+        source,
+        encoding: encoding_info.encoding_type.to_string(),
+        mime_type: metadata.mime_type.clone(),
+        size_bytes: decoded.len(),
+        is_temporary: is_temp,
+    };
+    if let Err(e) = history::record(&history::temp_dir(), &entry) {
+        warnings.push(format!("Could not record history: {}", e));
+    }
 
     let duration = start.elapsed();
 
@@ -345,8 +1064,18 @@ fn run_decode(
         metadata: metadata.clone(),
         encoding: legacy_encoded,
         encoding_info: encoding_info.clone(),
+        layers: chain_info.clone().unwrap_or_default(),
         duration_ms: duration.as_millis() as u64,
-        warnings: vec![],
+        warnings,
+        risk,
+        iocs,
+        stats: stats.then(|| ByteStats::compute(&decoded, stats::DEFAULT_WINDOW)),
+        strings: strings
+            .map(|options| strings_view::extract(&decoded, &options))
+            .unwrap_or_default(),
+        archive,
+        extracted,
+        hashes: Hashes::compute(&decoded, &hash_algorithms),
     };
 
     // Output result
@@ -356,17 +1085,18 @@ fn run_decode(
         decodeck::output::text::format(&result, &mut io::stdout())?;
 
         // Show chain info if available
-        if let Some(ref chain) = chain_info {
-            if chain.len() > 1 {
-                let chain_str: Vec<_> = chain.iter().map(|e| e.encoding_type.to_string()).collect();
-                println!("Chain: {} (depth: {})", chain_str.join(" → "), chain.len());
+        if let Some(ref steps) = chain_info {
+            if layers.recipe.is_some() {
+                println!("Recipe: {}", recipe_string(steps));
+            } else if steps.len() > 1 {
+                println!("Chain: {} (depth: {})", recipe_string(steps), steps.len());
             }
         }
     }
 
     // Interactive prompt for viewable/playable content
     let prompt = InteractivePrompt::new(quiet, no_interactive);
-    if prompt.show_and_wait(&metadata) {
+    if prompt.show_and_wait(&metadata, result.risk.as_ref()) {
         if let Err(e) = InteractivePrompt::open_file(&output_path) {
             if !quiet {
                 eprintln!("Warning: Could not open file: {}", e);
@@ -377,11 +1107,123 @@ fn run_decode(
     Ok(())
 }
 
+/// How `decode` peels layers beyond a single encoding
+struct Layers {
+    chain: bool,
+    max_depth: usize,
+    ciphers: bool,
+    recipe: Option<Recipe>,
+}
+
+/// How an existing output file is handled
+fn overwrite_mode(force: bool, backup: bool, suffix: bool) -> Overwrite {
+    if backup {
+        Overwrite::Backup
+    } else if suffix {
+        Overwrite::Suffix
+    } else if force {
+        Overwrite::Replace
+    } else {
+        Overwrite::Refuse
+    }
+}
+
+/// Directory and file name template for outputs
+struct OutputDir {
+    dir: PathBuf,
+    template: String,
+}
+
+/// What to do with decoded archives
+struct ArchiveOptions {
+    inspect: bool,
+    extract: Option<PathBuf>,
+}
+
+/// Hashes to report and the digest to verify
+struct HashOptions {
+    algorithms: Vec<HashAlgorithm>,
+    verify: Option<String>,
+}
+
+fn recipe_string(steps: &[Step]) -> String {
+    let steps: Vec<_> = steps.iter().map(Step::to_string).collect();
+    steps.join(" → ")
+}
+
+fn run_history(limit: Option<usize>, json: bool, quiet: bool) -> Result<()> {
+    let entries = history::load(&history::temp_dir())?;
+    let skip = limit.map_or(0, |n| entries.len().saturating_sub(n));
+    let entries = &entries[skip..];
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(entries)?);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        if !quiet {
+            eprintln!("No decode history.");
+        }
+        return Ok(());
+    }
+
+    if !quiet {
+        println!(
+            "{:<20}  {:>10}  {:<10}  {:<24}  {:<12}  Output",
+            "Created", "Size", "Encoding", "Type", "Source"
+        );
+    }
+    for entry in entries {
+        let mut line = format!(
+            "{:<20}  {:>10}  {:<10}  {:<24}  {:<12}  {}",
+            entry.created_at(),
+            format_size(entry.size_bytes),
+            entry.encoding,
+            entry.mime_type,
+            entry.source,
+            entry.path.display()
+        );
+        if !entry.path.exists() {
+            line.push_str(" (removed)");
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn run_clean(older_than: &str, dry_run: bool, quiet: bool) -> Result<()> {
+    let age = history::parse_age(older_than)?;
+    let removed = history::clean(&history::temp_dir(), age, dry_run)?;
+    if quiet {
+        return Ok(());
+    }
+    for path in &removed {
+        println!("{}", path.display());
+    }
+    println!(
+        "{} {} file{}",
+        if dry_run { "Would remove" } else { "Removed" },
+        removed.len(),
+        if removed.len() == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
+
 fn run_completions(shell: Shell) {
     let mut cmd = Cli::command();
     generate(shell, &mut cmd, "decodeck", &mut io::stdout());
 }
 
+/// Where input came from, for the history
+fn describe_source(input: &InputSource) -> String {
+    match (input.source_type, &input.path) {
+        (SourceType::File, Some(path)) => path.display().to_string(),
+        (SourceType::File, None) => "file".to_string(),
+        (SourceType::Arg, _) => "argument".to_string(),
+        (SourceType::Stdin, _) => "stdin".to_string(),
+    }
+}
+
 fn get_input(data: Option<String>, file: Option<PathBuf>, clipboard: bool) -> Result<InputSource> {
     // Priority: argument > clipboard > file > stdin
     if let Some(arg_data) = data {
@@ -414,11 +1256,14 @@ fn get_input(data: Option<String>, file: Option<PathBuf>, clipboard: bool) -> Re
     Err(DecodeckError::NoInput.into())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_encode(
     data: Option<String>,
     file: Option<PathBuf>,
     clipboard: bool,
     encoding: EncodingType,
+    options: EncodeOptions,
+    literal: Option<LiteralOptions>,
     copy: bool,
     json: bool,
     quiet: bool,
@@ -444,9 +1289,13 @@ fn run_encode(
     if input_bytes.is_empty() {
         return Err(DecodeckError::NoInput.into());
     }
+    check_encode_options(encoding, &options)?;
 
     // Encode the data
-    let encoded = decodeck::encoding::encode::encode(&input_bytes, encoding)?;
+    let encoded = match &literal {
+        Some(options) => literal::to_literal(&input_bytes, options)?,
+        None => decodeck::encoding::encode::encode_with(&input_bytes, encoding, &options)?,
+    };
 
     // Copy to clipboard if requested
     if copy {
@@ -478,11 +1327,50 @@ fn run_encode(
     Ok(())
 }
 
+/// Reject encode options given for an encoding they do not apply to
+fn check_encode_options(encoding: EncodingType, options: &EncodeOptions) -> Result<()> {
+    use EncodingType::{Base32, Base64, Base85, Hex, Url};
+    let defaults = EncodeOptions::default();
+    let checks = [
+        ("--url-safe", options.url_safe, &[Base64][..]),
+        ("--no-padding", !options.padding, &[Base64, Base32]),
+        ("--wrap", options.wrap.is_some(), &[Base64, Base32, Hex, Base85]),
+        ("--uppercase", options.uppercase, &[Hex]),
+        ("--hex-format", options.hex_format != defaults.hex_format, &[Hex]),
+        ("--hex-group", options.hex_group.is_some(), &[Hex]),
+        (
+            "--base32-alphabet",
+            options.base32_alphabet != defaults.base32_alphabet,
+            &[Base32],
+        ),
+        ("--no-delimiters", !options.base85_delimiters, &[Base85]),
+        ("--url-charset", options.url_charset != defaults.url_charset, &[Url]),
+    ];
+    for (option, given, applies_to) in checks {
+        if given && !applies_to.contains(&encoding) {
+            return Err(DecodeckError::UnsupportedOption {
+                option: option.to_string(),
+                encoding: encoding.to_string(),
+            }
+            .into());
+        }
+    }
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
 fn run_scan(
     data: Option<String>,
     file: Option<PathBuf>,
     clipboard: bool,
     format: Option<ScanFormat>,
+    redact: bool,
+    secrets_only: bool,
+    expand: bool,
+    annotate: bool,
+    options: ScanOptions,
+    iocs: Option<IocOptions>,
+    raw: bool,
     json: bool,
     quiet: bool,
 ) -> Result<()> {
@@ -491,31 +1379,83 @@ fn run_scan(
     let input_str =
         String::from_utf8(input.raw_data.clone()).context("Input is not valid UTF-8")?;
 
+    // Use the hint, else the file extension, else the content
+    let format =
+        format.unwrap_or_else(|| scan::detect_format(&input_str, input.path.as_deref()));
+
+    // Expansion mode - print the document with findings decoded in place
+    if expand {
+        let expanded = scan::expand::expand_with(&input_str, format, annotate)?;
+        println!("{}", expanded);
+        return Ok(());
+    }
+
     // Scan based on format
-    let result = match format {
-        Some(ScanFormat::Json) => scan::scan_json(&input_str)?,
-        Some(ScanFormat::Xml) => scan::scan_xml(&input_str)?,
-        None => scan::scan_auto(&input_str)?,
+    let result = scan::scan_with(&input_str, format, &options)?;
+
+    let iocs = match iocs {
+        Some(options) => {
+            let found = ioc::dedup(
+                result
+                    .findings
+                    .iter()
+                    .filter(|f| f.is_text)
+                    .flat_map(|f| ioc::extract(&f.decoded)),
+            );
+            options.export(&found)?;
+            Some(found)
+        }
+        None => None,
     };
 
+    // Raw mode - print decoded values only (e.g. a single selected field)
+    if raw {
+        for finding in result.findings.iter().filter(|f| f.depth == 0) {
+            println!("{}", finding.decoded);
+        }
+        return Ok(());
+    }
+
+    // Redaction mode - print the document with findings replaced
+    if redact {
+        let mode = if secrets_only {
+            scan::redact::RedactMode::SecretsOnly
+        } else {
+            scan::redact::RedactMode::All
+        };
+        print!(
+            "{}",
+            scan::redact::redact(&input_str, &result.findings, mode)?
+        );
+        return Ok(());
+    }
+
     // Output
     if json {
-        let output = serde_json::json!({
+        let mut output = serde_json::json!({
             "success": true,
             "format": result.format,
             "values_scanned": result.values_scanned,
             "findings_count": result.findings.len(),
             "findings": result.findings.iter().map(|f| {
-                serde_json::json!({
+                let mut finding = serde_json::json!({
                     "path": f.path,
                     "encoding": f.encoding.display_name(),
                     "confidence": format!("{:?}", f.confidence).to_lowercase(),
                     "original": f.original,
                     "decoded": f.decoded,
-                    "is_text": f.is_text
-                })
+                    "is_text": f.is_text,
+                    "depth": f.depth
+                });
+                if let Some(ref context) = f.context {
+                    finding["context"] = serde_json::json!(context);
+                }
+                finding
             }).collect::<Vec<_>>()
         });
+        if let Some(ref iocs) = iocs {
+            output["iocs"] = serde_json::to_value(iocs)?;
+        }
         println!("{}", serde_json::to_string_pretty(&output)?);
     } else {
         if !quiet {
@@ -538,21 +1478,336 @@ fn run_scan(
                 "   Decoded:  {}",
                 truncate_string(&finding.decoded, 60)
             );
+            if let Some(ref context) = finding.context {
+                println!("   Context:  {}", truncate_string(context, 60));
+            }
             println!();
         }
 
         if result.findings.is_empty() && !quiet {
             println!("No encoded content found.");
         }
+
+        if let Some(iocs) = iocs.filter(|iocs| !iocs.is_empty()) {
+            println!("IOCs:");
+            for ioc in &iocs {
+                println!("  {:<12} {}", ioc.kind.name(), ioc.value);
+            }
+        }
     }
 
     Ok(())
 }
 
+fn run_deobfuscate(
+    data: Option<String>,
+    file: Option<PathBuf>,
+    clipboard: bool,
+    language: Option<ScriptLanguage>,
+    annotate: bool,
+    json: bool,
+    quiet: bool,
+) -> Result<()> {
+    let input = get_input(data, file, clipboard)?;
+    let source = String::from_utf8(input.raw_data.clone()).context("Input is not valid UTF-8")?;
+
+    let language = language
+        .unwrap_or_else(|| deobfuscate::detect_language(&source, input.path.as_deref()));
+    let result = deobfuscate::deobfuscate(&source, language, annotate);
+
+    if json {
+        let output = serde_json::json!({
+            "success": true,
+            "language": result.language,
+            "replacements_count": result.replacements.len(),
+            "replacements": result.replacements,
+            "script": result.script,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+    } else {
+        print!("{}", result.script);
+        if !result.script.ends_with('\n') {
+            println!();
+        }
+        if result.replacements.is_empty() && !quiet {
+            eprintln!("No encoded payloads found.");
+        }
+    }
+
+    Ok(())
+}
+
+/// How `analyze` turns its input into the bytes to analyze
+struct AnalysisInput {
+    encoding: Option<EncodingType>,
+    recipe: Option<Recipe>,
+    raw_input: bool,
+}
+
+impl AnalysisInput {
+    /// Decode the input, falling back to the raw bytes when it is binary or
+    /// auto-detection finds no encoding that decodes it
+    fn decode(&self, input: &InputSource) -> Result<Vec<u8>> {
+        if self.raw_input {
+            return Ok(input.raw_data.clone());
+        }
+        let text = std::str::from_utf8(&input.raw_data);
+        if let Some(ref recipe) = self.recipe {
+            let text = text.context("Input is not valid UTF-8")?;
+            return Ok(recipe.apply(text.trim())?.data);
+        }
+        if let Some(encoding) = self.encoding {
+            let text = text.context("Input is not valid UTF-8")?;
+            return Ok(encoding.decoder().decode(text.trim())?);
+        }
+        match text {
+            Ok(text) => {
+                let decoder = detect_encoding(text.trim()).encoding_type.decoder();
+                Ok(decoder
+                    .decode(text.trim())
+                    .unwrap_or_else(|_| input.raw_data.clone()))
+            }
+            Err(_) => Ok(input.raw_data.clone()),
+        }
+    }
+}
+
+fn run_analyze_stats(
+    data: Option<String>,
+    file: Option<PathBuf>,
+    clipboard: bool,
+    decoding: AnalysisInput,
+    window: usize,
+    json: bool,
+    quiet: bool,
+) -> Result<()> {
+    let input = get_input(data, file, clipboard)?;
+    let data = decoding.decode(&input)?;
+    let stats = ByteStats::compute(&data, window);
+
+    if json {
+        let output = serde_json::json!({
+            "success": true,
+            "stats": stats,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if !quiet {
+        println!("Analyzed {}\n", format_size(data.len()));
+    }
+    decodeck::output::text::format_stats(&stats, &mut io::stdout())?;
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_analyze_carve(
+    data: Option<String>,
+    file: Option<PathBuf>,
+    clipboard: bool,
+    decoding: AnalysisInput,
+    output_dir: Option<PathBuf>,
+    force: bool,
+    json: bool,
+    quiet: bool,
+) -> Result<()> {
+    let input = get_input(data, file, clipboard)?;
+    let data = decoding.decode(&input)?;
+    let carved = carve::carve(&data);
+
+    let extracted = match output_dir {
+        Some(ref dir) => carve::extract(&data, &carved, dir, overwrite_mode(force, false, false))
+            .with_context(|| format!("Failed to extract to {}", dir.display()))?,
+        None => vec![],
+    };
+
+    if json {
+        let files: Vec<_> = carved
+            .iter()
+            .enumerate()
+            .map(|(i, c)| {
+                let mut file = serde_json::to_value(c).unwrap_or_default();
+                if let Some(path) = extracted.get(i) {
+                    file["path"] = serde_json::json!(path);
+                }
+                file
+            })
+            .collect();
+        let output = serde_json::json!({
+            "success": true,
+            "size_bytes": data.len(),
+            "files": files,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if carved.is_empty() {
+        if !quiet {
+            eprintln!("No embedded files found.");
+        }
+        return Ok(());
+    }
+
+    if !quiet {
+        println!("Analyzed {}\n", format_size(data.len()));
+        println!("{:<12} {:<12} Type", "Offset", "Size");
+    }
+    for (i, c) in carved.iter().enumerate() {
+        let mut line = format!(
+            "0x{:08x}   {:<12} {} ({})",
+            c.offset,
+            format_size(c.length),
+            c.mime_type,
+            c.extension
+        );
+        if !c.complete {
+            line.push_str(" [end not found]");
+        }
+        if let Some(path) = extracted.get(i) {
+            line.push_str(&format!(" -> {}", path.display()));
+        }
+        println!("{}", line);
+    }
+    if !quiet {
+        println!(
+            "\nFound {} embedded file{}",
+            carved.len(),
+            if carved.len() == 1 { "" } else { "s" }
+        );
+    }
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn run_analyze_xor(
+    data: Option<String>,
+    file: Option<PathBuf>,
+    clipboard: bool,
+    decoding: AnalysisInput,
+    known: Option<String>,
+    max_key_len: usize,
+    count: usize,
+    output: Option<PathBuf>,
+    force: bool,
+    json: bool,
+    quiet: bool,
+) -> Result<()> {
+    let input = get_input(data, file, clipboard)?;
+    let data = decoding.decode(&input)?;
+    let lengths = xor::estimate_key_lengths(&data, max_key_len);
+    let candidates = xor::crack(&data, max_key_len, count, known.as_deref().map(str::as_bytes));
+
+    let Some(best) = candidates.first() else {
+        return Err(DecodeckError::DecodeFailed {
+            message: "Not enough data to recover a key".to_string(),
+        }
+        .into());
+    };
+
+    if let Some(ref path) = output {
+        write_output(
+            path,
+            &xor::decrypt(&data, &best.key),
+            overwrite_mode(force, false, false),
+        )?;
+    }
+
+    use std::io::Write;
+
+    // The likeliest lengths, shown by score
+    let mut likely: Vec<_> = lengths.iter().take(5).collect();
+    likely.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+    let mut out = io::stdout().lock();
+    if json {
+        let output = serde_json::json!({
+            "success": true,
+            "size_bytes": data.len(),
+            "key_lengths": likely,
+            "candidates": candidates.iter().map(|c| {
+                serde_json::json!({
+                    "key": c.key_hex(),
+                    "key_text": c.key_text(),
+                    "key_length": c.key.len(),
+                    "score": c.score,
+                    "preview": c.preview,
+                })
+            }).collect::<Vec<_>>(),
+            "output": output,
+        });
+        writeln!(out, "{}", serde_json::to_string_pretty(&output)?)?;
+        return Ok(());
+    }
+
+    if !quiet {
+        writeln!(out, "Analyzed {}", format_size(data.len()))?;
+        let likely: Vec<_> = likely
+            .iter()
+            .map(|l| format!("{} ({:.2})", l.length, l.score))
+            .collect();
+        writeln!(out, "Likely key lengths: {}\n", likely.join(", "))?;
+    }
+    for (i, candidate) in candidates.iter().enumerate() {
+        let key = match candidate.key_text() {
+            Some(text) => format!("{:?} ({})", text, candidate.key_hex()),
+            None => candidate.key_hex(),
+        };
+        writeln!(out, "{}. Key: {}  score {:.2}", i + 1, key, candidate.score)?;
+        writeln!(out, "   {}", candidate.preview)?;
+    }
+    if let Some(path) = output.filter(|_| !quiet) {
+        writeln!(out, "\nDecrypted with key 1: {}", path.display())?;
+    }
+
+    Ok(())
+}
+
+/// Settings for `--extract-iocs`
+struct IocOptions {
+    export: Option<PathBuf>,
+    format: IocFormat,
+    overwrite: Overwrite,
+}
+
+impl IocOptions {
+    fn new(
+        extract: bool,
+        export: Option<PathBuf>,
+        format: IocFormat,
+        overwrite: Overwrite,
+    ) -> Option<Self> {
+        extract.then_some(Self {
+            export,
+            format,
+            overwrite,
+        })
+    }
+
+    /// Write indicators to the export file, if one was requested
+    fn export(&self, iocs: &[ioc::Ioc]) -> Result<()> {
+        if let Some(ref path) = self.export {
+            let content = ioc::export(iocs, self.format)?;
+            write_output(path, content.as_bytes(), self.overwrite)?;
+        }
+        Ok(())
+    }
+}
+
 fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
     } else {
-        format!("{}...", &s[..max_len])
+        // Cut at the last character that fits, not inside one
+        let end = s
+            .char_indices()
+            .map(|(i, c)| i + c.len_utf8())
+            .take_while(|&end| end <= max_len)
+            .last()
+            .unwrap_or(0);
+        format!("{}...", &s[..end])
     }
 }
@@ -1,7 +1,10 @@
 //! Nested/chained encoding detection and decoding
 
-use super::{detect::detect_encoding, DetectionConfidence, EncodingInfo};
+use super::cipher;
+use super::recipe::Step;
+use super::{detect::detect_encoding, powershell, DetectionConfidence, EncodingInfo, EncodingType};
 use crate::error::DecodeckError;
+use crate::metadata::charset;
 
 /// Maximum recursion depth to prevent infinite loops
 const MAX_CHAIN_DEPTH: usize = 10;
@@ -13,19 +16,97 @@ pub struct ChainResult {
     pub data: Vec<u8>,
     /// Chain of encodings detected (in order of decoding)
     pub chain: Vec<EncodingInfo>,
+    /// Every layer peeled, including cipher layers (in order of decoding)
+    pub layers: Vec<Step>,
     /// Whether max depth was reached
     pub truncated: bool,
 }
 
 /// Decode nested/chained encodings recursively
+///
+/// A PowerShell command line is reduced to its `-EncodedCommand` argument
+/// first. Decoded layers in other text encodings (such as UTF-16LE) are
+/// transcoded so further layers inside them can be peeled.
 pub fn decode_chain(input: &str, max_depth: Option<usize>) -> Result<ChainResult, DecodeckError> {
+    decode_input(input, max_depth, false)
+}
+
+/// Decode nested/chained encodings, also looking for cipher layers
+///
+/// Every decoded layer that does not read as plaintext is tried against
+/// ROT13, ROT47, Atbash, all Caesar shifts and all single-byte XOR keys; the
+/// best result is kept if it scores as plaintext (see [`cipher::crack`]).
+pub fn decode_chain_with_ciphers(
+    input: &str,
+    max_depth: Option<usize>,
+) -> Result<ChainResult, DecodeckError> {
+    decode_input(input, max_depth, true)
+}
+
+fn decode_input(
+    input: &str,
+    max_depth: Option<usize>,
+    ciphers: bool,
+) -> Result<ChainResult, DecodeckError> {
+    // An encoded command is always Base64
+    match powershell::extract_encoded_command(input) {
+        Some(argument) => decode_layers(
+            argument,
+            Some(EncodingInfo::detected(
+                EncodingType::Base64,
+                DetectionConfidence::High,
+            )),
+            max_depth,
+            ciphers,
+        ),
+        None => decode_layers(input, None, max_depth, ciphers),
+    }
+}
+
+/// Decode nested/chained encodings whose outermost layer is already known
+///
+/// Used where the context names the encoding, such as the argument of
+/// `atob()` in a script. Inner layers are detected as in [`decode_chain`].
+pub fn decode_chain_as(
+    input: &str,
+    encoding: EncodingType,
+    max_depth: Option<usize>,
+) -> Result<ChainResult, DecodeckError> {
+    decode_layers(
+        input,
+        Some(EncodingInfo::explicit(encoding)),
+        max_depth,
+        false,
+    )
+}
+
+fn decode_layers(
+    input: &str,
+    first: Option<EncodingInfo>,
+    max_depth: Option<usize>,
+    ciphers: bool,
+) -> Result<ChainResult, DecodeckError> {
     let max = max_depth.unwrap_or(MAX_CHAIN_DEPTH);
     let mut current = input.to_string();
     let mut chain = Vec::new();
+    let mut layers = Vec::new();
+    let crack = |decoded: Vec<u8>, layers: &mut Vec<Step>| match ciphers
+        .then(|| cipher::crack(&decoded))
+        .flatten()
+    {
+        Some(cracked) => {
+            layers.push(Step::Cipher(cracked.cipher));
+            cracked.data
+        }
+        None => decoded,
+    };
+    let mut first = first;
 
     for depth in 0..max {
-        // Detect encoding
-        let info = detect_encoding(&current);
+        let info = match first.take() {
+            Some(info) => info,
+            None => detect_encoding(&current),
+        };
 
         // Only continue if we have reasonable confidence
         if info.confidence == DetectionConfidence::Low && depth > 0 {
@@ -37,13 +118,21 @@ pub fn decode_chain(input: &str, max_depth: Option<usize>) -> Result<ChainResult
         let decoder = info.encoding_type.decoder();
         match decoder.decode(&current) {
             Ok(decoded) => {
+                layers.push(Step::Decode(info.encoding_type));
                 chain.push(info);
+                let decoded = crack(decoded, &mut layers);
 
-                // Check if result is valid UTF-8 and could be another encoding
-                if let Ok(decoded_str) = String::from_utf8(decoded.clone()) {
-                    // Check if it looks like it could be encoded data
-                    if could_be_encoded(&decoded_str) && depth + 1 < max {
-                        current = decoded_str;
+                // Check if result is text and could be another encoding
+                let next = match String::from_utf8(decoded.clone()) {
+                    Ok(decoded_str) if could_be_encoded(&decoded_str) => Some(decoded_str),
+                    // Text in another charset, e.g. UTF-16LE from PowerShell
+                    _ => charset::decode_text(&decoded)
+                        .map(|(_, text)| text)
+                        .filter(|text| could_be_encoded(text)),
+                };
+                if let Some(next) = next {
+                    if depth + 1 < max {
+                        current = next;
                         continue;
                     }
                 }
@@ -52,6 +141,7 @@ pub fn decode_chain(input: &str, max_depth: Option<usize>) -> Result<ChainResult
                 return Ok(ChainResult {
                     data: decoded,
                     chain,
+                    layers,
                     truncated: false,
                 });
             }
@@ -76,11 +166,13 @@ pub fn decode_chain(input: &str, max_depth: Option<usize>) -> Result<ChainResult
         let decoder = info.encoding_type.decoder();
         if let Ok(decoded) = decoder.decode(&current) {
             if !chain.iter().any(|c| c.encoding_type == info.encoding_type) || chain.is_empty() {
+                layers.push(Step::Decode(info.encoding_type));
                 chain.push(info);
             }
             return Ok(ChainResult {
-                data: decoded,
+                data: crack(decoded, &mut layers),
                 chain,
+                layers,
                 truncated,
             });
         }
@@ -89,6 +181,7 @@ pub fn decode_chain(input: &str, max_depth: Option<usize>) -> Result<ChainResult
     Ok(ChainResult {
         data: current.into_bytes(),
         chain,
+        layers,
         truncated,
     })
 }
@@ -138,7 +231,6 @@ fn could_be_encoded(s: &str) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::encoding::EncodingType;
 
     #[test]
     fn test_single_encoding() {
@@ -168,12 +260,63 @@ mod tests {
         assert!(result.chain.len() >= 2);
     }
 
+    #[test]
+    fn test_ascii85_inside_utf16() {
+        // Base64 of Ascii85("Hello World!") encoded as UTF-16LE
+        let result =
+            decode_chain("PAB+ADgANwBjAFUAUgBEAF0AaQAsACIARQBiAG8AOAAwAH4APgA=", None).unwrap();
+        assert_eq!(result.data, b"Hello World!");
+        assert_eq!(result.chain[1].encoding_type, EncodingType::Base85);
+    }
+
+    #[test]
+    fn test_powershell_command_line() {
+        // Base64 of "0x4869" encoded as UTF-16LE
+        let result = decode_chain("powershell.exe -NoP -enc MAB4ADQAOAA2ADkA", None).unwrap();
+        assert_eq!(result.chain[0].encoding_type, EncodingType::Base64);
+        assert_eq!(result.data, b"Hi");
+    }
+
     #[test]
     fn test_max_depth() {
         let result = decode_chain("SGVsbG8=", Some(1)).unwrap();
         assert_eq!(result.chain.len(), 1);
     }
 
+    #[test]
+    fn test_cipher_layers_need_opt_in() {
+        // Base64 of "attack at dawn, bring the maps" XORed with 0x2a
+        let input = "S15eS0lBCkteCk5LXUQGCkhYQ0RNCl5CTwpHS1pZ";
+        assert_ne!(
+            decode_chain(input, None).unwrap().data,
+            b"attack at dawn, bring the maps"
+        );
+
+        let result = decode_chain_with_ciphers(input, None).unwrap();
+        assert_eq!(result.data, b"attack at dawn, bring the maps");
+        assert_eq!(
+            result.layers,
+            [
+                Step::Decode(EncodingType::Base64),
+                Step::Cipher(cipher::Cipher::Xor(0x2a))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rot13_under_base64() {
+        let result = decode_chain_with_ciphers(
+            "R3V2ZiB2ZiBuIGNyZXNycGd5bCBhYmV6bnkgZnJhZ3JhcHIgbm9iaGcgZ3VyIGpybmd1cmUgZ2Jxbmwu",
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            result.data,
+            b"This is a perfectly normal sentence about the weather today."
+        );
+        assert_eq!(result.layers[1], Step::Cipher(cipher::Cipher::Rot13));
+    }
+
     #[test]
     fn test_could_be_encoded() {
         assert!(could_be_encoded("0x48656c6c6f"));
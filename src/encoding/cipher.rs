@@ -0,0 +1,311 @@
+//! Classical cipher layers: ROT13, ROT47, Caesar, Atbash and single-byte XOR
+//!
+//! These are not encodings, they have no alphabet to detect, so candidates
+//! are ranked instead: results that look like English text or start with
+//! recognisable magic bytes score highest. Brute force tries every Caesar
+//! shift and every XOR key and keeps the best-scoring result.
+
+use crate::metadata::magic;
+use serde::Serialize;
+
+/// Score at which a result is taken to be plaintext
+pub const PLAINTEXT_SCORE: f64 = 0.5;
+
+/// Minimum improvement over the input for a cracked layer to be accepted
+const MIN_IMPROVEMENT: f64 = 0.25;
+
+/// Shorter inputs give too little evidence to tell keys apart
+const MIN_CRACK_LEN: usize = 8;
+
+/// Relative frequencies of a-z in English text
+pub(crate) const ENGLISH_FREQUENCIES: [f64; 26] = [
+    0.0817, 0.0149, 0.0278, 0.0425, 0.1270, 0.0223, 0.0202, 0.0609, 0.0697, 0.0015, 0.0077, 0.0403,
+    0.0241, 0.0675, 0.0751, 0.0193, 0.0010, 0.0599, 0.0633, 0.0906, 0.0276, 0.0098, 0.0236, 0.0015,
+    0.0197, 0.0007,
+];
+
+/// A reversible transformation applied on top of (or under) an encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "cipher", content = "key", rename_all = "lowercase")]
+pub enum Cipher {
+    /// Rotate letters by 13
+    Rot13,
+    /// Rotate printable ASCII (`!` to `~`) by 47
+    Rot47,
+    /// Shift letters back by the key
+    Caesar(u8),
+    /// Mirror the alphabet (a↔z)
+    Atbash,
+    /// XOR every byte with the key
+    Xor(u8),
+}
+
+/// Family of ciphers searched by brute force
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CipherFamily {
+    /// All 26 Caesar shifts
+    Caesar,
+    /// All 256 single-byte XOR keys
+    Xor,
+}
+
+/// Result of applying a cipher, with its plaintext score
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    /// Cipher that produced the data
+    pub cipher: Cipher,
+    /// Transformed bytes
+    pub data: Vec<u8>,
+    /// Plaintext score between 0 and 1
+    pub score: f64,
+}
+
+impl Cipher {
+    /// Undo the cipher
+    ///
+    /// Letter ciphers leave bytes other than ASCII letters unchanged.
+    pub fn apply(&self, data: &[u8]) -> Vec<u8> {
+        match *self {
+            Cipher::Rot13 => shift_letters(data, 13),
+            Cipher::Rot47 => data
+                .iter()
+                .map(|&b| match b {
+                    b'!'..=b'~' => b'!' + (b - b'!' + 47) % 94,
+                    _ => b,
+                })
+                .collect(),
+            Cipher::Caesar(shift) => shift_letters(data, 26 - shift % 26),
+            Cipher::Atbash => data
+                .iter()
+                .map(|&b| match b {
+                    b'a'..=b'z' => b'z' - (b - b'a'),
+                    b'A'..=b'Z' => b'Z' - (b - b'A'),
+                    _ => b,
+                })
+                .collect(),
+            Cipher::Xor(key) => data.iter().map(|b| b ^ key).collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for Cipher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cipher::Rot13 => write!(f, "rot13"),
+            Cipher::Rot47 => write!(f, "rot47"),
+            Cipher::Caesar(shift) => write!(f, "caesar:{}", shift),
+            Cipher::Atbash => write!(f, "atbash"),
+            Cipher::Xor(key) => write!(f, "xor:0x{:02x}", key),
+        }
+    }
+}
+
+impl std::fmt::Display for CipherFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CipherFamily::Caesar => write!(f, "caesar"),
+            CipherFamily::Xor => write!(f, "xor"),
+        }
+    }
+}
+
+/// Try every key of a cipher family, best result first
+pub fn brute_force(data: &[u8], family: CipherFamily) -> Vec<Candidate> {
+    let ciphers: Vec<Cipher> = match family {
+        CipherFamily::Caesar => (0..26).map(Cipher::Caesar).collect(),
+        CipherFamily::Xor => (0..=255).map(Cipher::Xor).collect(),
+    };
+    rank(data, ciphers)
+}
+
+/// Find a cipher layer hiding plaintext
+///
+/// Every cipher and key is scored against the input itself, and the best
+/// candidate is returned if it scores as plaintext and clearly better than
+/// the input. XOR keys are always tried: text XORed with a key such as 0x2a
+/// can stay entirely printable.
+pub fn crack(data: &[u8]) -> Option<Candidate> {
+    if data.len() < MIN_CRACK_LEN {
+        return None;
+    }
+    let baseline = score(data);
+    if baseline >= PLAINTEXT_SCORE {
+        return None;
+    }
+
+    let mut ciphers = vec![Cipher::Rot13, Cipher::Rot47, Cipher::Atbash];
+    ciphers.extend((1..26).filter(|&s| s != 13).map(Cipher::Caesar));
+    ciphers.extend((1..=255).map(Cipher::Xor));
+
+    rank(data, ciphers)
+        .into_iter()
+        .next()
+        .filter(|c| c.score >= PLAINTEXT_SCORE && c.score - baseline >= MIN_IMPROVEMENT)
+}
+
+/// Score how much data looks like plaintext, between 0 and 1
+///
+/// Recognised file formats score 1, anything else is scored as text.
+pub fn score(data: &[u8]) -> f64 {
+    if has_magic(data) {
+        return 1.0;
+    }
+    text_score(data)
+}
+
+/// Score how much data looks like English text, between 0 and 1
+///
+/// Combines the share of printable bytes, the share of letters and spaces,
+/// and how well the letter distribution matches English, so that both binary
+/// noise and letter soup score low. Order is ignored, so every n-th byte of
+/// a text scores like the text itself.
+pub fn text_score(data: &[u8]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+
+    let printable = printable_ratio(data);
+    let mut counts = [0usize; 26];
+    let (mut uppercase, mut spaces) = (0, 0);
+    for &b in data {
+        match b {
+            b'a'..=b'z' => counts[(b - b'a') as usize] += 1,
+            b'A'..=b'Z' => {
+                counts[(b - b'A') as usize] += 1;
+                uppercase += 1;
+            }
+            b' ' => spaces += 1,
+            _ => {}
+        }
+    }
+    let letters: usize = counts.iter().sum();
+    if letters == 0 {
+        return printable.powi(4) * 0.1;
+    }
+
+    // Average log-likelihood per letter: about -2.9 for English, -4 for
+    // uniformly random letters
+    let log_likelihood = counts
+        .iter()
+        .zip(ENGLISH_FREQUENCIES)
+        .map(|(&n, freq)| n as f64 * freq.ln())
+        .sum::<f64>()
+        / letters as f64;
+    let fit = ((log_likelihood + 4.5) / 2.0).clamp(0.0, 1.0);
+    // Case-flipped text (XOR with 0x20) has the same letters, so mostly
+    // lowercase text wins ties
+    let wordy = (letters + spaces) as f64 - uppercase as f64 / 2.0;
+    let wordy = wordy / data.len() as f64;
+
+    printable.powi(4) * fit * wordy
+}
+
+/// Check whether data starts with magic bytes of a known file format
+pub fn has_magic(data: &[u8]) -> bool {
+    magic::detect(data).is_signature_match()
+}
+
+/// Apply each cipher and sort the results by score, keeping the earlier
+/// cipher on ties
+fn rank(data: &[u8], ciphers: Vec<Cipher>) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = ciphers
+        .into_iter()
+        .map(|cipher| {
+            let data = cipher.apply(data);
+            let score = score(&data);
+            Candidate {
+                cipher,
+                data,
+                score,
+            }
+        })
+        .collect();
+    candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+    candidates
+}
+
+fn shift_letters(data: &[u8], shift: u8) -> Vec<u8> {
+    data.iter()
+        .map(|&b| match b {
+            b'a'..=b'z' => b'a' + (b - b'a' + shift) % 26,
+            b'A'..=b'Z' => b'A' + (b - b'A' + shift) % 26,
+            _ => b,
+        })
+        .collect()
+}
+
+fn printable_ratio(data: &[u8]) -> f64 {
+    let printable = data
+        .iter()
+        .filter(|&&b| b.is_ascii_graphic() || matches!(b, b' ' | b'\t' | b'\n' | b'\r'))
+        .count();
+    printable as f64 / data.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAINTEXT: &[u8] = b"This is a perfectly normal sentence about the weather today.";
+
+    #[test]
+    fn test_letter_ciphers_roundtrip() {
+        assert_eq!(Cipher::Rot13.apply(b"Uryyb, Jbeyq!"), b"Hello, World!");
+        assert_eq!(Cipher::Rot47.apply(b"w6==@[ (@C=5P"), b"Hello, World!");
+        assert_eq!(Cipher::Atbash.apply(b"Svool"), b"Hello");
+        assert_eq!(Cipher::Caesar(3).apply(b"Khoor"), b"Hello");
+        assert_eq!(
+            Cipher::Xor(0x2a).apply(&Cipher::Xor(0x2a).apply(PLAINTEXT)),
+            PLAINTEXT
+        );
+    }
+
+    #[test]
+    fn test_score_prefers_english() {
+        let rotated = Cipher::Rot13.apply(PLAINTEXT);
+        assert!(score(PLAINTEXT) >= PLAINTEXT_SCORE);
+        assert!(score(&rotated) < score(PLAINTEXT));
+        assert!(score(&Cipher::Xor(0x5a).apply(PLAINTEXT)) < 0.1);
+        assert_eq!(score(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), 1.0);
+    }
+
+    #[test]
+    fn test_brute_force_caesar() {
+        let encrypted = shift_letters(PLAINTEXT, 7);
+        let best = &brute_force(&encrypted, CipherFamily::Caesar)[0];
+        assert_eq!(best.cipher, Cipher::Caesar(7));
+        assert_eq!(best.data, PLAINTEXT);
+    }
+
+    #[test]
+    fn test_brute_force_xor() {
+        let encrypted = Cipher::Xor(0x41).apply(PLAINTEXT);
+        let best = &brute_force(&encrypted, CipherFamily::Xor)[0];
+        assert_eq!(best.cipher, Cipher::Xor(0x41));
+    }
+
+    #[test]
+    fn test_crack() {
+        let cracked = crack(&Cipher::Rot13.apply(PLAINTEXT)).unwrap();
+        assert_eq!(cracked.cipher, Cipher::Rot13);
+
+        let png = Cipher::Xor(0x99).apply(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+        assert_eq!(crack(&png).unwrap().cipher, Cipher::Xor(0x99));
+
+        assert!(crack(PLAINTEXT).is_none());
+        assert!(crack(b"short").is_none());
+    }
+
+    #[test]
+    fn test_crack_printable_xor() {
+        let encrypted = Cipher::Xor(0x2a).apply(b"attack at dawn and bring the maps");
+        assert!(encrypted
+            .iter()
+            .all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace()));
+
+        let cracked = crack(&encrypted).unwrap();
+        assert_eq!(cracked.cipher, Cipher::Xor(0x2a));
+        assert_eq!(cracked.data, b"attack at dawn and bring the maps");
+    }
+}
@@ -0,0 +1,232 @@
+//! Data URIs (RFC 2397)
+//!
+//! `data:[<media type>][;<parameter>=<value>]*[;base64],<data>` strings embed
+//! files in HTML, CSS and HAR captures. The payload is Base64 when the
+//! `;base64` flag is present and percent-encoded otherwise.
+
+use super::base64::Base64Decoder;
+use super::{Decoder, DetectionConfidence};
+use crate::error::DecodeckError;
+use crate::metadata::ContentMetadata;
+use base64::Engine;
+use percent_encoding::percent_decode_str;
+
+/// Media type assumed when a data URI declares none
+pub const DEFAULT_MEDIA_TYPE: &str = "text/plain";
+
+/// Media types with more than one name, mapped to the one detection reports
+const ALIASES: &[(&str, &str)] = &[
+    ("image/jpg", "image/jpeg"),
+    ("image/x-png", "image/png"),
+    ("image/x-icon", "image/vnd.microsoft.icon"),
+    ("application/x-javascript", "text/javascript"),
+    ("application/javascript", "text/javascript"),
+];
+
+/// A parsed data URI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataUri {
+    /// Declared media type, lower-cased, without parameters
+    pub media_type: String,
+    /// Declared `charset` parameter
+    pub charset: Option<String>,
+    /// Whether the payload is Base64
+    pub base64: bool,
+    pub data: Vec<u8>,
+}
+
+/// Whether input looks like a data URI
+pub fn is_data_uri(input: &str) -> bool {
+    let input = input.trim();
+    input
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+        && input.contains(',')
+}
+
+/// Parse a data URI and decode its payload
+pub fn parse(input: &str) -> Result<DataUri, DecodeckError> {
+    let input = input.trim();
+    if !is_data_uri(input) {
+        return Err(DecodeckError::DecodeFailed {
+            message: "Invalid data URI: expected \"data:[<type>][;base64],<data>\"".to_string(),
+        });
+    }
+    let (header, payload) = input[5..].split_once(',').unwrap_or_default();
+    let (media_type, charset, base64) = parse_header(header);
+
+    // Base64 payloads may themselves be percent-encoded
+    let payload: Vec<u8> = percent_decode_str(payload).collect();
+    let data = if base64 {
+        let payload = String::from_utf8(payload).map_err(|_| DecodeckError::DecodeFailed {
+            message: "Invalid data URI: Base64 payload is not ASCII".to_string(),
+        })?;
+        if payload.trim().is_empty() {
+            Vec::new()
+        } else {
+            Base64Decoder.decode(&payload)?
+        }
+    } else {
+        payload
+    };
+
+    Ok(DataUri {
+        media_type,
+        charset,
+        base64,
+        data,
+    })
+}
+
+/// Declared media type of a data URI, without decoding its payload
+pub fn media_type(input: &str) -> Option<String> {
+    let input = input.trim();
+    if !is_data_uri(input) {
+        return None;
+    }
+    let (header, _) = input[5..].split_once(',')?;
+    Some(parse_header(header).0)
+}
+
+/// Warning when content does not look like the media type it was declared as
+///
+/// Weak detections (keyword guesses, unknown binary) are not taken as
+/// evidence against the declaration. Plain text only contradicts
+/// declarations of a different top-level type.
+pub fn mismatch_warning(declared: &str, detected: &ContentMetadata) -> Option<String> {
+    let detected_type = detected.mime_type.split(';').next().unwrap_or_default();
+    if detected.confidence == DetectionConfidence::Low {
+        return None;
+    }
+    let canonical = |mime: &str| -> String {
+        let mime = mime.trim().to_lowercase();
+        ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == mime)
+            .map_or(mime, |(_, name)| name.to_string())
+    };
+    let (declared_canonical, detected_canonical) = (canonical(declared), canonical(detected_type));
+    let top_level = |mime: &str| mime.split('/').next().unwrap_or_default().to_string();
+    // Plain text is only a fallback, so it contradicts other kinds of
+    // content but not a more specific text type
+    let mismatch = if detected_canonical == "text/plain" {
+        top_level(&declared_canonical) != "text"
+    } else {
+        declared_canonical != detected_canonical
+    };
+    mismatch.then(|| {
+        format!(
+            "Data URI declares {} but the content looks like {}",
+            declared, detected_type
+        )
+    })
+}
+
+/// Media type (default `text/plain`), charset and Base64 flag of a header
+fn parse_header(header: &str) -> (String, Option<String>, bool) {
+    let mut parameters = header.split(';').map(str::trim);
+    let media_type = match parameters.next().unwrap_or_default() {
+        "" => DEFAULT_MEDIA_TYPE.to_string(),
+        media_type => media_type.to_lowercase(),
+    };
+    let mut charset = None;
+    let mut base64 = false;
+    for parameter in parameters {
+        match parameter.split_once('=') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("charset") => {
+                charset = Some(value.trim().trim_matches('"').to_string());
+            }
+            None if parameter.eq_ignore_ascii_case("base64") => base64 = true,
+            _ => {}
+        }
+    }
+    (media_type, charset, base64)
+}
+
+/// Build a Base64 data URI
+pub fn build(data: &[u8], media_type: &str) -> String {
+    format!(
+        "data:{};base64,{}",
+        media_type,
+        base64::engine::general_purpose::STANDARD.encode(data)
+    )
+}
+
+/// Data URI decoder implementation
+pub struct DataUriDecoder;
+
+impl Decoder for DataUriDecoder {
+    fn decode(&self, input: &str) -> Result<Vec<u8>, DecodeckError> {
+        parse(input).map(|uri| uri.data)
+    }
+
+    fn name(&self) -> &'static str {
+        "data-uri"
+    }
+
+    fn can_decode(&self, input: &str) -> bool {
+        is_data_uri(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_base64() {
+        let uri = parse("data:image/PNG;name=dot.png;base64,iVBORw0KGgo=").unwrap();
+        assert_eq!(uri.media_type, "image/png");
+        assert!(uri.base64);
+        assert_eq!(uri.data, b"\x89PNG\r\n\x1a\n");
+
+        let uri = parse("DATA:text/html;charset=UTF-8;base64,PGI%2BaGk8L2I%2B").unwrap();
+        assert_eq!(uri.charset.as_deref(), Some("UTF-8"));
+        assert_eq!(uri.data, b"<b>hi</b>");
+    }
+
+    #[test]
+    fn test_parse_percent_encoded() {
+        let uri = parse("data:,Hello%2C%20World%21").unwrap();
+        assert_eq!(uri.media_type, DEFAULT_MEDIA_TYPE);
+        assert!(!uri.base64);
+        assert_eq!(uri.data, b"Hello, World!");
+
+        let uri = parse("data:;charset=iso-8859-1,caf%E9").unwrap();
+        assert_eq!(uri.charset.as_deref(), Some("iso-8859-1"));
+        assert_eq!(uri.data, b"caf\xe9");
+    }
+
+    #[test]
+    fn test_mismatch_warning() {
+        let png = crate::metadata::magic::detect(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR");
+        assert!(mismatch_warning("image/png", &png).is_none());
+        let warning = mismatch_warning("image/gif", &png).unwrap();
+        assert!(warning.contains("declares image/gif"));
+
+        let jpeg = crate::metadata::magic::detect(b"\xff\xd8\xff\xe0\0\x10JFIF\0");
+        assert!(mismatch_warning("image/jpg", &jpeg).is_none());
+        let text = crate::metadata::magic::detect(b"just some words");
+        assert!(mismatch_warning("text/css", &text).is_none());
+        let warning = mismatch_warning("image/png", &text).unwrap();
+        assert!(warning.contains("looks like text/plain"));
+
+        assert_eq!(
+            media_type("data:IMAGE/GIF;base64,R0lG").as_deref(),
+            Some("image/gif")
+        );
+        assert_eq!(media_type("data:,x").as_deref(), Some(DEFAULT_MEDIA_TYPE));
+        assert!(media_type("R0lG").is_none());
+    }
+
+    #[test]
+    fn test_build_and_reject() {
+        let uri = build(b"Hello", "text/plain");
+        assert_eq!(uri, "data:text/plain;base64,SGVsbG8=");
+        assert_eq!(parse(&uri).unwrap().data, b"Hello");
+
+        assert!(parse("SGVsbG8=").is_err());
+        assert!(parse("data:text/plain;base64").is_err());
+        assert!(parse("data:;base64,!!!").is_err());
+    }
+}
@@ -1,11 +1,21 @@
 //! Auto-detection of encoding type
 
-use super::{DetectionConfidence, EncodingInfo, EncodingType};
+use super::{data_uri, literal, DetectionConfidence, EncodingInfo, EncodingType};
 
 /// Detect the encoding type from input data
 pub fn detect_encoding(input: &str) -> EncodingInfo {
     let trimmed = input.trim();
 
+    // 0. Check for a data URI scheme
+    if data_uri::is_data_uri(trimmed) {
+        return EncodingInfo::detected(EncodingType::DataUri, DetectionConfidence::High);
+    }
+
+    // Source-code byte literals, which may also start with 0x
+    if let Some(confidence) = literal::detect(trimmed) {
+        return EncodingInfo::detected(EncodingType::Literal, confidence);
+    }
+
     // 1. Check for 0x prefix (definite hex)
     if trimmed.starts_with("0x") || trimmed.starts_with("0X") {
         return EncodingInfo::detected(EncodingType::Hex, DetectionConfidence::High);
@@ -92,6 +102,41 @@ fn is_likely_base32(input: &str) -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_detect_data_uri() {
+        let info = detect_encoding("data:image/png;base64,iVBORw0KGgo=");
+        assert_eq!(info.encoding_type, EncodingType::DataUri);
+        assert_eq!(info.confidence, DetectionConfidence::High);
+        // Percent-encoded payloads are still data URIs, not URL encoding
+        let info = detect_encoding("data:,Hello%20World");
+        assert_eq!(info.encoding_type, EncodingType::DataUri);
+    }
+
+    #[test]
+    fn test_detect_literal() {
+        let info = detect_encoding("0x48, 0x69");
+        assert_eq!(info.encoding_type, EncodingType::Hex);
+        let info = detect_encoding("{0x48, 0x69}");
+        assert_eq!(info.encoding_type, EncodingType::Literal);
+        assert_eq!(info.confidence, DetectionConfidence::Medium);
+        let info = detect_encoding("b\"\\x48\\x69\"");
+        assert_eq!(info.encoding_type, EncodingType::Literal);
+        assert_eq!(info.confidence, DetectionConfidence::High);
+        let info = detect_encoding("unsigned char data[] = {0x48, 0x69};");
+        assert_eq!(info.encoding_type, EncodingType::Literal);
+        assert_eq!(info.confidence, DetectionConfidence::High);
+
+        // Short lists of plain numbers are data, not byte arrays
+        assert_ne!(
+            detect_encoding("{1, 2}").encoding_type,
+            EncodingType::Literal
+        );
+        assert_ne!(
+            detect_encoding("[10, 20, 30]").encoding_type,
+            EncodingType::Literal
+        );
+    }
+
     #[test]
     fn test_detect_hex_with_prefix() {
         let info = detect_encoding("0x48656c6c6f");
@@ -3,45 +3,315 @@
 use crate::encoding::EncodingType;
 use crate::error::DecodeckError;
 use base64::Engine;
+use clap::ValueEnum;
+use percent_encoding::{AsciiSet, NON_ALPHANUMERIC};
+
+/// RFC 3986 unreserved characters are kept
+const COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Also keeps the separators and sub-delimiters allowed in a path
+const PATH: &AsciiSet = &COMPONENT
+    .remove(b'/')
+    .remove(b':')
+    .remove(b'@')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=');
+
+/// Like a path, plus `?`, but encoding `&`, `=` and `+` so a value cannot
+/// split a query string
+const QUERY: &AsciiSet = &PATH.remove(b'?').add(b'&').add(b'=').add(b'+');
+
+/// `application/x-www-form-urlencoded`, before spaces become `+`
+const FORM: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'*')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_');
+
+/// How hex bytes are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum HexFormat {
+    /// `48656c`
+    #[default]
+    Plain,
+    /// `48:65:6c`
+    Colon,
+    /// `48 65 6c`
+    Space,
+    /// `\x48\x65\x6c`
+    Escape,
+    /// `0x48656c`
+    Prefix,
+}
+
+/// Base32 alphabet
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Base32Alphabet {
+    /// RFC 4648 `A-Z2-7`
+    #[default]
+    Standard,
+    /// RFC 4648 extended hex `0-9A-V`
+    Hex,
+    /// Crockford's `0-9A-Z` without I, L, O and U; never padded
+    Crockford,
+}
+
+/// Characters left unencoded by URL encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum UrlCharset {
+    /// Encode everything but letters and digits
+    #[default]
+    All,
+    /// Keep RFC 3986 unreserved characters (`-._~`)
+    Component,
+    /// Also keep `/`, `:`, `@` and sub-delimiters
+    Path,
+    /// Like path plus `?`, but encode `&`, `=` and `+`
+    Query,
+    /// HTML form encoding: keep `*-._`, spaces become `+`
+    Form,
+}
+
+/// Line ending used when wrapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+}
+
+impl LineEnding {
+    fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::Crlf => "\r\n",
+        }
+    }
+}
+
+/// Variations on an encoding's output; the defaults match [`encode`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// Base64 URL-safe alphabet (`-_` instead of `+/`)
+    pub url_safe: bool,
+    /// `=` padding for Base64 and Base32
+    pub padding: bool,
+    /// Wrap Base64, Base32, hex and Ascii85 output at this many columns;
+    /// hex lines only break between groups, or between bytes if ungrouped
+    pub wrap: Option<usize>,
+    pub line_ending: LineEnding,
+    /// Uppercase hex digits
+    pub uppercase: bool,
+    pub hex_format: HexFormat,
+    /// Bytes per hex group; by default one group for plain and prefixed hex,
+    /// one byte otherwise
+    pub hex_group: Option<usize>,
+    pub base32_alphabet: Base32Alphabet,
+    /// Ascii85 `<~ ~>` delimiters
+    pub base85_delimiters: bool,
+    pub url_charset: UrlCharset,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            url_safe: false,
+            padding: true,
+            wrap: None,
+            line_ending: LineEnding::Lf,
+            uppercase: false,
+            hex_format: HexFormat::Plain,
+            hex_group: None,
+            base32_alphabet: Base32Alphabet::Standard,
+            base85_delimiters: true,
+            url_charset: UrlCharset::All,
+        }
+    }
+}
 
 /// Encode bytes to string using the specified encoding
 pub fn encode(data: &[u8], encoding: EncodingType) -> Result<String, DecodeckError> {
-    match encoding {
-        EncodingType::Base64 => Ok(encode_base64(data)),
-        EncodingType::Hex => Ok(encode_hex(data)),
-        EncodingType::Base32 => Ok(encode_base32(data)),
-        EncodingType::Url => encode_url(data),
-        EncodingType::Base85 => Ok(encode_base85(data)),
-    }
+    encode_with(data, encoding, &EncodeOptions::default())
+}
+
+/// Encode bytes to string using the specified encoding and options
+///
+/// Options that do not apply to the encoding are ignored.
+pub fn encode_with(
+    data: &[u8],
+    encoding: EncodingType,
+    options: &EncodeOptions,
+) -> Result<String, DecodeckError> {
+    let encoded = match encoding {
+        EncodingType::Base64 => encode_base64(data, options),
+        EncodingType::Hex => return Ok(encode_hex(data, options)),
+        EncodingType::Base32 => encode_base32(data, options),
+        EncodingType::Url => return encode_url(data, options.url_charset),
+        EncodingType::Base85 => encode_base85(data, options.base85_delimiters),
+        EncodingType::DataUri => return Ok(encode_data_uri(data)),
+        EncodingType::Literal => return super::literal::to_literal(data, &Default::default()),
+    };
+    Ok(match options.wrap {
+        Some(width) if width > 0 => wrap(&encoded, width, options.line_ending),
+        _ => encoded,
+    })
+}
+
+/// Break text into lines of `width` characters
+fn wrap(text: &str, width: usize, line_ending: LineEnding) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(width)
+        .map(|line| line.iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join(line_ending.as_str())
 }
 
 /// Encode to Base64
-fn encode_base64(data: &[u8]) -> String {
-    base64::engine::general_purpose::STANDARD.encode(data)
+fn encode_base64(data: &[u8], options: &EncodeOptions) -> String {
+    use base64::engine::general_purpose::{STANDARD, STANDARD_NO_PAD, URL_SAFE, URL_SAFE_NO_PAD};
+    match (options.url_safe, options.padding) {
+        (false, true) => STANDARD.encode(data),
+        (false, false) => STANDARD_NO_PAD.encode(data),
+        (true, true) => URL_SAFE.encode(data),
+        (true, false) => URL_SAFE_NO_PAD.encode(data),
+    }
 }
 
 /// Encode to hexadecimal
-fn encode_hex(data: &[u8]) -> String {
-    data_encoding::HEXLOWER.encode(data)
+///
+/// Wrapped lines hold as many whole groups as fit, so no group (or, without
+/// grouping, no byte) is split across lines.
+fn encode_hex(data: &[u8], options: &EncodeOptions) -> String {
+    // Prefix of each byte, prefix of each group, separator between groups
+    let (byte_prefix, group_prefix, separator, group) = match options.hex_format {
+        HexFormat::Plain => ("", "", " ", 0),
+        HexFormat::Colon => ("", "", ":", 1),
+        HexFormat::Space => ("", "", " ", 1),
+        HexFormat::Escape => ("\\x", "", " ", 0),
+        HexFormat::Prefix => ("", "0x", " ", 0),
+    };
+    let byte = |byte: &u8| {
+        if options.uppercase {
+            format!("{}{:02X}", byte_prefix, byte)
+        } else {
+            format!("{}{:02x}", byte_prefix, byte)
+        }
+    };
+    // Ungrouped output is a single group; it is broken between bytes
+    let (tokens, separator): (Vec<String>, &str) = match options.hex_group.unwrap_or(group) {
+        0 => {
+            let tokens = data.iter().map(byte).enumerate();
+            let tokens = tokens.map(|(i, token)| match i {
+                0 => format!("{}{}", group_prefix, token),
+                _ => token,
+            });
+            (tokens.collect(), "")
+        }
+        group => {
+            let groups = data
+                .chunks(group)
+                .map(|chunk| {
+                    let bytes: String = chunk.iter().map(byte).collect();
+                    format!("{}{}", group_prefix, bytes)
+                })
+                .collect();
+            (groups, separator)
+        }
+    };
+
+    let width = options.wrap.filter(|&width| width > 0);
+    let mut out = String::new();
+    let mut column = 0;
+    for token in tokens {
+        if column > 0 {
+            if width.is_some_and(|width| column + separator.len() + token.len() > width) {
+                out.push_str(options.line_ending.as_str());
+                column = 0;
+            } else {
+                out.push_str(separator);
+                column += separator.len();
+            }
+        }
+        column += token.len();
+        out.push_str(&token);
+    }
+    out
 }
 
 /// Encode to Base32
-fn encode_base32(data: &[u8]) -> String {
-    data_encoding::BASE32.encode(data)
+fn encode_base32(data: &[u8], options: &EncodeOptions) -> String {
+    let encoding = match (options.base32_alphabet, options.padding) {
+        (Base32Alphabet::Standard, true) => data_encoding::BASE32,
+        (Base32Alphabet::Standard, false) => data_encoding::BASE32_NOPAD,
+        (Base32Alphabet::Hex, true) => data_encoding::BASE32HEX,
+        (Base32Alphabet::Hex, false) => data_encoding::BASE32HEX_NOPAD,
+        (Base32Alphabet::Crockford, _) => {
+            let mut spec = data_encoding::Specification::new();
+            spec.symbols.push_str("0123456789ABCDEFGHJKMNPQRSTVWXYZ");
+            spec.encoding().expect("valid Crockford alphabet")
+        }
+    };
+    encoding.encode(data)
 }
 
 /// Encode to URL percent-encoding
-fn encode_url(data: &[u8]) -> Result<String, DecodeckError> {
+fn encode_url(data: &[u8], charset: UrlCharset) -> Result<String, DecodeckError> {
     let s = std::str::from_utf8(data).map_err(|e| DecodeckError::DecodeFailed {
         message: format!("URL encoding requires valid UTF-8: {}", e),
     })?;
-    Ok(percent_encoding::utf8_percent_encode(s, percent_encoding::NON_ALPHANUMERIC).to_string())
+    let set = match charset {
+        UrlCharset::All => NON_ALPHANUMERIC,
+        UrlCharset::Component => COMPONENT,
+        UrlCharset::Path => PATH,
+        UrlCharset::Query => QUERY,
+        UrlCharset::Form => FORM,
+    };
+    let encoded = percent_encoding::utf8_percent_encode(s, set).to_string();
+    Ok(match charset {
+        // A literal `+` was encoded as %2B, so this is unambiguous
+        UrlCharset::Form => encoded.replace("%20", "+"),
+        _ => encoded,
+    })
 }
 
 /// Encode to Ascii85
-fn encode_base85(data: &[u8]) -> String {
+fn encode_base85(data: &[u8], delimiters: bool) -> String {
     // ascii85::encode already adds delimiters
-    ascii85::encode(data)
+    let encoded = ascii85::encode(data);
+    if delimiters {
+        encoded
+    } else {
+        encoded
+            .trim_start_matches("<~")
+            .trim_end_matches("~>")
+            .to_string()
+    }
+}
+
+/// Encode to a Base64 data URI typed by the detected content
+fn encode_data_uri(data: &[u8]) -> String {
+    let metadata = crate::metadata::magic::detect(data);
+    let media_type = match metadata.charset {
+        Some(charset) if metadata.mime_type.starts_with("text/") => {
+            format!("{};charset={}", metadata.mime_type, charset.name())
+        }
+        _ => metadata.mime_type,
+    };
+    super::data_uri::build(data, &media_type)
 }
 
 #[cfg(test)]
@@ -79,4 +349,120 @@ mod tests {
         assert!(result.starts_with("<~") && result.ends_with("~>"));
         assert!(result.contains("FCfN8"));
     }
+
+    #[test]
+    fn test_encode_options_base64_base32() {
+        let options = EncodeOptions {
+            url_safe: true,
+            padding: false,
+            ..EncodeOptions::default()
+        };
+        let result = encode_with(b"\xfb\xff", EncodingType::Base64, &options).unwrap();
+        assert_eq!(result, "-_8");
+        let result = encode_with(b"Hello", EncodingType::Base32, &options).unwrap();
+        assert_eq!(result, "JBSWY3DP");
+        let result = encode_with(b"Hi", EncodingType::Base32, &options).unwrap();
+        assert_eq!(result, "JBUQ");
+
+        let options = EncodeOptions {
+            base32_alphabet: Base32Alphabet::Hex,
+            ..EncodeOptions::default()
+        };
+        let result = encode_with(b"Hi", EncodingType::Base32, &options).unwrap();
+        assert_eq!(result, "91KG====");
+        let options = EncodeOptions {
+            base32_alphabet: Base32Alphabet::Crockford,
+            ..EncodeOptions::default()
+        };
+        let result = encode_with(b"Hi", EncodingType::Base32, &options).unwrap();
+        assert_eq!(result, "91MG");
+    }
+
+    #[test]
+    fn test_encode_options_wrap() {
+        let options = EncodeOptions {
+            wrap: Some(4),
+            line_ending: LineEnding::Crlf,
+            ..EncodeOptions::default()
+        };
+        let result = encode_with(b"Hello", EncodingType::Base64, &options).unwrap();
+        assert_eq!(result, "SGVs\r\nbG8=");
+        let options = EncodeOptions {
+            wrap: Some(4),
+            ..EncodeOptions::default()
+        };
+        let result = encode_with(b"Hi", EncodingType::Base64, &options).unwrap();
+        assert_eq!(result, "SGk=");
+    }
+
+    #[test]
+    fn test_encode_options_hex() {
+        let hex = |hex_format, hex_group, uppercase| {
+            let options = EncodeOptions {
+                hex_format,
+                hex_group,
+                uppercase,
+                ..EncodeOptions::default()
+            };
+            encode_with(b"\xde\xad\xbe", EncodingType::Hex, &options).unwrap()
+        };
+        assert_eq!(hex(HexFormat::Plain, None, true), "DEADBE");
+        assert_eq!(hex(HexFormat::Colon, None, false), "de:ad:be");
+        assert_eq!(hex(HexFormat::Space, Some(2), false), "dead be");
+        assert_eq!(hex(HexFormat::Plain, Some(2), false), "dead be");
+        assert_eq!(hex(HexFormat::Escape, None, false), "\\xde\\xad\\xbe");
+        assert_eq!(hex(HexFormat::Prefix, None, true), "0xDEADBE");
+        assert_eq!(hex(HexFormat::Prefix, Some(1), false), "0xde 0xad 0xbe");
+    }
+
+    #[test]
+    fn test_encode_options_hex_wrap() {
+        let hex = |hex_format, hex_group, wrap| {
+            let options = EncodeOptions {
+                hex_format,
+                hex_group,
+                wrap: Some(wrap),
+                ..EncodeOptions::default()
+            };
+            encode_with(b"deadbeef", EncodingType::Hex, &options).unwrap()
+        };
+        assert_eq!(
+            hex(HexFormat::Space, None, 4),
+            "64\n65\n61\n64\n62\n65\n65\n66"
+        );
+        assert_eq!(hex(HexFormat::Space, None, 8), "64 65 61\n64 62 65\n65 66");
+        assert_eq!(hex(HexFormat::Colon, None, 12), "64:65:61:64\n62:65:65:66");
+        assert_eq!(hex(HexFormat::Plain, Some(3), 13), "646561 646265\n6566");
+        assert_eq!(hex(HexFormat::Plain, None, 7), "646561\n646265\n6566");
+        assert_eq!(
+            hex(HexFormat::Escape, None, 10),
+            "\\x64\\x65\n\\x61\\x64\n\\x62\\x65\n\\x65\\x66"
+        );
+        assert_eq!(hex(HexFormat::Prefix, None, 8), "0x646561\n64626565\n66");
+        // A group wider than the line gets a line of its own
+        assert_eq!(hex(HexFormat::Prefix, Some(4), 4), "0x64656164\n0x62656566");
+    }
+
+    #[test]
+    fn test_encode_options_url_and_base85() {
+        let url = |url_charset| {
+            let options = EncodeOptions {
+                url_charset,
+                ..EncodeOptions::default()
+            };
+            encode_with(b"a b/c?d=e&f+g~", EncodingType::Url, &options).unwrap()
+        };
+        assert_eq!(url(UrlCharset::All), "a%20b%2Fc%3Fd%3De%26f%2Bg%7E");
+        assert_eq!(url(UrlCharset::Component), "a%20b%2Fc%3Fd%3De%26f%2Bg~");
+        assert_eq!(url(UrlCharset::Path), "a%20b/c%3Fd=e&f+g~");
+        assert_eq!(url(UrlCharset::Query), "a%20b/c?d%3De%26f%2Bg~");
+        assert_eq!(url(UrlCharset::Form), "a+b%2Fc%3Fd%3De%26f%2Bg%7E");
+
+        let options = EncodeOptions {
+            base85_delimiters: false,
+            ..EncodeOptions::default()
+        };
+        let result = encode_with(b"test", EncodingType::Base85, &options).unwrap();
+        assert_eq!(result, "FCfN8");
+    }
 }
@@ -0,0 +1,625 @@
+//! Byte literals in source code
+//!
+//! Encodes data as a byte-array (or byte-string) literal for C, Rust,
+//! Python, Go, JavaScript, Java or C#, and decodes such literals back:
+//! arrays of numbers (`{0x48, 0x65}`, `[72, 101]`, `(byte) 0x89`) and escaped
+//! strings (`b"\x48\x65"`, `"\110\145"`), including `xxd -i` output.
+
+use super::{Decoder, DetectionConfidence};
+use crate::error::DecodeckError;
+use clap::ValueEnum;
+use regex::Regex;
+use std::sync::OnceLock;
+
+/// Fewest elements for an array of plain decimal numbers to be taken as a
+/// literal; shorter lists such as version numbers are common in data
+const MIN_DECIMAL_ELEMENTS: usize = 16;
+
+/// Language of a generated literal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Language {
+    /// `const uint8_t data[] = {...};`
+    #[default]
+    C,
+    /// `const DATA: [u8; N] = [...];`
+    Rust,
+    /// `data = (b"\x.." ...)`
+    Python,
+    /// `var data = []byte{...}`
+    Go,
+    /// `const data = new Uint8Array([...]);`
+    Js,
+    /// `byte[] data = {...};`
+    Java,
+    /// `byte[] data = {...};`
+    Csharp,
+}
+
+/// How a literal is generated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LiteralOptions {
+    pub language: Language,
+    /// Variable name; upper-cased for Rust constants
+    pub name: String,
+    /// Bytes per line
+    pub width: usize,
+    /// Also declare a constant holding the length
+    pub length: bool,
+}
+
+impl Default for LiteralOptions {
+    fn default() -> Self {
+        Self {
+            language: Language::C,
+            name: "data".to_string(),
+            width: 12,
+            length: false,
+        }
+    }
+}
+
+/// Source code declaring data as a byte literal
+pub fn to_literal(data: &[u8], options: &LiteralOptions) -> Result<String, DecodeckError> {
+    let name = &options.name;
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(DecodeckError::InvalidIdentifier { name: name.clone() });
+    }
+    let width = options.width.max(1);
+    let len = data.len();
+
+    let (declaration, length) = match options.language {
+        Language::C => (
+            array(
+                &format!("const uint8_t {}[] = {{", name),
+                "};",
+                "    ",
+                data,
+                width,
+                hex_byte,
+            ),
+            format!("const size_t {}_len = {};", name, len),
+        ),
+        Language::Rust => {
+            let name = name.to_uppercase();
+            (
+                array(
+                    &format!("const {}: [u8; {}] = [", name, len),
+                    "];",
+                    "    ",
+                    data,
+                    width,
+                    hex_byte,
+                ),
+                format!("const {}_LEN: usize = {};", name, len),
+            )
+        }
+        Language::Python => (python(name, data, width), format!("{}_len = {}", name, len)),
+        Language::Go => (
+            array(
+                &format!("var {} = []byte{{", name),
+                "}",
+                "\t",
+                data,
+                width,
+                hex_byte,
+            ),
+            format!("\nconst {}Len = {}", name, len),
+        ),
+        Language::Js => (
+            array(
+                &format!("const {} = new Uint8Array([", name),
+                "]);",
+                "  ",
+                data,
+                width,
+                hex_byte,
+            ),
+            format!("const {}Length = {};", name, len),
+        ),
+        Language::Java => (
+            array(
+                &format!("byte[] {} = {{", name),
+                "};",
+                "    ",
+                data,
+                width,
+                java_byte,
+            ),
+            format!("final int {}Length = {};", name, len),
+        ),
+        Language::Csharp => (
+            array(
+                &format!("byte[] {} = {{", name),
+                "};",
+                "    ",
+                data,
+                width,
+                hex_byte,
+            ),
+            format!("const int {}Length = {};", name, len),
+        ),
+    };
+
+    Ok(if options.length {
+        format!("{}\n{}", declaration, length)
+    } else {
+        declaration
+    })
+}
+
+fn hex_byte(byte: u8) -> String {
+    format!("0x{:02x}", byte)
+}
+
+/// Java bytes are signed, so values above 0x7f need a cast
+fn java_byte(byte: u8) -> String {
+    if byte > 0x7f {
+        format!("(byte) 0x{:02x}", byte)
+    } else {
+        hex_byte(byte)
+    }
+}
+
+/// Array literal with a trailing comma on every line, which all the target
+/// languages accept (and Go requires)
+fn array(
+    open: &str,
+    close: &str,
+    indent: &str,
+    data: &[u8],
+    width: usize,
+    element: fn(u8) -> String,
+) -> String {
+    let mut out = String::from(open);
+    for line in data.chunks(width) {
+        let elements: Vec<String> = line.iter().map(|&b| element(b)).collect();
+        out.push('\n');
+        out.push_str(indent);
+        out.push_str(&elements.join(", "));
+        out.push(',');
+    }
+    out.push('\n');
+    out.push_str(close);
+    out
+}
+
+/// Parenthesized, implicitly concatenated bytes literals
+fn python(name: &str, data: &[u8], width: usize) -> String {
+    if data.is_empty() {
+        return format!("{} = b\"\"", name);
+    }
+    let mut out = format!("{} = (", name);
+    for line in data.chunks(width) {
+        let escaped: String = line.iter().map(|b| format!("\\x{:02x}", b)).collect();
+        out.push_str(&format!("\n    b\"{}\"", escaped));
+    }
+    out.push_str("\n)");
+    out
+}
+
+/// Decode a byte-array or string literal
+///
+/// Only the value is read: a declaration before `=` and statements after
+/// the literal (such as a length constant) are skipped.
+pub fn parse(input: &str) -> Result<Vec<u8>, DecodeckError> {
+    let source = strip_comments(input);
+    // The declaration ends at the first `=`, unless that is inside a string
+    let quote = source.find(['"', '\'']).unwrap_or(source.len());
+    let value = match source[..quote].find('=') {
+        Some(equals) => &source[equals + 1..],
+        None => source.as_str(),
+    };
+
+    let quote = value.find(['"', '\'']);
+    let bracket = value.find(['{', '[']);
+    match (quote, bracket) {
+        (Some(q), Some(b)) if q < b => parse_strings(value),
+        (Some(_), None) => parse_strings(value),
+        (_, Some(_)) => parse_array(value),
+        (None, None) => Err(invalid("expected an array or string literal")),
+    }
+}
+
+fn invalid(reason: &str) -> DecodeckError {
+    DecodeckError::DecodeFailed {
+        message: format!("Invalid literal: {}", reason),
+    }
+}
+
+/// Remove `//`, `/* */` and `#` line comments outside string literals
+fn strip_comments(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    let mut quote = None;
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        if let Some(q) = quote {
+            out.push(c);
+            if c == '\\' {
+                out.extend(chars.next());
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => {
+                quote = Some(c);
+                out.push(c);
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                chars.by_ref().find(|&c| c == '\n');
+                out.push('\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+                out.push(' ');
+            }
+            // Python comments and C preprocessor lines
+            '#' if line_start => {
+                chars.by_ref().find(|&c| c == '\n');
+                out.push('\n');
+            }
+            c => out.push(c),
+        }
+        if !c.is_whitespace() {
+            line_start = false;
+        }
+        if c == '\n' || out.ends_with('\n') {
+            line_start = true;
+        }
+    }
+    out
+}
+
+/// Elements of the first non-empty `{...}` or `[...]`
+fn parse_array(value: &str) -> Result<Vec<u8>, DecodeckError> {
+    let mut rest = value;
+    let body = loop {
+        let start = rest
+            .find(['{', '['])
+            .ok_or_else(|| invalid("no array elements"))?;
+        let close = if rest[start..].starts_with('{') {
+            '}'
+        } else {
+            ']'
+        };
+        let end = rest[start + 1..]
+            .find(close)
+            .ok_or_else(|| invalid("unclosed array"))?
+            + start
+            + 1;
+        let body = &rest[start + 1..end];
+        // Skip type syntax such as Go's `[]byte` or Rust's `[u8; 5]`
+        if !body.trim().is_empty() && !body.contains(';') {
+            break body;
+        }
+        rest = &rest[end + 1..];
+    };
+
+    let mut bytes = Vec::new();
+    for element in body.split(',').map(str::trim) {
+        if element.is_empty() {
+            continue;
+        }
+        bytes.push(parse_element(element)?);
+    }
+    if bytes.is_empty() {
+        return Err(invalid("no array elements"));
+    }
+    Ok(bytes)
+}
+
+/// One array element: a number, possibly cast or suffixed, or a character
+fn parse_element(element: &str) -> Result<u8, DecodeckError> {
+    static NUMBER: OnceLock<Regex> = OnceLock::new();
+    let number = NUMBER.get_or_init(|| {
+        Regex::new(
+            r"^(?:\(\s*[A-Za-z_][A-Za-z0-9_ ]*\)\s*)?(-)?(?:0[xX]([0-9a-fA-F_]+)|0[bB]([01_]+)|0[oO]([0-7_]+)|([0-9][0-9_]*))(?:u8|i8|[uUlL]*)$",
+        )
+        .unwrap()
+    });
+
+    if element.starts_with('\'') {
+        let bytes = unescape(element.trim_matches('\''))?;
+        return match bytes[..] {
+            [byte] => Ok(byte),
+            _ => Err(invalid(&format!("'{}' is not a single byte", element))),
+        };
+    }
+    let captures = number
+        .captures(element)
+        .ok_or_else(|| invalid(&format!("'{}' is not a number", element)))?;
+    let (digits, radix) = [(2, 16), (3, 2), (4, 8), (5, 10)]
+        .iter()
+        .find_map(|&(group, radix)| captures.get(group).map(|m| (m.as_str(), radix)))
+        .unwrap_or_default();
+    let value = i64::from_str_radix(&digits.replace('_', ""), radix)
+        .map_err(|_| invalid(&format!("'{}' is out of range", element)))?;
+    let value = if captures.get(1).is_some() {
+        -value
+    } else {
+        value
+    };
+    // Negative values are signed bytes, as Java and C# `sbyte` write them
+    match value {
+        0..=255 => Ok(value as u8),
+        -128..=-1 => Ok((value + 256) as u8),
+        _ => Err(invalid(&format!("'{}' does not fit in a byte", element))),
+    }
+}
+
+/// Concatenated string literals, with `b`/`u8`-style prefixes and `+`
+fn parse_strings(value: &str) -> Result<Vec<u8>, DecodeckError> {
+    let mut bytes = Vec::new();
+    let mut found = false;
+    let mut chars = value.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' | '\'' => {
+                let mut body = String::new();
+                let mut closed = false;
+                while let Some((_, d)) = chars.next() {
+                    if d == '\\' {
+                        body.push(d);
+                        body.extend(chars.next().map(|(_, e)| e));
+                    } else if d == c {
+                        closed = true;
+                        break;
+                    } else {
+                        body.push(d);
+                    }
+                }
+                if !closed {
+                    return Err(invalid("unterminated string"));
+                }
+                bytes.extend(unescape(&body)?);
+                found = true;
+            }
+            c if c.is_whitespace() || matches!(c, '(' | ')' | '+' | '&') => {}
+            ';' if found => break,
+            // Prefixes: b"..", u8"..", L"..", @".."
+            c if c.is_ascii_alphanumeric() || c == '@' => {
+                let prefix: String = value[i..]
+                    .chars()
+                    .take_while(|c| c.is_ascii_alphanumeric() || *c == '@')
+                    .collect();
+                let next = value[i + prefix.len()..].chars().next();
+                if !matches!(next, Some('"' | '\'')) {
+                    // The next statement after the literal
+                    if found {
+                        break;
+                    }
+                    return Err(invalid(&format!("unexpected '{}'", prefix)));
+                }
+                for _ in 1..prefix.len() {
+                    chars.next();
+                }
+            }
+            c => return Err(invalid(&format!("unexpected '{}'", c))),
+        }
+    }
+    if !found {
+        return Err(invalid("no string literal"));
+    }
+    Ok(bytes)
+}
+
+/// Bytes of a string literal body, resolving escape sequences
+fn unescape(body: &str) -> Result<Vec<u8>, DecodeckError> {
+    let mut bytes = Vec::new();
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buffer = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+        let escape = chars.next().ok_or_else(|| invalid("dangling '\\'"))?;
+        let simple = match escape {
+            'n' => Some(b'\n'),
+            'r' => Some(b'\r'),
+            't' => Some(b'\t'),
+            'a' => Some(0x07),
+            'b' => Some(0x08),
+            'f' => Some(0x0c),
+            'v' => Some(0x0b),
+            'e' => Some(0x1b),
+            '\\' | '\'' | '"' | '?' => Some(escape as u8),
+            _ => None,
+        };
+        if let Some(byte) = simple {
+            bytes.push(byte);
+            continue;
+        }
+        match escape {
+            'x' => {
+                let digits: String = std::iter::from_fn(|| chars.next_if(char::is_ascii_hexdigit))
+                    .take(2)
+                    .collect();
+                let byte = u8::from_str_radix(&digits, 16)
+                    .map_err(|_| invalid("'\\x' needs hex digits"))?;
+                bytes.push(byte);
+            }
+            '0'..='7' => {
+                let mut digits = escape.to_string();
+                digits.extend(
+                    std::iter::from_fn(|| chars.next_if(|c| ('0'..='7').contains(c))).take(2),
+                );
+                let value = u16::from_str_radix(&digits, 8).unwrap_or_default();
+                let byte = u8::try_from(value).map_err(|_| invalid("octal escape above \\377"))?;
+                bytes.push(byte);
+            }
+            'u' => {
+                let digits: String = if chars.peek() == Some(&'{') {
+                    chars.next();
+                    let digits = std::iter::from_fn(|| chars.next_if(|&c| c != '}')).collect();
+                    chars.next();
+                    digits
+                } else {
+                    std::iter::from_fn(|| chars.next_if(char::is_ascii_hexdigit))
+                        .take(4)
+                        .collect()
+                };
+                let c = u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| invalid("invalid '\\u' escape"))?;
+                let mut buffer = [0u8; 4];
+                bytes.extend_from_slice(c.encode_utf8(&mut buffer).as_bytes());
+            }
+            // Line continuation
+            '\n' => {}
+            other => return Err(invalid(&format!("unknown escape '\\{}'", other))),
+        }
+    }
+    Ok(bytes)
+}
+
+/// Whether input is a byte-array or escaped-string literal
+pub fn is_literal(input: &str) -> bool {
+    detect(input).is_some()
+}
+
+/// How likely input is a byte-array or escaped-string literal
+///
+/// Arrays need hex elements or many elements, and strings an escape
+/// sequence. Only a declaration (`data = {`, `b"`, `[]byte`) makes the
+/// detection high confidence.
+pub fn detect(input: &str) -> Option<DetectionConfidence> {
+    let trimmed = input.trim();
+    let quoted = trimmed.contains(['"', '\'']);
+    let looks_right = if quoted {
+        trimmed.contains("\\x") || trimmed.contains("\\u") || trimmed.contains("\\0")
+    } else {
+        trimmed.contains(['{', '['])
+            && (trimmed.contains("0x") || trimmed.contains("0X") || trimmed.contains(','))
+    };
+    if !looks_right {
+        return None;
+    }
+    let bytes = parse(trimmed).ok()?;
+    let hex_elements = trimmed.contains("0x") || trimmed.contains("0X");
+    if !quoted && !hex_elements && bytes.len() < MIN_DECIMAL_ELEMENTS {
+        return None;
+    }
+    Some(if has_declaration(trimmed) {
+        DetectionConfidence::High
+    } else {
+        DetectionConfidence::Medium
+    })
+}
+
+/// Whether a literal comes with source syntax around it: an assignment, a
+/// byte-string prefix or a byte-slice type
+fn has_declaration(literal: &str) -> bool {
+    // As in `parse`, only an `=` before any string is an assignment
+    let quote = literal.find(['"', '\'']).unwrap_or(literal.len());
+    literal[..quote].contains('=')
+        || literal.starts_with("b\"")
+        || literal.starts_with("b'")
+        || literal.contains("[]byte")
+}
+
+/// Source literal decoder implementation
+pub struct LiteralDecoder;
+
+impl Decoder for LiteralDecoder {
+    fn decode(&self, input: &str) -> Result<Vec<u8>, DecodeckError> {
+        parse(input)
+    }
+
+    fn name(&self) -> &'static str {
+        "literal"
+    }
+
+    fn can_decode(&self, input: &str) -> bool {
+        is_literal(input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(language: Language) -> LiteralOptions {
+        LiteralOptions {
+            language,
+            width: 4,
+            length: true,
+            ..LiteralOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_to_literal() {
+        let data = b"Hello\x89";
+        assert_eq!(
+            to_literal(data, &options(Language::C)).unwrap(),
+            "const uint8_t data[] = {\n    0x48, 0x65, 0x6c, 0x6c,\n    0x6f, 0x89,\n};\nconst size_t data_len = 6;"
+        );
+        assert_eq!(
+            to_literal(data, &options(Language::Python)).unwrap(),
+            "data = (\n    b\"\\x48\\x65\\x6c\\x6c\"\n    b\"\\x6f\\x89\"\n)\ndata_len = 6"
+        );
+        let rust = to_literal(data, &options(Language::Rust)).unwrap();
+        assert!(rust.starts_with("const DATA: [u8; 6] = [\n"));
+        assert!(rust.ends_with("];\nconst DATA_LEN: usize = 6;"));
+        let java = to_literal(data, &options(Language::Java)).unwrap();
+        assert!(java.contains("0x6f, (byte) 0x89,"));
+        let go = to_literal(b"A", &options(Language::Go)).unwrap();
+        assert_eq!(go, "var data = []byte{\n\t0x41,\n}\n\nconst dataLen = 1");
+
+        let bad = LiteralOptions {
+            name: "2fast".to_string(),
+            ..LiteralOptions::default()
+        };
+        assert!(to_literal(data, &bad).is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let data: Vec<u8> = (0..=255).collect();
+        for language in Language::value_variants() {
+            let source = to_literal(&data, &options(*language)).unwrap();
+            assert_eq!(parse(&source).unwrap(), data, "{:?}", language);
+            assert!(is_literal(&source), "{:?}", language);
+        }
+    }
+
+    #[test]
+    fn test_parse_foreign_literals() {
+        let xxd = "unsigned char hello_txt[] = {\n  0x48, 0x69, 0x0a\n};\nunsigned int hello_txt_len = 3;\n";
+        assert_eq!(parse(xxd).unwrap(), b"Hi\n");
+        assert_eq!(parse("[72, 105]").unwrap(), b"Hi");
+        assert_eq!(
+            parse("new byte[] { (byte) -1, 'A' /* a */ }").unwrap(),
+            b"\xffA"
+        );
+        assert_eq!(
+            parse("char s[] = \"\\110i\\n\"; // greeting").unwrap(),
+            b"Hi\n"
+        );
+        assert_eq!(parse("const s = '\\x48' + \"\\u0069\";").unwrap(), b"Hi");
+        assert_eq!(parse("let b = b\"\\x00\\xffok\";").unwrap(), b"\x00\xffok");
+        assert_eq!(parse("\"a=b\"").unwrap(), b"a=b");
+
+        assert!(parse("[1, 2, 300]").is_err());
+        assert!(parse("{\"user\": 1}").is_err());
+        assert!(parse("SGVsbG8=").is_err());
+        assert!(!is_literal("[1]"));
+        assert!(!is_literal("0x48656c6c6f"));
+    }
+}
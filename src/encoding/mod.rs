@@ -1,15 +1,21 @@
 //! Multi-encoding support module
 //!
 //! Provides a unified interface for encoding and decoding various formats:
-//! Base64, Hex, Base32, URL percent-encoding, and Ascii85.
+//! Base64, Hex, Base32, URL percent-encoding, Ascii85 and data URIs, plus classical
+//! cipher layers (ROT13, Caesar, XOR, ...) combined with them in recipes.
 
 pub mod base32;
 pub mod base64;
 pub mod base85;
 pub mod chain;
+pub mod cipher;
+pub mod data_uri;
 pub mod detect;
 pub mod encode;
 pub mod hex;
+pub mod literal;
+pub mod powershell;
+pub mod recipe;
 pub mod scan;
 pub mod url;
 
@@ -43,6 +49,11 @@ pub enum EncodingType {
     Url,
     /// Ascii85 encoding (Adobe variant)
     Base85,
+    /// Data URI (RFC 2397), e.g. "data:image/png;base64,..."
+    #[serde(rename = "data-uri")]
+    DataUri,
+    /// Byte-array or escaped-string literal from C, Rust, Python, Go, JS, Java or C# source
+    Literal,
 }
 
 impl EncodingType {
@@ -54,6 +65,8 @@ impl EncodingType {
             EncodingType::Base32 => Box::new(base32::Base32Decoder),
             EncodingType::Url => Box::new(url::UrlDecoder),
             EncodingType::Base85 => Box::new(base85::Base85Decoder),
+            EncodingType::DataUri => Box::new(data_uri::DataUriDecoder),
+            EncodingType::Literal => Box::new(literal::LiteralDecoder),
         }
     }
 
@@ -65,6 +78,8 @@ impl EncodingType {
             EncodingType::Base32 => "base32",
             EncodingType::Url => "url",
             EncodingType::Base85 => "base85",
+            EncodingType::DataUri => "data-uri",
+            EncodingType::Literal => "literal",
         }
     }
 }
@@ -0,0 +1,108 @@
+//! Recognition of PowerShell `-EncodedCommand` command lines
+//!
+//! `powershell -enc <base64>` carries a Base64 script encoded as UTF-16LE.
+//! PowerShell accepts any prefix of a parameter name, so `-e`, `-en`,
+//! `-enc` and `-EncodedCommand` are equivalent, as is the `-ec` alias.
+
+/// Executable names that accept `-EncodedCommand`
+const POWERSHELL_NAMES: &[&str] = &["powershell", "pwsh", "powershell_ise"];
+
+/// Full name of the parameter
+const ENCODED_COMMAND: &str = "encodedcommand";
+
+/// Extract the Base64 argument from a PowerShell command line
+///
+/// The executable may be given with a path, an `.exe` suffix or behind a
+/// launcher such as `cmd /c`. Returns `None` if the input is not a PowerShell
+/// command line with an encoded command.
+pub fn extract_encoded_command(input: &str) -> Option<&str> {
+    let tokens = tokenize(input);
+    let start = tokens.iter().position(|t| is_powershell(t))?;
+
+    let mut args = tokens[start + 1..].iter();
+    while let Some(token) = args.next() {
+        if is_encoded_command_flag(token) {
+            let argument = args.next()?;
+            let is_base64 = argument.len() >= 4
+                && argument
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '/' | '='));
+            return is_base64.then_some(*argument);
+        }
+    }
+    None
+}
+
+fn is_powershell(token: &str) -> bool {
+    let name = token
+        .rsplit(['\\', '/'])
+        .next()
+        .unwrap_or(token)
+        .to_lowercase();
+    let name = name.strip_suffix(".exe").unwrap_or(&name);
+    POWERSHELL_NAMES.contains(&name)
+}
+
+fn is_encoded_command_flag(token: &str) -> bool {
+    let Some(name) = token.strip_prefix(['-', '/']) else {
+        return false;
+    };
+    let name = name.to_lowercase();
+    name == "ec" || (!name.is_empty() && ENCODED_COMMAND.starts_with(&name))
+}
+
+/// Split a command line on whitespace, keeping quoted arguments together
+/// and removing their quotes
+fn tokenize(input: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut rest = input.trim_start();
+
+    while !rest.is_empty() {
+        let (token, next) = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => match rest[1..].find(quote) {
+                Some(end) => (&rest[1..end + 1], &rest[end + 2..]),
+                None => (&rest[1..], ""),
+            },
+            _ => match rest.find(char::is_whitespace) {
+                Some(end) => (&rest[..end], &rest[end..]),
+                None => (rest, ""),
+            },
+        };
+        tokens.push(token);
+        rest = next.trim_start();
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_encoded_command() {
+        let payload = "VwByAGkAdABlAC0ASABvAHMAdAAgAGgAaQA=";
+        for line in [
+            format!("powershell -enc {}", payload),
+            format!("powershell.exe -NoP -W Hidden -EncodedCommand {}", payload),
+            format!(
+                "cmd /c C:\\Windows\\System32\\WindowsPowerShell\\v1.0\\PowerShell.exe -e \"{}\"",
+                payload
+            ),
+            format!("pwsh -ec {}", payload),
+        ] {
+            assert_eq!(extract_encoded_command(&line), Some(payload), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_extract_requires_powershell_and_flag() {
+        assert_eq!(extract_encoded_command("bash -enc SGVsbG8="), None);
+        assert_eq!(
+            extract_encoded_command("powershell -ExecutionPolicy Bypass"),
+            None
+        );
+        assert_eq!(extract_encoded_command("powershell -enc"), None);
+        assert_eq!(extract_encoded_command("SGVsbG8="), None);
+    }
+}
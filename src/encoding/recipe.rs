@@ -0,0 +1,202 @@
+//! Explicit decoding recipes
+//!
+//! A recipe lists the layers to peel, outermost first, separated by commas
+//! or `|`: `base64,xor:0x2a` or `hex|rot13`. Encodings use the names accepted
+//! by `--encoding`; ciphers are `rot13`, `rot47`, `atbash`, `caesar:<shift>`
+//! and `xor:<key>`. A Caesar or XOR step without a key (or with `?`) is
+//! brute-forced and the best-scoring key is used.
+
+use super::cipher::{self, Cipher, CipherFamily};
+use super::EncodingType;
+use crate::error::DecodeckError;
+use crate::metadata::charset;
+use clap::ValueEnum;
+use std::str::FromStr;
+
+/// One layer of a recipe or decoding chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    /// Decode an encoding
+    Decode(EncodingType),
+    /// Undo a cipher with a known key
+    Cipher(Cipher),
+    /// Undo a cipher, trying every key
+    BruteForce(CipherFamily),
+}
+
+impl std::fmt::Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Step::Decode(encoding) => write!(f, "{}", encoding),
+            Step::Cipher(cipher) => write!(f, "{}", cipher),
+            Step::BruteForce(family) => write!(f, "{}:?", family),
+        }
+    }
+}
+
+impl FromStr for Step {
+    type Err = DecodeckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let step = s.trim();
+        let invalid = |reason: &str| DecodeckError::InvalidRecipe {
+            step: step.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let (name, key) = match step.split_once(':') {
+            Some((name, key)) => (name.trim().to_lowercase(), Some(key.trim())),
+            None => (step.to_lowercase(), None),
+        };
+        let brute_force = matches!(key, None | Some("?"));
+        let parse_key = |key: &str| -> Option<u8> {
+            match key.strip_prefix("0x").or(key.strip_prefix("0X")) {
+                Some(hex) => u8::from_str_radix(hex, 16).ok(),
+                None => key.parse().ok(),
+            }
+        };
+
+        match name.as_str() {
+            "rot13" | "rot47" | "atbash" if key.is_some() => Err(invalid("takes no key")),
+            "rot13" => Ok(Step::Cipher(Cipher::Rot13)),
+            "rot47" => Ok(Step::Cipher(Cipher::Rot47)),
+            "atbash" => Ok(Step::Cipher(Cipher::Atbash)),
+            "caesar" if brute_force => Ok(Step::BruteForce(CipherFamily::Caesar)),
+            "caesar" => key
+                .and_then(parse_key)
+                .filter(|&shift| shift < 26)
+                .map(|shift| Step::Cipher(Cipher::Caesar(shift)))
+                .ok_or_else(|| invalid("shift must be 0-25")),
+            "xor" if brute_force => Ok(Step::BruteForce(CipherFamily::Xor)),
+            "xor" => key
+                .and_then(parse_key)
+                .map(|k| Step::Cipher(Cipher::Xor(k)))
+                .ok_or_else(|| invalid("key must be a byte, e.g. 42 or 0x2a")),
+            _ if key.is_some() => Err(invalid("only caesar and xor take a key")),
+            _ => EncodingType::from_str(&name, true)
+                .map(Step::Decode)
+                .map_err(|_| invalid("unknown encoding or cipher")),
+        }
+    }
+}
+
+/// A sequence of layers to peel, outermost first
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recipe {
+    pub steps: Vec<Step>,
+}
+
+/// Result of applying a recipe
+#[derive(Debug, Clone)]
+pub struct RecipeResult {
+    /// Final decoded bytes
+    pub data: Vec<u8>,
+    /// Layers applied, with brute-forced keys resolved
+    pub steps: Vec<Step>,
+}
+
+impl FromStr for Recipe {
+    type Err = DecodeckError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let steps = s
+            .split([',', '|'])
+            .filter(|step| !step.trim().is_empty())
+            .map(Step::from_str)
+            .collect::<Result<Vec<_>, _>>()?;
+        if steps.is_empty() {
+            return Err(DecodeckError::InvalidRecipe {
+                step: s.to_string(),
+                reason: "recipe is empty".to_string(),
+            });
+        }
+        Ok(Recipe { steps })
+    }
+}
+
+impl Recipe {
+    /// Apply every step in order
+    pub fn apply(&self, input: &str) -> Result<RecipeResult, DecodeckError> {
+        let mut data = input.as_bytes().to_vec();
+        let mut steps = Vec::with_capacity(self.steps.len());
+
+        for (i, step) in self.steps.iter().enumerate() {
+            let applied = match *step {
+                Step::Decode(encoding) => {
+                    let text = match std::str::from_utf8(&data) {
+                        Ok(text) => text.to_string(),
+                        Err(_) => charset::decode_text(&data)
+                            .map(|(_, text)| text)
+                            .ok_or_else(|| DecodeckError::DecodeFailed {
+                                message: format!(
+                                    "step {} ({}) needs text input, got binary data",
+                                    i + 1,
+                                    encoding
+                                ),
+                            })?,
+                    };
+                    data = encoding.decoder().decode(&text)?;
+                    *step
+                }
+                Step::Cipher(cipher) => {
+                    data = cipher.apply(&data);
+                    *step
+                }
+                Step::BruteForce(family) => {
+                    let best = cipher::brute_force(&data, family).swap_remove(0);
+                    data = best.data;
+                    Step::Cipher(best.cipher)
+                }
+            };
+            steps.push(applied);
+        }
+
+        Ok(RecipeResult { data, steps })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_recipe() {
+        let recipe: Recipe = "base64, xor:0x2a | caesar:3,rot13,xor".parse().unwrap();
+        assert_eq!(
+            recipe.steps,
+            [
+                Step::Decode(EncodingType::Base64),
+                Step::Cipher(Cipher::Xor(0x2a)),
+                Step::Cipher(Cipher::Caesar(3)),
+                Step::Cipher(Cipher::Rot13),
+                Step::BruteForce(CipherFamily::Xor),
+            ]
+        );
+        assert!("base64,rot13:1".parse::<Recipe>().is_err());
+        assert!("caesar:30".parse::<Recipe>().is_err());
+        assert!("base65".parse::<Recipe>().is_err());
+        assert!(" , ".parse::<Recipe>().is_err());
+    }
+
+    #[test]
+    fn test_apply_recipe() {
+        // hex of "Uryyb, Jbeyq!" (ROT13 of "Hello, World!")
+        let recipe: Recipe = "hex,rot13".parse().unwrap();
+        let result = recipe.apply("55727979622c204a6265797121").unwrap();
+        assert_eq!(result.data, b"Hello, World!");
+    }
+
+    #[test]
+    fn test_apply_recipe_brute_force() {
+        let secret = Cipher::Xor(0x2a).apply(b"attack at dawn, bring the maps");
+        let input = crate::encoding::encode::encode(&secret, EncodingType::Base64).unwrap();
+
+        let result = "base64,xor"
+            .parse::<Recipe>()
+            .unwrap()
+            .apply(&input)
+            .unwrap();
+        assert_eq!(result.data, b"attack at dawn, bring the maps");
+        assert_eq!(result.steps[1], Step::Cipher(Cipher::Xor(0x2a)));
+    }
+}
@@ -0,0 +1,512 @@
+//! Value extraction from flat configuration formats
+//!
+//! Each parser returns values in document order, with quoting, escapes and
+//! multiline values already resolved, along with the span of their raw text.
+//! Locations use the conventions of the format: `KEY` for `.env` and
+//! properties files, `section.key` for INI and TOML.
+
+use super::span::{span_of, Span};
+use super::FlatValue;
+use crate::error::DecodeckError;
+
+/// Parse a `.env` file
+///
+/// Supports `export` prefixes, `#` comments, inline comments after unquoted
+/// values, single-quoted literals and double-quoted values with escapes that
+/// may span several lines.
+pub fn parse_env(input: &str) -> Result<Vec<FlatValue>, DecodeckError> {
+    let mut values = Vec::new();
+    let mut lines = input.lines().enumerate();
+
+    while let Some((line_no, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line).trim_start();
+        let Some((key, raw)) = line.split_once('=') else {
+            return Err(parse_error("env", line_no, "expected KEY=value"));
+        };
+        let key = key.trim().to_string();
+        let raw = raw.trim_start();
+
+        let (value, span) = if let Some(rest) = raw.strip_prefix('"') {
+            // Double-quoted values may continue over following lines
+            let mut buffer = rest.to_string();
+            loop {
+                if let Some(end) = find_closing_quote(&buffer) {
+                    let span = quoted_span(input, rest, find_closing_quote);
+                    break (unescape_double_quoted(&buffer[..end]), span);
+                }
+                match lines.next() {
+                    Some((_, next)) => {
+                        buffer.push('\n');
+                        buffer.push_str(next);
+                    }
+                    None => return Err(parse_error("env", line_no, "unterminated quote")),
+                }
+            }
+        } else if let Some(rest) = raw.strip_prefix('\'') {
+            let mut buffer = rest.to_string();
+            loop {
+                if let Some(end) = buffer.find('\'') {
+                    let span = quoted_span(input, rest, |s| s.find('\''));
+                    break (buffer[..end].to_string(), span);
+                }
+                match lines.next() {
+                    Some((_, next)) => {
+                        buffer.push('\n');
+                        buffer.push_str(next);
+                    }
+                    None => return Err(parse_error("env", line_no, "unterminated quote")),
+                }
+            }
+        } else {
+            let value = strip_inline_comment(raw).trim_end();
+            (value.to_string(), span_of(input, value.as_bytes()))
+        };
+
+        values.push(FlatValue {
+            path: key,
+            value,
+            span,
+        });
+    }
+
+    Ok(values)
+}
+
+/// Parse an INI file
+///
+/// Supports `[section]` headers, `=` or `:` separators, `;` and `#`
+/// comments, quoted values and indented continuation lines.
+pub fn parse_ini(input: &str) -> Result<Vec<FlatValue>, DecodeckError> {
+    let mut values: Vec<FlatValue> = Vec::new();
+    let mut section = String::new();
+    let mut continuing = false;
+
+    for (line_no, raw_line) in input.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+            continuing = false;
+            continue;
+        }
+
+        // Indented lines continue the previous value
+        if continuing && raw_line.starts_with(char::is_whitespace) {
+            if let Some(last) = values.last_mut() {
+                last.value.push('\n');
+                last.value.push_str(line);
+                last.span = last
+                    .span
+                    .take()
+                    .zip(span_of(input, line.as_bytes()))
+                    .map(|(span, line)| span.start..line.end);
+            }
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = name.trim().to_string();
+            continuing = false;
+            continue;
+        }
+
+        let Some(split) = line.find(['=', ':']) else {
+            return Err(parse_error("INI", line_no, "expected key = value"));
+        };
+        let key = line[..split].trim();
+        let value = unquote(line[split + 1..].trim());
+        let span = span_of(input, value.as_bytes());
+
+        let path = if section.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", section, key)
+        };
+        values.push(FlatValue {
+            path,
+            value: value.to_string(),
+            span,
+        });
+        continuing = true;
+    }
+
+    Ok(values)
+}
+
+/// Parse a Java properties file
+///
+/// Supports `=`, `:` or whitespace separators, `#` and `!` comments,
+/// backslash line continuations and `\uXXXX` escapes.
+pub fn parse_properties(input: &str) -> Result<Vec<FlatValue>, DecodeckError> {
+    let mut values = Vec::new();
+    let mut logical = String::new();
+    // Physical lines joined into the logical line, as their offset in the
+    // logical line and their span in the document
+    let mut pieces: Vec<(usize, Span)> = Vec::new();
+
+    for line in input.lines() {
+        let trimmed = line.trim_start();
+        if logical.is_empty() && (trimmed.is_empty() || trimmed.starts_with(['#', '!'])) {
+            continue;
+        }
+
+        // An odd number of trailing backslashes continues the line
+        let trailing = trimmed.len() - trimmed.trim_end_matches('\\').len();
+        let piece = if trailing % 2 == 1 {
+            &trimmed[..trimmed.len() - 1]
+        } else {
+            trimmed
+        };
+        pieces.extend(span_of(input, piece.as_bytes()).map(|span| (logical.len(), span)));
+        logical.push_str(piece);
+        if trailing % 2 == 1 {
+            continue;
+        }
+
+        values.push(property(&logical, &pieces));
+        logical.clear();
+        pieces.clear();
+    }
+
+    if !logical.is_empty() {
+        values.push(property(&logical, &pieces));
+    }
+
+    Ok(values)
+}
+
+/// Build a property from a logical line and the physical lines it joins
+fn property(logical: &str, pieces: &[(usize, Span)]) -> FlatValue {
+    let (key, value) = split_property(logical);
+    // The value is the end of the logical line
+    let start = logical.len() - value.len();
+    let span = pieces
+        .iter()
+        .rev()
+        .find(|(offset, _)| *offset <= start)
+        .zip(pieces.last())
+        .map(|((offset, first), (_, last))| first.start + (start - offset)..last.end);
+    FlatValue {
+        path: unescape_property(&key),
+        value: unescape_property(&value),
+        span,
+    }
+}
+
+/// Parse a TOML document
+///
+/// Tables become dotted locations (`server.tls.key`), array elements are
+/// indexed (`keys[0]`) and keys that are not bare are quoted.
+pub fn parse_toml(input: &str) -> Result<Vec<FlatValue>, DecodeckError> {
+    let document =
+        toml_edit::ImDocument::parse(input).map_err(|e| DecodeckError::DecodeFailed {
+            message: format!("Invalid TOML: {}", e),
+        })?;
+
+    let mut values = Vec::new();
+    collect_toml_table(input, document.as_table(), "", &mut values);
+    Ok(values)
+}
+
+fn collect_toml_table(
+    input: &str,
+    table: &toml_edit::Table,
+    path: &str,
+    values: &mut Vec<FlatValue>,
+) {
+    for (key, item) in table.iter() {
+        let item_path = toml_path(path, key);
+        match item {
+            toml_edit::Item::Value(value) => collect_toml(input, value, &item_path, values),
+            toml_edit::Item::Table(table) => collect_toml_table(input, table, &item_path, values),
+            toml_edit::Item::ArrayOfTables(tables) => {
+                for (i, table) in tables.iter().enumerate() {
+                    let table_path = format!("{}[{}]", item_path, i);
+                    collect_toml_table(input, table, &table_path, values);
+                }
+            }
+            toml_edit::Item::None => {}
+        }
+    }
+}
+
+fn collect_toml(input: &str, value: &toml_edit::Value, path: &str, values: &mut Vec<FlatValue>) {
+    match value {
+        toml_edit::Value::String(s) => values.push(FlatValue {
+            path: path.to_string(),
+            value: s.value().clone(),
+            span: s.span().and_then(|span| toml_string_span(input, span)),
+        }),
+        toml_edit::Value::Array(arr) => {
+            for (i, item) in arr.iter().enumerate() {
+                collect_toml(input, item, &format!("{}[{}]", path, i), values);
+            }
+        }
+        toml_edit::Value::InlineTable(table) => {
+            for (key, item) in table.iter() {
+                collect_toml(input, item, &toml_path(path, key), values);
+            }
+        }
+        _ => {} // Skip numbers, bools, dates
+    }
+}
+
+/// Location of `key` in the table at `path`
+fn toml_path(path: &str, key: &str) -> String {
+    let key = toml_key(key);
+    if path.is_empty() {
+        key
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+/// Span of a TOML string's contents, without its quotes
+fn toml_string_span(input: &str, span: Span) -> Option<Span> {
+    let raw = input.get(span.clone())?;
+    let quote = if raw.starts_with("\"\"\"") || raw.starts_with("'''") {
+        3
+    } else {
+        1
+    };
+    (raw.len() >= 2 * quote).then(|| span.start + quote..span.end - quote)
+}
+
+/// Quote a TOML key unless it is a bare key
+fn toml_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        serde_json::to_string(key).unwrap_or_default()
+    }
+}
+
+/// Split a logical properties line into key and value
+fn split_property(line: &str) -> (String, String) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 2,
+            '=' | ':' => break,
+            c if c.is_whitespace() => break,
+            _ => i += 1,
+        }
+    }
+    let i = i.min(chars.len());
+    let key: String = chars[..i].iter().collect();
+
+    // Skip whitespace around a single separator
+    let mut rest = chars[i..].iter().collect::<String>();
+    rest = rest.trim_start().to_string();
+    if rest.starts_with(['=', ':']) {
+        rest = rest[1..].trim_start().to_string();
+    }
+
+    (key, rest)
+}
+
+fn unescape_property(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\x0c'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(decoded) => out.push(decoded),
+                    None => {
+                        out.push_str("\\u");
+                        out.push_str(&hex);
+                    }
+                }
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Find the closing double quote, skipping escaped quotes
+fn find_closing_quote(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn unescape_double_quoted(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    out
+}
+
+/// Span of a quoted value starting at `rest`, which may continue over
+/// following lines up to the closing quote `find_end` finds
+fn quoted_span(input: &str, rest: &str, find_end: impl Fn(&str) -> Option<usize>) -> Option<Span> {
+    let start = span_of(input, rest.as_bytes())?.start;
+    let end = start + find_end(&input[start..])?;
+    Some(start..end)
+}
+
+/// Remove a ` #comment` suffix from an unquoted value
+fn strip_inline_comment(value: &str) -> &str {
+    match value.find(" #") {
+        Some(pos) => &value[..pos],
+        None if value.starts_with('#') => "",
+        None => value,
+    }
+}
+
+/// Remove matching surrounding quotes
+fn unquote(value: &str) -> &str {
+    for quote in ['"', '\''] {
+        if value.len() >= 2 && value.starts_with(quote) && value.ends_with(quote) {
+            return &value[1..value.len() - 1];
+        }
+    }
+    strip_inline_comment(value).trim_end()
+}
+
+fn parse_error(format: &str, line_no: usize, reason: &str) -> DecodeckError {
+    DecodeckError::DecodeFailed {
+        message: format!("Invalid {} at line {}: {}", format, line_no + 1, reason),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn parsed(values: Vec<FlatValue>) -> Vec<(String, String)> {
+        values.into_iter().map(|v| (v.path, v.value)).collect()
+    }
+
+    /// Raw text of each value
+    fn raw<'a>(input: &'a str, values: &[FlatValue]) -> Vec<&'a str> {
+        values
+            .iter()
+            .map(|v| &input[v.span.clone().unwrap()])
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_env() {
+        let env = "# comment\nexport A=0x4869 # inline\nB='single # kept'\nC=\"multi\nline\\tvalue\"\n\nD=\n";
+        assert_eq!(
+            parsed(parse_env(env).unwrap()),
+            pairs(&[
+                ("A", "0x4869"),
+                ("B", "single # kept"),
+                ("C", "multi\nline\tvalue"),
+                ("D", ""),
+            ])
+        );
+        assert_eq!(
+            raw(env, &parse_env(env).unwrap()),
+            vec!["0x4869", "single # kept", "multi\nline\\tvalue", ""]
+        );
+        assert!(parse_env("A=\"open").is_err());
+    }
+
+    #[test]
+    fn test_parse_ini() {
+        let ini = "top = 1\n[db]\npassword = \"0x4869\"\n; comment\nhost: localhost\nlong = first\n  second\n";
+        assert_eq!(
+            parsed(parse_ini(ini).unwrap()),
+            pairs(&[
+                ("top", "1"),
+                ("db.password", "0x4869"),
+                ("db.host", "localhost"),
+                ("db.long", "first\nsecond"),
+            ])
+        );
+        assert_eq!(
+            raw(ini, &parse_ini(ini).unwrap()),
+            vec!["1", "0x4869", "localhost", "first\n  second"]
+        );
+    }
+
+    #[test]
+    fn test_parse_properties() {
+        let props = "# comment\n! other\na.b=0x4869\nc : spaced value\nd continued\\\n    line\ne=\\u0041\\n\n";
+        assert_eq!(
+            parsed(parse_properties(props).unwrap()),
+            pairs(&[
+                ("a.b", "0x4869"),
+                ("c", "spaced value"),
+                ("d", "continuedline"),
+                ("e", "A\n"),
+            ])
+        );
+        assert_eq!(
+            raw(props, &parse_properties(props).unwrap()),
+            vec![
+                "0x4869",
+                "spaced value",
+                "continued\\\n    line",
+                "\\u0041\\n"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_toml() {
+        let toml =
+            "title = \"x\"\nport = 80\n[server.tls]\nkey = \"0x4869\"\n\"a.b\" = [\"0x4869\"]\n";
+        assert_eq!(
+            parsed(parse_toml(toml).unwrap()),
+            pairs(&[
+                ("title", "x"),
+                ("server.tls.key", "0x4869"),
+                ("server.tls.\"a.b\"[0]", "0x4869"),
+            ])
+        );
+        assert_eq!(
+            raw(toml, &parse_toml(toml).unwrap()),
+            vec!["x", "0x4869", "0x4869"]
+        );
+    }
+}
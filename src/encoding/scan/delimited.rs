@@ -0,0 +1,153 @@
+//! Value extraction from CSV exports
+//!
+//! The first row is treated as a header and counts as row 1, so locations
+//! match the row numbers a spreadsheet shows: `row 12 col "token"`. Columns
+//! without a header name fall back to their 1-based number (`row 12 col 3`).
+
+use super::span::Span;
+use super::FlatValue;
+use crate::error::DecodeckError;
+
+/// Parse CSV content into values in row order
+///
+/// Quoted fields may contain delimiters, doubled quotes and line breaks.
+/// Rows may have differing numbers of fields.
+pub fn parse_csv(input: &str) -> Result<Vec<FlatValue>, DecodeckError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(input.as_bytes());
+
+    let csv_error = |e: csv::Error| DecodeckError::DecodeFailed {
+        message: format!("Invalid CSV: {}", e),
+    };
+
+    let headers = reader.headers().map_err(csv_error)?.clone();
+    let mut values = Vec::new();
+
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(csv_error)?;
+        let row = i + 2;
+        let mut spans = match record.position() {
+            Some(position) => field_spans(input, position.byte() as usize),
+            None => Vec::new(),
+        }
+        .into_iter();
+        for (col, field) in record.iter().enumerate() {
+            let column = match headers.get(col).map(str::trim) {
+                Some(name) if !name.is_empty() => serde_json::to_string(name).unwrap_or_default(),
+                _ => (col + 1).to_string(),
+            };
+            values.push(FlatValue {
+                path: format!("row {} col {}", row, column),
+                value: field.to_string(),
+                span: spans.next(),
+            });
+        }
+    }
+
+    Ok(values)
+}
+
+/// Spans of the raw fields of the record starting at `start`
+///
+/// Quoted fields span the text between their quotes.
+fn field_spans(input: &str, start: usize) -> Vec<Span> {
+    let bytes = input.as_bytes();
+    let is_end = |at: usize| {
+        bytes
+            .get(at)
+            .map_or(true, |b| matches!(b, b',' | b'\r' | b'\n'))
+    };
+    let mut spans = Vec::new();
+    let mut at = start;
+
+    loop {
+        if bytes.get(at) == Some(&b'"') {
+            let from = at + 1;
+            at = from;
+            while at < bytes.len() {
+                match (bytes[at], bytes.get(at + 1)) {
+                    (b'"', Some(b'"')) => at += 2,
+                    (b'"', _) => break,
+                    _ => at += 1,
+                }
+            }
+            spans.push(from..at.min(bytes.len()));
+            while !is_end(at) {
+                at += 1;
+            }
+        } else {
+            let from = at;
+            while !is_end(at) {
+                at += 1;
+            }
+            spans.push(from..at);
+        }
+
+        if bytes.get(at) != Some(&b',') {
+            return spans;
+        }
+        at += 1;
+    }
+}
+
+/// Check whether content looks like CSV
+///
+/// Requires a header with at least two columns and every record (parsed with
+/// quoting rules) to have the same number of fields.
+pub fn looks_like_csv(input: &str) -> bool {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_reader(input.as_bytes());
+
+    let mut width = None;
+    let mut rows = 0;
+    for record in reader.records() {
+        let Ok(record) = record else {
+            return false;
+        };
+        match width {
+            None if record.len() < 2 => return false,
+            None => width = Some(record.len()),
+            Some(width) if width != record.len() => return false,
+            _ => {}
+        }
+        rows += 1;
+    }
+
+    rows >= 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "id,token,\n1,0x4869,x\n2,\"multi\nline, quoted\",y\n";
+        let values = parse_csv(csv).unwrap();
+        let pair = |i: usize| (values[i].path.as_str(), values[i].value.as_str());
+        assert_eq!(pair(1), ("row 2 col \"token\"", "0x4869"));
+        assert_eq!(pair(2), ("row 2 col 3", "x"));
+        assert_eq!(pair(4), ("row 3 col \"token\"", "multi\nline, quoted"));
+
+        let raw: Vec<&str> = values
+            .iter()
+            .map(|v| &csv[v.span.clone().unwrap()])
+            .collect();
+        assert_eq!(
+            raw,
+            vec!["1", "0x4869", "x", "2", "multi\nline, quoted", "y"]
+        );
+    }
+
+    #[test]
+    fn test_looks_like_csv() {
+        assert!(looks_like_csv("a,b\n1,2\n"));
+        assert!(looks_like_csv("a,b\n\"1,5\",2\n"));
+        assert!(!looks_like_csv("a,b\n1,2,3\n"));
+        assert!(!looks_like_csv("single\nvalue\n"));
+        assert!(!looks_like_csv("a,b\n"));
+    }
+}
@@ -0,0 +1,348 @@
+//! Decode-in-place rewriting of structured documents
+
+use super::{scan_xml, try_decode_value, ScanFormat};
+use crate::error::DecodeckError;
+use serde_json::Value as JsonValue;
+
+/// Maximum nesting of decoded documents expanded inside each other
+const MAX_EXPAND_DEPTH: usize = 8;
+
+/// Rewrite a JSON document with every encoded text value replaced by its
+/// decoded form
+///
+/// Decoded values that are themselves JSON objects or arrays are inserted as
+/// real nested values and expanded in turn. With `annotate`, each replaced
+/// value becomes `{"encoding": "<type>", "decoded": <value>}`.
+pub fn expand_json(input: &str, annotate: bool) -> Result<String, DecodeckError> {
+    let mut value: JsonValue =
+        serde_json::from_str(input).map_err(|e| DecodeckError::DecodeFailed {
+            message: format!("Invalid JSON: {}", e),
+        })?;
+
+    expand_json_value(&mut value, annotate, 0);
+
+    serde_json::to_string_pretty(&value).map_err(|e| DecodeckError::SystemError {
+        message: format!("Failed to serialize JSON: {}", e),
+    })
+}
+
+/// Rewrite JSON Lines like [`expand_json`], keeping one compact document
+/// per line
+pub fn expand_json_lines(input: &str, annotate: bool) -> Result<String, DecodeckError> {
+    let mut output = String::with_capacity(input.len());
+    for (index, line) in input.lines().enumerate() {
+        if !line.trim().is_empty() {
+            let mut value: JsonValue =
+                serde_json::from_str(line).map_err(|e| DecodeckError::DecodeFailed {
+                    message: format!("Invalid JSON on line {}: {}", index + 1, e),
+                })?;
+            expand_json_value(&mut value, annotate, 0);
+            output.push_str(&value.to_string());
+        }
+        output.push('\n');
+    }
+    Ok(output)
+}
+
+/// Recursively expand a JSON value in place
+fn expand_json_value(value: &mut JsonValue, annotate: bool, depth: usize) {
+    match value {
+        JsonValue::String(s) => {
+            let Some(finding) = try_decode_value(s, "") else {
+                return;
+            };
+            if !finding.is_text {
+                return;
+            }
+
+            let mut decoded = match serde_json::from_str::<JsonValue>(&finding.decoded) {
+                Ok(nested @ (JsonValue::Object(_) | JsonValue::Array(_)))
+                    if depth < MAX_EXPAND_DEPTH =>
+                {
+                    nested
+                }
+                _ => JsonValue::String(finding.decoded),
+            };
+            if !decoded.is_string() {
+                expand_json_value(&mut decoded, annotate, depth + 1);
+            }
+
+            *value = if annotate {
+                serde_json::json!({
+                    "encoding": finding.encoding.display_name(),
+                    "decoded": decoded,
+                })
+            } else {
+                decoded
+            };
+        }
+        JsonValue::Array(arr) => {
+            for item in arr {
+                expand_json_value(item, annotate, depth);
+            }
+        }
+        JsonValue::Object(obj) => {
+            for (_, val) in obj.iter_mut() {
+                expand_json_value(val, annotate, depth);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Rewrite an XML document with every encoded text value replaced by its
+/// decoded form
+///
+/// Element text and attribute values are replaced; decoded values that are
+/// themselves XML are inserted as markup and expanded in turn. With
+/// `annotate`, a `<!-- decodeck: <type> -->` comment follows each replaced
+/// text node (attributes cannot carry comments and are left unannotated).
+pub fn expand_xml(input: &str, annotate: bool) -> Result<String, DecodeckError> {
+    expand_xml_at_depth(input, annotate, 0)
+}
+
+fn expand_xml_at_depth(input: &str, annotate: bool, depth: usize) -> Result<String, DecodeckError> {
+    use quick_xml::events::{BytesCData, BytesStart, BytesText, Event};
+    use quick_xml::{Reader, Writer};
+
+    let xml_error = |e: &dyn std::fmt::Display| DecodeckError::DecodeFailed {
+        message: format!("Invalid XML: {}", e),
+    };
+
+    let mut reader = Reader::from_str(input);
+    let mut writer = Writer::new(Vec::new());
+
+    loop {
+        let event = reader.read_event().map_err(|e| xml_error(&e))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                let mut rewritten = BytesStart::new(name);
+                for attr in e.attributes() {
+                    let attr = attr.map_err(|e| xml_error(&e))?;
+                    let value = attr.unescape_value().map_err(|e| xml_error(&e))?;
+                    match try_decode_value(&value, "").filter(|f| f.is_text) {
+                        Some(finding) => {
+                            let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+                            rewritten.push_attribute((key.as_str(), finding.decoded.as_str()));
+                        }
+                        None => rewritten.push_attribute(attr),
+                    }
+                }
+                let rewritten = if matches!(event, Event::Empty(_)) {
+                    Event::Empty(rewritten)
+                } else {
+                    Event::Start(rewritten)
+                };
+                writer.write_event(rewritten).map_err(|e| xml_error(&e))?;
+            }
+            Event::Text(ref e) => {
+                let text = e.unescape().map_err(|e| xml_error(&e))?;
+                let finding = try_decode_value(&text, "").filter(|f| f.is_text);
+                let Some(finding) = finding else {
+                    writer
+                        .write_event(event.clone())
+                        .map_err(|e| xml_error(&e))?;
+                    continue;
+                };
+
+                // Keep surrounding whitespace so the layout is unchanged
+                let leading = &text[..text.len() - text.trim_start().len()];
+                let trailing = &text[text.trim_end().len()..];
+
+                let decoded = finding.decoded.trim();
+                let nested = if depth < MAX_EXPAND_DEPTH
+                    && decoded.starts_with('<')
+                    && scan_xml(decoded).is_ok()
+                {
+                    Some(expand_xml_at_depth(
+                        strip_xml_declaration(decoded),
+                        annotate,
+                        depth + 1,
+                    )?)
+                } else {
+                    None
+                };
+
+                writer
+                    .write_event(Event::Text(BytesText::new(leading)))
+                    .map_err(|e| xml_error(&e))?;
+                match nested {
+                    Some(markup) => writer.get_mut().extend_from_slice(markup.as_bytes()),
+                    None => writer
+                        .write_event(Event::Text(BytesText::new(&finding.decoded)))
+                        .map_err(|e| xml_error(&e))?,
+                }
+                if annotate {
+                    let comment = format!(" decodeck: {} ", finding.encoding.display_name());
+                    writer
+                        .write_event(Event::Comment(BytesText::from_escaped(comment)))
+                        .map_err(|e| xml_error(&e))?;
+                }
+                writer
+                    .write_event(Event::Text(BytesText::new(trailing)))
+                    .map_err(|e| xml_error(&e))?;
+            }
+            Event::CData(ref e) => {
+                let text = String::from_utf8_lossy(e).to_string();
+                let finding =
+                    try_decode_value(&text, "").filter(|f| f.is_text && !f.decoded.contains("]]>"));
+                let Some(finding) = finding else {
+                    writer
+                        .write_event(event.clone())
+                        .map_err(|e| xml_error(&e))?;
+                    continue;
+                };
+
+                writer
+                    .write_event(Event::CData(BytesCData::new(finding.decoded.as_str())))
+                    .map_err(|e| xml_error(&e))?;
+                if annotate {
+                    let comment = format!(" decodeck: {} ", finding.encoding.display_name());
+                    writer
+                        .write_event(Event::Comment(BytesText::from_escaped(comment)))
+                        .map_err(|e| xml_error(&e))?;
+                }
+            }
+            Event::Eof => break,
+            other => writer.write_event(other).map_err(|e| xml_error(&e))?,
+        }
+    }
+
+    String::from_utf8(writer.into_inner()).map_err(|e| DecodeckError::SystemError {
+        message: format!("Expanded XML is not valid UTF-8: {}", e),
+    })
+}
+
+/// Remove a leading `<?xml ... ?>` declaration from embedded markup
+fn strip_xml_declaration(xml: &str) -> &str {
+    if xml.starts_with("<?xml") {
+        if let Some(end) = xml.find("?>") {
+            return xml[end + 2..].trim_start();
+        }
+    }
+    xml
+}
+
+/// Expand a document of a known format
+///
+/// Only JSON, JSON Lines and XML can be rewritten; flat formats are rejected.
+pub fn expand_with(
+    input: &str,
+    format: ScanFormat,
+    annotate: bool,
+) -> Result<String, DecodeckError> {
+    match format {
+        ScanFormat::Json => expand_json(input, annotate),
+        ScanFormat::Jsonl => expand_json_lines(input, annotate),
+        ScanFormat::Xml => expand_xml(input, annotate),
+        other => Err(DecodeckError::DecodeFailed {
+            message: format!(
+                "Expansion supports JSON, JSON Lines and XML only, not {}",
+                other.name()
+            ),
+        }),
+    }
+}
+
+/// Auto-detect format and expand
+pub fn expand_auto(input: &str, annotate: bool) -> Result<String, DecodeckError> {
+    if input.trim().starts_with('<') {
+        expand_xml(input, annotate)
+    } else {
+        expand_json(input, annotate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_json_text() {
+        let json = r#"{"data": "0x48656c6c6f", "name": "test"}"#;
+        let expanded: JsonValue = serde_json::from_str(&expand_json(json, false).unwrap()).unwrap();
+        assert_eq!(expanded["data"], "Hello");
+        assert_eq!(expanded["name"], "test");
+    }
+
+    #[test]
+    fn test_expand_json_lines() {
+        let jsonl = "{\"a\": \"0x4869\"}\n\n{\"b\": 1}\n";
+        assert_eq!(
+            expand_json_lines(jsonl, false).unwrap(),
+            "{\"a\":\"Hi\"}\n\n{\"b\":1}\n"
+        );
+    }
+
+    #[test]
+    fn test_expand_json_nested_document() {
+        // {"k":"0x4869"} hex-encoded
+        let json = r#"{"payload": "0x7b226b223a22307834383639227d"}"#;
+        let expanded: JsonValue = serde_json::from_str(&expand_json(json, false).unwrap()).unwrap();
+        assert_eq!(expanded["payload"]["k"], "Hi");
+    }
+
+    #[test]
+    fn test_expand_json_annotated() {
+        let json = r#"{"data": "0x48656c6c6f"}"#;
+        let expanded: JsonValue = serde_json::from_str(&expand_json(json, true).unwrap()).unwrap();
+        assert_eq!(expanded["data"]["encoding"], "hex");
+        assert_eq!(expanded["data"]["decoded"], "Hello");
+    }
+
+    #[test]
+    fn test_expand_json_keeps_key_order() {
+        let json = r#"{"z": 1, "a": "0x4869"}"#;
+        let expanded = expand_json(json, false).unwrap();
+        assert!(expanded.find("\"z\"").unwrap() < expanded.find("\"a\"").unwrap());
+    }
+
+    #[test]
+    fn test_expand_xml_text_and_attributes() {
+        let xml = r#"<root id="0x4869"><data>0x48656c6c6f</data><n>plain</n></root>"#;
+        let expanded = expand_xml(xml, false).unwrap();
+        assert_eq!(
+            expanded,
+            r#"<root id="Hi"><data>Hello</data><n>plain</n></root>"#
+        );
+    }
+
+    #[test]
+    fn test_expand_xml_annotated() {
+        let xml = "<root><data>0x48656c6c6f</data></root>";
+        let expanded = expand_xml(xml, true).unwrap();
+        assert_eq!(
+            expanded,
+            "<root><data>Hello<!-- decodeck: hex --></data></root>"
+        );
+    }
+
+    #[test]
+    fn test_expand_xml_nested_markup() {
+        // <a>0x4869</a> hex-encoded
+        let xml = "<root>0x3c613e3078343836393c2f613e</root>";
+        let expanded = expand_xml(xml, false).unwrap();
+        assert_eq!(expanded, "<root><a>Hi</a></root>");
+    }
+
+    #[test]
+    fn test_expand_xml_cdata() {
+        let xml = "<root><![CDATA[0x48656c6c6f]]></root>";
+        let expanded = expand_xml(xml, false).unwrap();
+        assert_eq!(expanded, "<root><![CDATA[Hello]]></root>");
+    }
+
+    #[test]
+    fn test_expand_rejects_flat_formats() {
+        assert!(expand_with("A=0x4869", ScanFormat::Env, false).is_err());
+    }
+
+    #[test]
+    fn test_expand_auto() {
+        assert!(expand_auto(r#"{"x": "0x4869"}"#, false)
+            .unwrap()
+            .contains("Hi"));
+        assert!(expand_auto("<r>0x4869</r>", false).unwrap().contains("Hi"));
+    }
+}
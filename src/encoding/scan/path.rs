@@ -0,0 +1,334 @@
+//! Location paths into scanned JSON documents
+//!
+//! Paths can be rendered as JSONPath (`$.a['b.c'][0]`), RFC 6901 JSON Pointer
+//! (`/a/b.c/0`) or jq filters (`.a["b.c"][0]`), with keys escaped so every
+//! path is unambiguous and can be fed back into the matching tool.
+
+use crate::error::DecodeckError;
+use clap::ValueEnum;
+use serde_json::Value as JsonValue;
+
+/// Syntax used to render JSON locations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum PathStyle {
+    /// JSONPath (`$.a['b.c'][0]`)
+    #[default]
+    Jsonpath,
+    /// RFC 6901 JSON Pointer (`/a/b.c/0`)
+    Pointer,
+    /// jq filter (`.a["b.c"][0]`)
+    Jq,
+}
+
+/// A single step in a JSON location
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathSegment {
+    /// Object member
+    Key(String),
+    /// Array element
+    Index(usize),
+}
+
+/// Render a location in the given style
+pub fn format_path(segments: &[PathSegment], style: PathStyle) -> String {
+    let mut path = match style {
+        PathStyle::Jsonpath => "$".to_string(),
+        PathStyle::Pointer => String::new(),
+        PathStyle::Jq => String::new(),
+    };
+
+    for segment in segments {
+        match (style, segment) {
+            (PathStyle::Jsonpath, PathSegment::Key(key)) if is_identifier(key) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            (PathStyle::Jsonpath, PathSegment::Key(key)) => {
+                let escaped = key.replace('\\', "\\\\").replace('\'', "\\'");
+                path.push_str(&format!("['{}']", escaped));
+            }
+            (PathStyle::Jq, PathSegment::Key(key)) if is_identifier(key) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            (PathStyle::Jq, PathSegment::Key(key)) => {
+                let quoted = serde_json::to_string(key).unwrap_or_default();
+                if path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&format!("[{}]", quoted));
+            }
+            (PathStyle::Jq, PathSegment::Index(i)) => {
+                if path.is_empty() {
+                    path.push('.');
+                }
+                path.push_str(&format!("[{}]", i));
+            }
+            (PathStyle::Jsonpath, PathSegment::Index(i)) => path.push_str(&format!("[{}]", i)),
+            (PathStyle::Pointer, PathSegment::Key(key)) => {
+                path.push('/');
+                path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+            }
+            (PathStyle::Pointer, PathSegment::Index(i)) => path.push_str(&format!("/{}", i)),
+        }
+    }
+
+    if style == PathStyle::Jq && path.is_empty() {
+        path.push('.');
+    }
+
+    path
+}
+
+/// Parse a location, inferring its style from the first character
+///
+/// `$` starts a JSONPath, `/` (or an empty string) a JSON Pointer and `.` a
+/// jq filter.
+pub fn parse_path(path: &str) -> Result<Vec<PathSegment>, DecodeckError> {
+    if path.is_empty() || path.starts_with('/') {
+        parse_pointer(path)
+    } else if let Some(rest) = path.strip_prefix('$') {
+        parse_bracketed(path, rest, PathStyle::Jsonpath)
+    } else if path.starts_with('.') {
+        parse_bracketed(path, path, PathStyle::Jq)
+    } else {
+        Err(invalid(
+            path,
+            "expected a path starting with '$', '/' or '.'",
+        ))
+    }
+}
+
+/// Look up the value at a location
+///
+/// JSON Pointers cannot tell keys from indices, so numeric keys that step
+/// into arrays are rewritten to [`PathSegment::Index`] along the way.
+pub fn resolve<'a>(value: &'a JsonValue, segments: &mut [PathSegment]) -> Option<&'a JsonValue> {
+    let mut current = value;
+    for segment in segments.iter_mut() {
+        if let (JsonValue::Array(_), PathSegment::Key(key)) = (current, &*segment) {
+            *segment = PathSegment::Index(key.parse().ok()?);
+        }
+        current = match (current, &*segment) {
+            (JsonValue::Object(obj), PathSegment::Key(key)) => obj.get(key)?,
+            (JsonValue::Array(arr), PathSegment::Index(i)) => arr.get(*i)?,
+            _ => return None,
+        };
+    }
+    Some(current)
+}
+
+fn parse_pointer(path: &str) -> Result<Vec<PathSegment>, DecodeckError> {
+    if path.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    path[1..]
+        .split('/')
+        .map(|token| {
+            if token.contains('~') && !valid_pointer_escapes(token) {
+                return Err(invalid(path, "'~' must be followed by '0' or '1'"));
+            }
+            Ok(PathSegment::Key(
+                token.replace("~1", "/").replace("~0", "~"),
+            ))
+        })
+        .collect()
+}
+
+fn valid_pointer_escapes(token: &str) -> bool {
+    let bytes = token.as_bytes();
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|(_, b)| **b == b'~')
+        .all(|(i, _)| matches!(bytes.get(i + 1), Some(b'0') | Some(b'1')))
+}
+
+/// Parse the shared dotted/bracketed syntax of JSONPath and jq
+fn parse_bracketed(
+    path: &str,
+    rest: &str,
+    style: PathStyle,
+) -> Result<Vec<PathSegment>, DecodeckError> {
+    let chars: Vec<char> = rest.chars().collect();
+    let mut segments = Vec::new();
+    let mut i = 0;
+
+    // A lone "." is the jq identity filter
+    if style == PathStyle::Jq && rest == "." {
+        return Ok(segments);
+    }
+
+    while i < chars.len() {
+        match chars[i] {
+            '.' => {
+                i += 1;
+                if i < chars.len() && chars[i] == '[' {
+                    continue;
+                }
+                if style == PathStyle::Jq && i < chars.len() && chars[i] == '"' {
+                    let (key, next) = parse_quoted(path, &chars, i, '"')?;
+                    segments.push(PathSegment::Key(key));
+                    i = next;
+                    continue;
+                }
+                let start = i;
+                while i < chars.len() && chars[i] != '.' && chars[i] != '[' {
+                    i += 1;
+                }
+                if start == i {
+                    return Err(invalid(path, "empty key"));
+                }
+                segments.push(PathSegment::Key(chars[start..i].iter().collect()));
+            }
+            '[' => {
+                i += 1;
+                match chars.get(i) {
+                    Some(&quote @ ('\'' | '"')) => {
+                        let (key, next) = parse_quoted(path, &chars, i, quote)?;
+                        segments.push(PathSegment::Key(key));
+                        i = next;
+                    }
+                    _ => {
+                        let start = i;
+                        while i < chars.len() && chars[i].is_ascii_digit() {
+                            i += 1;
+                        }
+                        let index: String = chars[start..i].iter().collect();
+                        let index = index
+                            .parse()
+                            .map_err(|_| invalid(path, "expected an index or quoted key"))?;
+                        segments.push(PathSegment::Index(index));
+                    }
+                }
+                if chars.get(i) != Some(&']') {
+                    return Err(invalid(path, "missing ']'"));
+                }
+                i += 1;
+            }
+            _ => return Err(invalid(path, "expected '.' or '['")),
+        }
+    }
+
+    Ok(segments)
+}
+
+/// Parse a quoted key starting at `start`, returning it and the next index
+fn parse_quoted(
+    path: &str,
+    chars: &[char],
+    start: usize,
+    quote: char,
+) -> Result<(String, usize), DecodeckError> {
+    let mut key = String::new();
+    let mut i = start + 1;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if i + 1 < chars.len() => {
+                let escaped = chars[i + 1];
+                key.push(match escaped {
+                    'n' => '\n',
+                    't' => '\t',
+                    'r' => '\r',
+                    other => other,
+                });
+                i += 2;
+            }
+            c if c == quote => return Ok((key, i + 1)),
+            c => {
+                key.push(c);
+                i += 1;
+            }
+        }
+    }
+
+    Err(invalid(path, "unterminated quoted key"))
+}
+
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn invalid(path: &str, reason: &str) -> DecodeckError {
+    DecodeckError::InvalidPath {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments() -> Vec<PathSegment> {
+        vec![
+            PathSegment::Key("a".to_string()),
+            PathSegment::Key("b.c d".to_string()),
+            PathSegment::Index(0),
+            PathSegment::Key("x/y~z".to_string()),
+        ]
+    }
+
+    #[test]
+    fn test_format_jsonpath() {
+        assert_eq!(
+            format_path(&segments(), PathStyle::Jsonpath),
+            "$.a['b.c d'][0]['x/y~z']"
+        );
+        assert_eq!(format_path(&[], PathStyle::Jsonpath), "$");
+    }
+
+    #[test]
+    fn test_format_pointer() {
+        assert_eq!(
+            format_path(&segments(), PathStyle::Pointer),
+            "/a/b.c d/0/x~1y~0z"
+        );
+        assert_eq!(format_path(&[], PathStyle::Pointer), "");
+    }
+
+    #[test]
+    fn test_format_jq() {
+        assert_eq!(
+            format_path(&segments(), PathStyle::Jq),
+            r#".a["b.c d"][0]["x/y~z"]"#
+        );
+        assert_eq!(format_path(&[], PathStyle::Jq), ".");
+        assert_eq!(format_path(&[PathSegment::Index(2)], PathStyle::Jq), ".[2]");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for style in [PathStyle::Jsonpath, PathStyle::Jq] {
+            let path = format_path(&segments(), style);
+            assert_eq!(parse_path(&path).unwrap(), segments(), "{}", path);
+        }
+
+        // Pointers do not distinguish indices from keys
+        let parsed = parse_path("/a/b.c d/0/x~1y~0z").unwrap();
+        assert_eq!(parsed[2], PathSegment::Key("0".to_string()));
+        assert_eq!(parsed[3], PathSegment::Key("x/y~z".to_string()));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(parse_path("a.b").is_err());
+        assert!(parse_path("$.a[").is_err());
+        assert!(parse_path("$['unterminated").is_err());
+        assert!(parse_path("/a~2").is_err());
+    }
+
+    #[test]
+    fn test_resolve() {
+        let value: JsonValue = serde_json::json!({"a": {"b.c d": ["x"]}});
+        let mut segments = parse_path("/a/b.c d/0").unwrap();
+        let found = resolve(&value, &mut segments);
+        assert_eq!(found, Some(&JsonValue::String("x".to_string())));
+        assert_eq!(segments[2], PathSegment::Index(0));
+        assert!(resolve(&value, &mut parse_path("$.missing").unwrap()).is_none());
+    }
+}
@@ -0,0 +1,260 @@
+//! Byte spans of scanned values in their source document
+//!
+//! Parsers report where the raw text of each value lies, so a finding can be
+//! traced back to the exact bytes it came from even when the same text
+//! appears elsewhere in the document, or the value was written with escapes.
+
+use super::path::PathSegment;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// A byte range in a document
+pub type Span = Range<usize>;
+
+/// Byte range of `part` within `text`, when `part` is a slice of it
+pub fn span_of(text: &str, part: &[u8]) -> Option<Span> {
+    let start = (part.as_ptr() as usize).checked_sub(text.as_ptr() as usize)?;
+    let end = start.checked_add(part.len())?;
+    (end <= text.len()).then_some(start..end)
+}
+
+/// Span of `part`, a slice of `value`, given the span of `value`'s raw text
+///
+/// Only known when the raw text is `value` itself. Escapes and folded lines
+/// always make raw text longer than the value, so equal lengths mean the
+/// two are identical.
+pub fn sub_span(span: Option<&Span>, value: &str, part: &str) -> Option<Span> {
+    let span = span.filter(|span| span.len() == value.len())?;
+    let offset = span_of(value, part.as_bytes())?;
+    Some(span.start + offset.start..span.start + offset.end)
+}
+
+/// Find `part` of a value in the value's raw text
+///
+/// Returns a range of `raw`. Unescaped values map directly. Otherwise the
+/// whole value maps to the whole raw text, and a part of it to the single
+/// place where it appears in one of the forms it may be escaped in. A part
+/// that appears nowhere, or in several places, cannot be located.
+pub fn locate(raw: &str, value: &str, part: Span) -> Option<Span> {
+    if raw == value {
+        return Some(part);
+    }
+
+    let before = value.get(..part.start)?;
+    let after = value.get(part.end..)?;
+    if before.trim().is_empty() && after.trim().is_empty() {
+        let start = raw.len() - raw.trim_start().len();
+        return Some(start..raw.trim_end().len().max(start));
+    }
+
+    let original = value.get(part)?;
+    let mut found: Vec<Span> = raw_forms(original)
+        .iter()
+        .flat_map(|form| {
+            raw.match_indices(form.as_str())
+                .map(|(start, form)| start..start + form.len())
+        })
+        .collect();
+    found.sort_by_key(|span| (span.start, span.end));
+    found.dedup();
+    match found.as_slice() {
+        [span] => Some(span.clone()),
+        _ => None,
+    }
+}
+
+/// Forms in which a value may appear in a source document
+fn raw_forms(value: &str) -> Vec<String> {
+    let mut forms = vec![value.to_string()];
+
+    let json = serde_json::to_string(value).unwrap_or_default();
+    let json = json.trim_matches('"').to_string();
+    let json_slash = json.replace('/', "\\/");
+    let xml = quick_xml::escape::escape(value).to_string();
+
+    for form in [json, json_slash, xml] {
+        if !form.is_empty() && !forms.contains(&form) {
+            forms.push(form);
+        }
+    }
+
+    forms
+}
+
+/// Spans of the string values of a JSON document, keyed by their location
+///
+/// Each span covers the text between the quotes. The input is expected to
+/// be valid JSON; scanning stops at the first thing that is not. When a key
+/// repeats, the last value wins, as it does when the document is parsed.
+pub fn json_spans(input: &str) -> HashMap<Vec<PathSegment>, Span> {
+    let mut walker = JsonWalker {
+        input: input.as_bytes(),
+        text: input,
+        at: 0,
+        spans: HashMap::new(),
+    };
+    walker.value(&mut Vec::new());
+    walker.spans
+}
+
+struct JsonWalker<'a> {
+    input: &'a [u8],
+    text: &'a str,
+    at: usize,
+    spans: HashMap<Vec<PathSegment>, Span>,
+}
+
+impl JsonWalker<'_> {
+    fn skip_whitespace(&mut self) {
+        while self
+            .input
+            .get(self.at)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.at += 1;
+        }
+    }
+
+    /// Consume `byte` if it is next
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        let found = self.input.get(self.at) == Some(&byte);
+        if found {
+            self.at += 1;
+        }
+        found
+    }
+
+    /// Walk one value, returning false when the input is not valid JSON
+    fn value(&mut self, path: &mut Vec<PathSegment>) -> bool {
+        self.skip_whitespace();
+        match self.input.get(self.at) {
+            Some(b'{') => {
+                self.at += 1;
+                if self.eat(b'}') {
+                    return true;
+                }
+                loop {
+                    self.skip_whitespace();
+                    // Keys are unescaped to match the parsed document
+                    let Some(span) = self.string() else {
+                        return false;
+                    };
+                    let quoted = &self.text[span.start - 1..span.end + 1];
+                    let Ok(key) = serde_json::from_str::<String>(quoted) else {
+                        return false;
+                    };
+                    if !self.eat(b':') {
+                        return false;
+                    }
+                    path.push(PathSegment::Key(key));
+                    let valid = self.value(path);
+                    path.pop();
+                    if !valid {
+                        return false;
+                    }
+                    if !self.eat(b',') {
+                        return self.eat(b'}');
+                    }
+                }
+            }
+            Some(b'[') => {
+                self.at += 1;
+                if self.eat(b']') {
+                    return true;
+                }
+                for index in 0.. {
+                    path.push(PathSegment::Index(index));
+                    let valid = self.value(path);
+                    path.pop();
+                    if !valid {
+                        return false;
+                    }
+                    if !self.eat(b',') {
+                        break;
+                    }
+                }
+                self.eat(b']')
+            }
+            Some(b'"') => match self.string() {
+                Some(span) => {
+                    self.spans.insert(path.clone(), span);
+                    true
+                }
+                None => false,
+            },
+            Some(_) => {
+                // Numbers, booleans and null
+                let start = self.at;
+                while self
+                    .input
+                    .get(self.at)
+                    .is_some_and(|b| !matches!(b, b',' | b']' | b'}') && !b.is_ascii_whitespace())
+                {
+                    self.at += 1;
+                }
+                self.at > start
+            }
+            None => false,
+        }
+    }
+
+    /// Consume a string, returning the span between its quotes
+    fn string(&mut self) -> Option<Span> {
+        if self.input.get(self.at) != Some(&b'"') {
+            return None;
+        }
+        let start = self.at + 1;
+        let mut at = start;
+        loop {
+            match self.input.get(at)? {
+                b'\\' => at += 2,
+                b'"' => break,
+                _ => at += 1,
+            }
+        }
+        self.at = at + 1;
+        Some(start..at)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_spans() {
+        let json = r#"{"a": ["x", 1, {"b\"c": "0x\u0034"}], "a2": true, "d": "y", "d": "z"}"#;
+        let spans = json_spans(json);
+        let key = |k: &str| PathSegment::Key(k.to_string());
+        let at = |path: Vec<PathSegment>| &json[spans[&path].clone()];
+
+        assert_eq!(at(vec![key("a"), PathSegment::Index(0)]), "x");
+        assert_eq!(
+            at(vec![key("a"), PathSegment::Index(2), key("b\"c")]),
+            "0x\\u0034"
+        );
+        assert_eq!(at(vec![key("d")]), "z");
+        assert_eq!(spans.len(), 3);
+    }
+
+    #[test]
+    fn test_locate() {
+        // Unescaped values map directly
+        assert_eq!(locate("a 0x41", "a 0x41", 2..6), Some(2..6));
+        // A whole escaped value maps to the whole raw text
+        assert_eq!(locate("0x\\u00341", "0x41", 0..4), Some(0..9));
+        // Part of an escaped value must appear exactly once
+        assert_eq!(locate("\\\"a\\\" 0x41", "\"a\" 0x41", 4..8), Some(6..10));
+        assert_eq!(locate("\\\"0x41\\\" 0x41", "\"0x41\" 0x41", 7..11), None);
+    }
+
+    #[test]
+    fn test_span_of() {
+        let text = "key=value";
+        assert_eq!(span_of(text, &text.as_bytes()[4..]), Some(4..9));
+        assert_eq!(span_of(text, String::from("value").as_bytes()), None);
+        assert_eq!(sub_span(Some(&(10..19)), text, &text[4..]), Some(14..19));
+        assert_eq!(sub_span(Some(&(10..21)), text, &text[4..]), None);
+    }
+}
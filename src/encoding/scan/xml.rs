@@ -0,0 +1,349 @@
+//! XML document model used by the scanner
+//!
+//! Documents are parsed into a small tree so that repeated siblings can be
+//! indexed (`/root/item[3]`) and namespaced names can be rewritten to a
+//! stable prefix form that does not depend on the prefixes a particular
+//! producer happened to declare.
+
+use super::span::{span_of, Span};
+use crate::error::DecodeckError;
+use std::collections::HashMap;
+
+/// Conventional prefixes for well-known namespaces
+const KNOWN_NAMESPACES: &[(&str, &str)] = &[
+    ("http://schemas.xmlsoap.org/soap/envelope/", "soap"),
+    ("http://www.w3.org/2003/05/soap-envelope", "soap12"),
+    ("urn:oasis:names:tc:SAML:2.0:assertion", "saml"),
+    ("urn:oasis:names:tc:SAML:2.0:protocol", "samlp"),
+    ("urn:oasis:names:tc:SAML:1.0:assertion", "saml1"),
+    ("http://www.w3.org/2000/09/xmldsig#", "ds"),
+    ("http://www.w3.org/2001/04/xmlenc#", "xenc"),
+    ("http://www.w3.org/2001/XMLSchema-instance", "xsi"),
+    ("http://www.w3.org/2001/XMLSchema", "xs"),
+    (
+        "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-secext-1.0.xsd",
+        "wsse",
+    ),
+    (
+        "http://docs.oasis-open.org/wss/2004/01/oasis-200401-wss-wssecurity-utility-1.0.xsd",
+        "wsu",
+    ),
+    ("http://www.w3.org/2005/08/addressing", "wsa"),
+    ("http://www.w3.org/XML/1998/namespace", "xml"),
+];
+
+/// A node in a parsed XML document
+///
+/// Text and comments keep the span of their raw text in the document.
+#[derive(Debug, Clone)]
+pub enum XmlNode {
+    /// Child element
+    Element(XmlElement),
+    /// Text or CDATA content
+    Text(String, Option<Span>),
+    /// Comment content
+    Comment(String, Option<Span>),
+}
+
+/// An element with namespace-resolved names
+#[derive(Debug, Clone, Default)]
+pub struct XmlElement {
+    /// Element name in stable prefix form (e.g. `soap:Body`)
+    pub name: String,
+    /// Attributes as (name, unescaped value, span of the raw value),
+    /// excluding namespace declarations
+    pub attributes: Vec<(String, String, Option<Span>)>,
+    /// Child nodes in document order
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlElement {
+    /// Location step for each child element or comment, with a 1-based
+    /// `[n]` index when a name occurs more than once among the siblings
+    pub fn child_steps(&self) -> Vec<Option<String>> {
+        let mut totals: HashMap<&str, usize> = HashMap::new();
+        for child in &self.children {
+            if let Some(step) = step_name(child) {
+                *totals.entry(step).or_default() += 1;
+            }
+        }
+
+        let mut seen: HashMap<&str, usize> = HashMap::new();
+        self.children
+            .iter()
+            .map(|child| {
+                let step = step_name(child)?;
+                let index = seen.entry(step).or_default();
+                *index += 1;
+                Some(if totals[step] > 1 {
+                    format!("{}[{}]", step, index)
+                } else {
+                    step.to_string()
+                })
+            })
+            .collect()
+    }
+}
+
+fn step_name(node: &XmlNode) -> Option<&str> {
+    match node {
+        XmlNode::Element(element) => Some(&element.name),
+        XmlNode::Comment(..) => Some("comment()"),
+        XmlNode::Text(..) => None,
+    }
+}
+
+/// Maps namespace URIs to stable prefixes
+///
+/// Prefixes come from the URI alone, never from the document's own
+/// declarations: well-known namespaces get their conventional prefix, and
+/// the others `ns0`, `ns1`, ... in the order their names first appear.
+#[derive(Default)]
+struct PrefixMap {
+    prefixes: HashMap<Vec<u8>, String>,
+    generated: usize,
+}
+
+impl PrefixMap {
+    fn qualify(&mut self, uri: &[u8], local: &str) -> String {
+        let generated = &mut self.generated;
+        let prefix = self.prefixes.entry(uri.to_vec()).or_insert_with(|| {
+            known_prefix(uri).map(str::to_string).unwrap_or_else(|| {
+                *generated += 1;
+                format!("ns{}", *generated - 1)
+            })
+        });
+        format!("{}:{}", prefix, local)
+    }
+}
+
+fn known_prefix(uri: &[u8]) -> Option<&'static str> {
+    KNOWN_NAMESPACES
+        .iter()
+        .find(|(known, _)| known.as_bytes() == uri)
+        .map(|(_, prefix)| *prefix)
+}
+
+/// Parse an XML document into a synthetic root holding the top-level nodes
+pub fn parse_document(input: &str) -> Result<XmlElement, DecodeckError> {
+    use quick_xml::events::Event;
+    use quick_xml::name::ResolveResult;
+    use quick_xml::NsReader;
+
+    let xml_error = |e: &dyn std::fmt::Display| DecodeckError::DecodeFailed {
+        message: format!("Invalid XML: {}", e),
+    };
+
+    let mut reader = NsReader::from_str(input);
+    reader.config_mut().trim_text(true);
+
+    let mut prefixes = PrefixMap::default();
+    let mut stack: Vec<XmlElement> = vec![XmlElement::default()];
+
+    loop {
+        let event = reader.read_event().map_err(|e| xml_error(&e))?;
+        match event {
+            Event::Start(ref e) | Event::Empty(ref e) => {
+                let (ns, local) = reader.resolve_element(e.name());
+                let local = String::from_utf8_lossy(local.as_ref()).to_string();
+                let name = match ns {
+                    ResolveResult::Bound(ns) => prefixes.qualify(ns.as_ref(), &local),
+                    _ => String::from_utf8_lossy(e.name().as_ref()).to_string(),
+                };
+
+                let mut element = XmlElement {
+                    name,
+                    ..Default::default()
+                };
+                for attr in e.attributes() {
+                    let attr = attr.map_err(|e| xml_error(&e))?;
+                    let key = attr.key.as_ref();
+                    if key == b"xmlns" || key.starts_with(b"xmlns:") {
+                        continue;
+                    }
+                    let (ns, local) = reader.resolve_attribute(attr.key);
+                    let local = String::from_utf8_lossy(local.as_ref()).to_string();
+                    let attr_name = match ns {
+                        ResolveResult::Bound(ns) => prefixes.qualify(ns.as_ref(), &local),
+                        _ => String::from_utf8_lossy(key).to_string(),
+                    };
+                    let value = attr
+                        .unescape_value()
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|_| String::from_utf8_lossy(&attr.value).to_string());
+                    let span = span_of(input, &attr.value);
+                    element.attributes.push((attr_name, value, span));
+                }
+
+                if matches!(event, Event::Empty(_)) {
+                    push_child(&mut stack, XmlNode::Element(element));
+                } else {
+                    stack.push(element);
+                }
+            }
+            Event::End(_) if stack.len() > 1 => {
+                let element = stack.pop().unwrap_or_default();
+                push_child(&mut stack, XmlNode::Element(element));
+            }
+            Event::Text(e) => {
+                let span = span_of(input, &e);
+                let text = e.unescape().unwrap_or_default().to_string();
+                if !text.trim().is_empty() {
+                    push_child(&mut stack, XmlNode::Text(text, span));
+                }
+            }
+            Event::CData(e) => {
+                let span = span_of(input, &e);
+                let text = String::from_utf8_lossy(&e.into_inner()).to_string();
+                if !text.trim().is_empty() {
+                    push_child(&mut stack, XmlNode::Text(text, span));
+                }
+            }
+            Event::Comment(e) => {
+                let span = span_of(input, &e);
+                let text = String::from_utf8_lossy(&e.into_inner()).to_string();
+                if !text.trim().is_empty() {
+                    push_child(&mut stack, XmlNode::Comment(text, span));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    // Close any unterminated elements so partial documents still scan
+    while stack.len() > 1 {
+        let element = stack.pop().unwrap_or_default();
+        push_child(&mut stack, XmlNode::Element(element));
+    }
+
+    Ok(stack.pop().unwrap_or_default())
+}
+
+fn push_child(stack: &mut [XmlElement], node: XmlNode) {
+    if let Some(parent) = stack.last_mut() {
+        parent.children.push(node);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn root(doc: &XmlElement) -> &XmlElement {
+        doc.children
+            .iter()
+            .find_map(|c| match c {
+                XmlNode::Element(e) => Some(e),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_known_namespace_prefix() {
+        let xml = r#"<SOAP-ENV:Envelope xmlns:SOAP-ENV="http://schemas.xmlsoap.org/soap/envelope/"><SOAP-ENV:Body/></SOAP-ENV:Envelope>"#;
+        let doc = parse_document(xml).unwrap();
+        assert_eq!(root(&doc).name, "soap:Envelope");
+    }
+
+    #[test]
+    fn test_declared_and_default_namespaces() {
+        let xml = r#"<a xmlns="urn:one" xmlns:x="urn:two"><x:b x:attr="1" plain="2"/></a>"#;
+        let doc = parse_document(xml).unwrap();
+        let a = root(&doc);
+        assert_eq!(a.name, "ns0:a");
+        let XmlNode::Element(b) = &a.children[0] else {
+            panic!("expected element");
+        };
+        assert_eq!(b.name, "ns1:b");
+        let attributes: Vec<(&str, &str)> = b
+            .attributes
+            .iter()
+            .map(|(name, value, _)| (name.as_str(), value.as_str()))
+            .collect();
+        assert_eq!(attributes, vec![("ns1:attr", "1"), ("plain", "2")]);
+    }
+
+    #[test]
+    fn test_prefixes_do_not_depend_on_declarations() {
+        let names = |xml: &str| {
+            let doc = parse_document(xml).unwrap();
+            let a = root(&doc);
+            let mut names = vec![a.name.clone()];
+            for child in &a.children {
+                if let XmlNode::Element(e) = child {
+                    names.push(e.name.clone());
+                    names.extend(e.attributes.iter().map(|(name, ..)| name.clone()));
+                }
+            }
+            names
+        };
+
+        let first = names(r#"<a xmlns="urn:one" xmlns:p="urn:two"><p:b p:c="1"/></a>"#);
+        let second = names(
+            r#"<q:a xmlns:q="urn:one" xmlns:unused="urn:three"><b xmlns="urn:two" xmlns:r="urn:two" r:c="1"/></q:a>"#,
+        );
+        assert_eq!(first, vec!["ns0:a", "ns1:b", "ns1:c"]);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_reused_prefix_gets_distinct_name() {
+        let xml = r#"<a xmlns:x="urn:one"><x:v/><b xmlns:x="urn:two"><x:v/></b><soap:c xmlns:soap="urn:three"/></a>"#;
+        let doc = parse_document(xml).unwrap();
+        let a = root(&doc);
+        let names: Vec<&str> = a
+            .children
+            .iter()
+            .map(|c| match c {
+                XmlNode::Element(e) => e.name.as_str(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(names, vec!["ns0:v", "b", "ns2:c"]);
+        let XmlNode::Element(b) = &a.children[1] else {
+            panic!("expected element");
+        };
+        assert!(matches!(&b.children[0], XmlNode::Element(v) if v.name == "ns1:v"));
+    }
+
+    #[test]
+    fn test_child_steps_index_repeated_siblings() {
+        let doc = parse_document("<r><item/><other/><item/><!--c--></r>").unwrap();
+        assert_eq!(
+            root(&doc).child_steps(),
+            vec![
+                Some("item[1]".to_string()),
+                Some("other".to_string()),
+                Some("item[2]".to_string()),
+                Some("comment()".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cdata_is_text() {
+        let doc = parse_document("<r><![CDATA[a < b]]></r>").unwrap();
+        assert!(matches!(&root(&doc).children[0], XmlNode::Text(t, _) if t == "a < b"));
+    }
+
+    #[test]
+    fn test_value_spans() {
+        let xml = "<r a=\"x &amp; y\">\n  t&lt;1 <![CDATA[c]]><!--m--></r>";
+        let doc = parse_document(xml).unwrap();
+        let r = root(&doc);
+        let raw = |span: &Option<Span>| &xml[span.clone().unwrap()];
+
+        assert_eq!(raw(&r.attributes[0].2), "x &amp; y");
+        let spans: Vec<&str> = r
+            .children
+            .iter()
+            .map(|child| match child {
+                XmlNode::Text(_, span) | XmlNode::Comment(_, span) => raw(span),
+                XmlNode::Element(_) => "",
+            })
+            .collect();
+        assert_eq!(spans, vec!["t&lt;1", "c", "m"]);
+    }
+}
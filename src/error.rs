@@ -26,6 +26,49 @@ pub enum DecodeckError {
     #[error("Invalid output path: {path} - {reason}")]
     InvalidOutputPath { path: String, reason: String },
 
+    #[error("Invalid path: {path} - {reason}")]
+    InvalidPath { path: String, reason: String },
+
+    #[error("Path not found: {path}")]
+    PathNotFound { path: String },
+
+    #[error("Invalid recipe step '{step}': {reason}")]
+    InvalidRecipe { step: String, reason: String },
+
+    #[error("Invalid archive: {message}")]
+    InvalidArchive { message: String },
+
+    #[error("Unsafe archive: {reason}")]
+    UnsafeArchive { reason: String },
+
+    #[error("Invalid signature file {path}: {reason}")]
+    InvalidSignatureFile { path: String, reason: String },
+
+    #[error("Invalid hash '{hash}': {reason}")]
+    InvalidHash { hash: String, reason: String },
+
+    #[error("Hash mismatch: expected {algorithm} {expected}, got {actual}")]
+    HashMismatch {
+        algorithm: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Invalid age '{age}': expected a number with unit s, m, h, d or w")]
+    InvalidAge { age: String },
+
+    #[error("Invalid name template '{template}': {reason}")]
+    InvalidTemplate { template: String, reason: String },
+
+    #[error("Invalid variable name '{name}': use letters, digits and underscores")]
+    InvalidIdentifier { name: String },
+
+    #[error("Option {option} does not apply to {encoding} encoding")]
+    UnsupportedOption { option: String, encoding: String },
+
+    #[error("Cannot redact {path}: its value could not be located in the document")]
+    RedactFailed { path: String },
+
     #[error("Decode failed: {message}")]
     DecodeFailed { message: String },
 
@@ -1,5 +1,6 @@
 //! Interactive terminal module
 
+use crate::metadata::risk::{Risk, RiskLevel};
 use crate::metadata::ContentMetadata;
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent},
@@ -7,6 +8,17 @@ use crossterm::{
 };
 use std::io::{IsTerminal, Write};
 
+/// Whether decoded content may be handed to the system opener
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenPolicy {
+    /// Open on space or enter
+    Allow,
+    /// Open only on an explicit `y`
+    Confirm,
+    /// Never open
+    Refuse,
+}
+
 /// Interactive prompt handler
 pub struct InteractivePrompt {
     /// Whether to show the prompt
@@ -33,22 +45,65 @@ impl InteractivePrompt {
         }
     }
 
+    /// Policy for content with the given risk
+    pub fn open_policy(risk: Option<&Risk>) -> OpenPolicy {
+        match risk.map(|r| r.level) {
+            None => OpenPolicy::Allow,
+            Some(RiskLevel::Medium) => OpenPolicy::Confirm,
+            Some(RiskLevel::High) => OpenPolicy::Refuse,
+        }
+    }
+
     /// Show prompt and wait for user input
-    /// Returns true if user pressed space or enter
-    pub fn show_and_wait(&self, metadata: &ContentMetadata) -> bool {
+    /// Returns true if user pressed space or enter, or `y` for risky content
+    /// that needs confirmation
+    pub fn show_and_wait(&self, metadata: &ContentMetadata, risk: Option<&Risk>) -> bool {
         if !self.enabled {
             return false;
         }
 
-        if let Some(prompt) = Self::prompt_text(metadata) {
-            println!();
-            print!("{}", prompt);
-            let _ = std::io::stdout().flush();
+        let Some(prompt) = Self::prompt_text(metadata) else {
+            return false;
+        };
+        println!();
+        match (Self::open_policy(risk), risk) {
+            (OpenPolicy::Refuse, Some(risk)) => {
+                println!("Not opening {}: {}", risk.kind.description(), risk.reason);
+                false
+            }
+            (OpenPolicy::Confirm, Some(risk)) => {
+                print!(
+                    "Content is a {} ({}). Press y to open anyway...",
+                    risk.kind.description(),
+                    risk.reason
+                );
+                let _ = std::io::stdout().flush();
+                self.wait_for_confirmation()
+            }
+            _ => {
+                print!("{}", prompt);
+                let _ = std::io::stdout().flush();
+                self.wait_for_key()
+            }
+        }
+    }
 
-            self.wait_for_key()
-        } else {
-            false
+    /// Wait for key press and return true if `y`; any other key declines
+    fn wait_for_confirmation(&self) -> bool {
+        if terminal::enable_raw_mode().is_err() {
+            return false;
         }
+
+        let result = loop {
+            if let Ok(Event::Key(KeyEvent { code, .. })) = event::read() {
+                break matches!(code, KeyCode::Char('y') | KeyCode::Char('Y'));
+            }
+        };
+
+        let _ = terminal::disable_raw_mode();
+        println!();
+
+        result
     }
 
     /// Wait for key press and return true if space or enter
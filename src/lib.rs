@@ -4,8 +4,10 @@
 //! - Decoding multiple formats: Base64, Hex, Base32, URL, Ascii85
 //! - Auto-detecting encoding types
 //! - Detecting content MIME types via magic bytes
+//! - Extracting indicators of compromise from decoded content
 //! - Formatting output in text and JSON formats
 
+pub mod analysis;
 pub mod decoder;
 pub mod encoding;
 pub mod error;
@@ -13,6 +15,7 @@ pub mod input;
 pub mod interactive;
 pub mod metadata;
 pub mod output;
+mod util;
 
 pub use error::DecodeckError;
 
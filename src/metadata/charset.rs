@@ -0,0 +1,277 @@
+//! Text encoding detection for decoded content
+//!
+//! Decoded payloads are often text in something other than UTF-8: PowerShell
+//! `-EncodedCommand` arguments are UTF-16LE, Windows tools emit
+//! Windows-1252 and Japanese systems Shift-JIS. Detection looks for a byte
+//! order mark first, then for the zero-byte patterns of UTF-16/UTF-32, then
+//! tries the multi-byte and single-byte encodings, accepting a candidate only
+//! if it decodes to printable text. Every byte is valid Latin-1, so the
+//! single-byte encodings also need the text to look like Western prose:
+//! mostly ASCII, with letters and typographic punctuation above it.
+
+use serde::Serialize;
+
+/// Minimum share of ASCII code units in BOM-less UTF-16/UTF-32 text
+const MIN_WIDE_ASCII_RATIO: f64 = 0.5;
+
+/// Minimum share of ASCII characters in Latin-1 or Windows-1252 text
+const MIN_SINGLE_BYTE_ASCII_RATIO: f64 = 0.7;
+
+/// Non-letter characters above ASCII that are common in Western text
+const TYPOGRAPHIC_PUNCTUATION: &[char] = &[
+    '\u{a0}', '¡', '¢', '£', '¥', '§', '©', '«', '®', '°', '±', '²', '³', '´', '·', '»', '¿', '€',
+    '‚', '„', '…', '•', '–', '—', '‘', '’', '“', '”', '™',
+];
+
+/// Text encoding of decoded content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Charset {
+    #[serde(rename = "utf-8")]
+    Utf8,
+    #[serde(rename = "utf-16le")]
+    Utf16Le,
+    #[serde(rename = "utf-16be")]
+    Utf16Be,
+    #[serde(rename = "utf-32le")]
+    Utf32Le,
+    #[serde(rename = "utf-32be")]
+    Utf32Be,
+    #[serde(rename = "iso-8859-1")]
+    Latin1,
+    #[serde(rename = "windows-1252")]
+    Windows1252,
+    #[serde(rename = "shift_jis")]
+    ShiftJis,
+}
+
+impl Charset {
+    /// Charset name as used in MIME parameters
+    pub fn name(&self) -> &'static str {
+        match self {
+            Charset::Utf8 => "utf-8",
+            Charset::Utf16Le => "utf-16le",
+            Charset::Utf16Be => "utf-16be",
+            Charset::Utf32Le => "utf-32le",
+            Charset::Utf32Be => "utf-32be",
+            Charset::Latin1 => "iso-8859-1",
+            Charset::Windows1252 => "windows-1252",
+            Charset::ShiftJis => "shift_jis",
+        }
+    }
+}
+
+impl std::fmt::Display for Charset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Detect the text encoding of decoded bytes
+///
+/// Returns `None` for empty or binary content.
+pub fn detect(data: &[u8]) -> Option<Charset> {
+    decode_text(data).map(|(charset, _)| charset)
+}
+
+/// Detect the text encoding of decoded bytes and transcode them to UTF-8
+///
+/// A byte order mark is removed from the returned text.
+pub fn decode_text(data: &[u8]) -> Option<(Charset, String)> {
+    if data.is_empty() {
+        return None;
+    }
+
+    if let Some(result) = decode_with_bom(data) {
+        return Some(result).filter(|(_, text)| is_printable(text));
+    }
+
+    // ASCII in UTF-16 is valid UTF-8 too, but full of NULs
+    if let Ok(text) = std::str::from_utf8(data) {
+        if is_printable(text) {
+            return Some((Charset::Utf8, text.to_string()));
+        }
+    }
+
+    let candidates = [
+        Charset::Utf32Le,
+        Charset::Utf32Be,
+        Charset::Utf16Le,
+        Charset::Utf16Be,
+        Charset::ShiftJis,
+        Charset::Latin1,
+        Charset::Windows1252,
+    ];
+    candidates.into_iter().find_map(|charset| {
+        let text = decode_as(data, charset)?;
+        let plausible = match charset {
+            Charset::Utf16Le | Charset::Utf16Be | Charset::Utf32Le | Charset::Utf32Be => {
+                mostly_ascii(&text)
+            }
+            Charset::Latin1 | Charset::Windows1252 => looks_like_western_text(&text),
+            _ => true,
+        };
+        (is_printable(&text) && plausible).then_some((charset, text))
+    })
+}
+
+/// Decode bytes with a known charset, or `None` if they are not valid in it
+pub fn decode_as(data: &[u8], charset: Charset) -> Option<String> {
+    match charset {
+        Charset::Utf8 => String::from_utf8(data.to_vec()).ok(),
+        Charset::Utf16Le => decode_utf16(data, u16::from_le_bytes),
+        Charset::Utf16Be => decode_utf16(data, u16::from_be_bytes),
+        Charset::Utf32Le => decode_utf32(data, u32::from_le_bytes),
+        Charset::Utf32Be => decode_utf32(data, u32::from_be_bytes),
+        Charset::Latin1 => {
+            // C1 control codes mean the text is really Windows-1252
+            if data.iter().any(|b| (0x80..0xa0).contains(b)) {
+                return None;
+            }
+            Some(data.iter().map(|&b| b as char).collect())
+        }
+        Charset::Windows1252 => {
+            let (text, had_errors) = encoding_rs::WINDOWS_1252.decode_without_bom_handling(data);
+            (!had_errors).then(|| text.into_owned())
+        }
+        Charset::ShiftJis => {
+            let (text, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(data);
+            // Short Latin-1 strings can form valid double-byte sequences by
+            // chance; real Japanese text practically always contains kana
+            let has_kana = text.chars().any(|c| ('\u{3041}'..='\u{30ff}').contains(&c));
+            (!had_errors && has_kana).then(|| text.into_owned())
+        }
+    }
+}
+
+fn decode_with_bom(data: &[u8]) -> Option<(Charset, String)> {
+    let (charset, bom_len) = match data {
+        [0xef, 0xbb, 0xbf, ..] => (Charset::Utf8, 3),
+        [0xff, 0xfe, 0x00, 0x00, ..] => (Charset::Utf32Le, 4),
+        [0x00, 0x00, 0xfe, 0xff, ..] => (Charset::Utf32Be, 4),
+        [0xff, 0xfe, ..] => (Charset::Utf16Le, 2),
+        [0xfe, 0xff, ..] => (Charset::Utf16Be, 2),
+        _ => return None,
+    };
+    decode_as(&data[bom_len..], charset).map(|text| (charset, text))
+}
+
+fn decode_utf16(data: &[u8], unit: fn([u8; 2]) -> u16) -> Option<String> {
+    if data.len() % 2 != 0 {
+        return None;
+    }
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|pair| unit([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16(&units).ok()
+}
+
+fn decode_utf32(data: &[u8], unit: fn([u8; 4]) -> u32) -> Option<String> {
+    if data.len() % 4 != 0 {
+        return None;
+    }
+    data.chunks_exact(4)
+        .map(|quad| char::from_u32(unit([quad[0], quad[1], quad[2], quad[3]])))
+        .collect()
+}
+
+/// Without a BOM, almost any even-length data is valid UTF-16, so wide
+/// encodings are only accepted when most characters are ASCII
+fn mostly_ascii(text: &str) -> bool {
+    let total = text.chars().count();
+    let ascii = text.chars().filter(char::is_ascii).count();
+    total > 0 && ascii as f64 / total as f64 >= MIN_WIDE_ASCII_RATIO
+}
+
+/// Single-byte text is mostly ASCII, and what is not is letters or
+/// punctuation; binary data decodes to symbols all over the code page
+fn looks_like_western_text(text: &str) -> bool {
+    let total = text.chars().count();
+    let ascii = text.chars().filter(char::is_ascii).count();
+    total > 0
+        && ascii as f64 / total as f64 >= MIN_SINGLE_BYTE_ASCII_RATIO
+        && text
+            .chars()
+            .filter(|c| !c.is_ascii())
+            .all(|c| c.is_alphabetic() || TYPOGRAPHIC_PUNCTUATION.contains(&c))
+}
+
+/// Check that text contains no control characters other than whitespace
+fn is_printable(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|c| !c.is_control() || c.is_whitespace())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16le(s: &str) -> Vec<u8> {
+        s.encode_utf16().flat_map(|u| u.to_le_bytes()).collect()
+    }
+
+    #[test]
+    fn test_detect_utf8() {
+        assert_eq!(detect(b"Hello World"), Some(Charset::Utf8));
+        assert_eq!(detect("caf\u{e9}".as_bytes()), Some(Charset::Utf8));
+    }
+
+    #[test]
+    fn test_detect_utf16() {
+        let data = utf16le("Write-Host hi");
+        assert_eq!(
+            decode_text(&data),
+            Some((Charset::Utf16Le, "Write-Host hi".to_string()))
+        );
+
+        let mut with_bom = vec![0xfe, 0xff];
+        with_bom.extend("Hi".encode_utf16().flat_map(|u| u.to_be_bytes()));
+        assert_eq!(
+            decode_text(&with_bom),
+            Some((Charset::Utf16Be, "Hi".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_detect_utf32() {
+        let data: Vec<u8> = "Hi!"
+            .chars()
+            .flat_map(|c| (c as u32).to_le_bytes())
+            .collect();
+        assert_eq!(detect(&data), Some(Charset::Utf32Le));
+    }
+
+    #[test]
+    fn test_detect_single_byte() {
+        assert_eq!(detect(b"caf\xe9"), Some(Charset::Latin1));
+        assert_eq!(detect(b"\x93quoted\x94"), Some(Charset::Windows1252));
+        assert_eq!(detect(b"Se\xf1or \x80 5"), Some(Charset::Windows1252));
+        assert_eq!(decode_as(b"caf\xe9", Charset::Latin1).unwrap(), "caf\u{e9}");
+    }
+
+    #[test]
+    fn test_detect_shift_jis() {
+        // "こんにちは" in Shift-JIS
+        let data = b"\x82\xb1\x82\xf1\x82\xc9\x82\xbf\x82\xcd";
+        assert_eq!(
+            decode_text(data),
+            Some((Charset::ShiftJis, "こんにちは".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_binary_is_not_text() {
+        assert_eq!(
+            detect(&[0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a]),
+            None
+        );
+        assert_eq!(detect(&[0x00, 0x01, 0x02, 0x03]), None);
+        // High bytes without control characters are still binary
+        assert_eq!(detect(b"\xde\xad\xbe\xef"), None);
+        assert_eq!(
+            detect(b"\xc3\xa9\xff\xa0\xde\xad\xbe\xef\xb0\xb1\xb2\xb3\xe4\xe5\xe6\xf7"),
+            None
+        );
+        assert_eq!(detect(b"ab\xb1\xb2\xb3\xd7cd"), None);
+        assert_eq!(detect(&[]), None);
+    }
+}
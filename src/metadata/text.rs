@@ -0,0 +1,243 @@
+//! Content type of decoded text
+//!
+//! Text has no magic bytes, so its type is inferred from its structure:
+//! JSON that parses, markup root elements, PEM armour and shebang lines are
+//! strong evidence; source code is recognised by how many keywords of a
+//! language it uses. Anything else is plain text.
+
+use crate::encoding::DetectionConfidence;
+
+/// Bytes of text examined for markers
+const HEAD_LEN: usize = 4096;
+
+/// Distinct keywords a text needs to be taken for source code
+const MIN_KEYWORDS: usize = 3;
+
+/// Type of a text document
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextType {
+    pub mime_type: &'static str,
+    /// Extension without the leading dot
+    pub extension: &'static str,
+    /// Name of the heuristic that matched
+    pub rule: &'static str,
+    pub confidence: DetectionConfidence,
+}
+
+impl TextType {
+    const fn new(
+        mime_type: &'static str,
+        extension: &'static str,
+        rule: &'static str,
+        confidence: DetectionConfidence,
+    ) -> Self {
+        Self {
+            mime_type,
+            extension,
+            rule,
+            confidence,
+        }
+    }
+}
+
+/// Interpreters named in shebang lines
+const INTERPRETERS: &[(&str, TextType)] = &[
+    ("python", script("text/x-python", "py")),
+    ("pwsh", script("text/x-powershell", "ps1")),
+    ("node", script("text/javascript", "js")),
+    ("perl", script("text/x-perl", "pl")),
+    ("ruby", script("text/x-ruby", "rb")),
+    ("sh", script("text/x-shellscript", "sh")),
+];
+
+const fn script(mime_type: &'static str, extension: &'static str) -> TextType {
+    TextType::new(mime_type, extension, "shebang", DetectionConfidence::High)
+}
+
+/// Source languages with lower-case keywords characteristic of them
+const LANGUAGES: &[(TextType, &[&str])] = &[
+    (
+        source("text/x-powershell", "ps1", "powershell"),
+        &[
+            "param(",
+            "$_",
+            "write-host",
+            "new-object",
+            "-eq ",
+            "get-",
+            "invoke-",
+        ],
+    ),
+    (
+        source("text/x-python", "py", "python"),
+        &["def ", "import ", "self.", "elif ", "__name__", "print("],
+    ),
+    (
+        source("text/javascript", "js", "javascript"),
+        &[
+            "function",
+            "var ",
+            "const ",
+            "=>",
+            "document.",
+            "console.log",
+            "===",
+            "window.",
+        ],
+    ),
+    (
+        source("text/x-c", "c", "c"),
+        &[
+            "#include",
+            "int main",
+            "printf(",
+            "sizeof(",
+            "return 0;",
+            "->",
+        ],
+    ),
+    (
+        source("text/x-rust", "rs", "rust"),
+        &[
+            "fn ",
+            "let mut ",
+            "impl ",
+            "pub fn",
+            "use std::",
+            "println!",
+            "::new(",
+        ],
+    ),
+    (
+        source("text/x-shellscript", "sh", "shell"),
+        &["echo ", "; then", "\nfi", "esac", "$(", "export "],
+    ),
+    (
+        source("text/x-msdos-batch", "bat", "batch"),
+        &["@echo off", "set ", "goto ", "%~", "errorlevel", "rem "],
+    ),
+    (
+        source("text/vbscript", "vbs", "vbscript"),
+        &[
+            "dim ",
+            "set ",
+            "createobject(",
+            "end sub",
+            "wscript.",
+            "end if",
+        ],
+    ),
+];
+
+const fn source(mime_type: &'static str, extension: &'static str, rule: &'static str) -> TextType {
+    TextType::new(mime_type, extension, rule, DetectionConfidence::Low)
+}
+
+/// Classify decoded text
+pub fn classify(text: &str) -> TextType {
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    let head: String = trimmed.chars().take(HEAD_LEN).collect();
+    let lower = head.to_lowercase();
+
+    if head.starts_with("-----BEGIN ") {
+        return TextType::new(
+            "application/x-pem-file",
+            "pem",
+            "pem",
+            DetectionConfidence::High,
+        );
+    }
+    if (head.starts_with('{') || head.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return TextType::new(
+            "application/json",
+            "json",
+            "json",
+            DetectionConfidence::High,
+        );
+    }
+    if head.starts_with('<') {
+        if lower.contains("<svg") {
+            return TextType::new("image/svg+xml", "svg", "svg", DetectionConfidence::Medium);
+        }
+        if lower.starts_with("<!doctype html") || lower.contains("<html") {
+            return TextType::new("text/html", "html", "html", DetectionConfidence::Medium);
+        }
+        if lower.starts_with("<?xml") {
+            return TextType::new("application/xml", "xml", "xml", DetectionConfidence::Medium);
+        }
+    }
+    if let Some(shebang) = head.lines().next().filter(|l| l.starts_with("#!")) {
+        return INTERPRETERS
+            .iter()
+            .find(|(name, _)| shebang.contains(name))
+            .map(|&(_, text_type)| text_type)
+            // An unknown interpreter says little about the content
+            .unwrap_or(TextType::new(
+                "text/x-script",
+                "txt",
+                "shebang",
+                DetectionConfidence::Low,
+            ));
+    }
+
+    // Reversed so the earlier language wins ties
+    LANGUAGES
+        .iter()
+        .rev()
+        .map(|(text_type, keywords)| {
+            let found = keywords.iter().filter(|k| lower.contains(*k)).count();
+            (*text_type, found)
+        })
+        .filter(|&(_, found)| found >= MIN_KEYWORDS)
+        .max_by_key(|&(_, found)| found)
+        .map_or(
+            TextType::new("text/plain", "txt", "plain", DetectionConfidence::Medium),
+            |(text_type, _)| text_type,
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_structured_text() {
+        let json = classify("  {\"user\": \"alice\", \"roles\": [1, 2]}\n");
+        assert_eq!(json.mime_type, "application/json");
+        assert_eq!(json.confidence, DetectionConfidence::High);
+        assert_eq!(classify("{not json").mime_type, "text/plain");
+
+        let svg = "<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+        assert_eq!(classify(svg).extension, "svg");
+        assert_eq!(classify("<?xml version=\"1.0\"?><a/>").rule, "xml");
+        assert_eq!(classify("<!DOCTYPE html><p>hi").mime_type, "text/html");
+        assert_eq!(
+            classify("-----BEGIN CERTIFICATE-----\nMIIB").extension,
+            "pem"
+        );
+        assert_eq!(
+            classify("#!/usr/bin/env python3\nprint(1)").mime_type,
+            "text/x-python"
+        );
+        let unknown = classify("#!/opt/tool/run\nstep one");
+        assert_eq!(unknown.mime_type, "text/x-script");
+        assert_eq!(unknown.confidence, DetectionConfidence::Low);
+    }
+
+    #[test]
+    fn test_source_code() {
+        let powershell =
+            "param($Url)\n$wc = New-Object Net.WebClient\nif ($x -eq 1) { Write-Host $_ }";
+        let detected = classify(powershell);
+        assert_eq!(detected.extension, "ps1");
+        assert_eq!(detected.confidence, DetectionConfidence::Low);
+
+        let c = "#include <stdio.h>\nint main(void) {\n    printf(\"hi\");\n    return 0;\n}";
+        assert_eq!(classify(c).rule, "c");
+
+        let prose = "Meeting moved to Thursday. Bring the printouts and the budget.";
+        assert_eq!(classify(prose).mime_type, "text/plain");
+    }
+}
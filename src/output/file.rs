@@ -0,0 +1,289 @@
+//! Writing decoded output files
+//!
+//! Data goes to a hidden file next to the target, which is moved into place
+//! once complete: an interrupted write never leaves a truncated output, and
+//! an existing file is only replaced by a finished one. Unless replacing is
+//! allowed, the file is hard-linked into place, which fails rather than
+//! replace a file that appeared in the meantime; where hard links are not
+//! supported, it is copied to a newly created file instead.
+
+use super::validate_output_path;
+use crate::error::DecodeckError;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Attempts at finding a free name before giving up
+const MAX_ATTEMPTS: u32 = 1000;
+
+/// What to do when the output file already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Overwrite {
+    /// Fail with `OutputExists`
+    #[default]
+    Refuse,
+    /// Replace the file
+    Replace,
+    /// Replace the file, keeping the old one as `<name>.bak` (or
+    /// `<name> (1).bak`, ... if that is taken)
+    Backup,
+    /// Write to `name (1).ext`, `name (2).ext`, ... instead
+    Suffix,
+}
+
+/// Write data to `path`, returning the path actually written
+///
+/// The path is validated first; a replaced file's permissions carry over to
+/// the new one.
+pub fn write_output(
+    path: &Path,
+    data: &[u8],
+    overwrite: Overwrite,
+) -> Result<PathBuf, DecodeckError> {
+    validate_output_path(path)?;
+    let staging = stage(path, data)?;
+    let result = commit(path, &staging, overwrite);
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result
+}
+
+/// Path an existing file is kept at by `Overwrite::Backup`
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+/// Write data to a new hidden file in the directory of `path`
+fn stage(path: &Path, data: &[u8]) -> Result<PathBuf, DecodeckError> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    for attempt in 0..MAX_ATTEMPTS {
+        let staging =
+            path.with_file_name(format!(".{}.{}-{}.tmp", name, std::process::id(), attempt));
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&staging)
+        {
+            Ok(mut file) => {
+                let written = file.write_all(data).and_then(|_| file.sync_all());
+                if let Err(e) = written {
+                    let _ = fs::remove_file(&staging);
+                    return Err(e.into());
+                }
+                return Ok(staging);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(no_free_name(path))
+}
+
+/// Move the staged file into place
+fn commit(path: &Path, staging: &Path, overwrite: Overwrite) -> Result<PathBuf, DecodeckError> {
+    let target = match overwrite {
+        Overwrite::Refuse | Overwrite::Suffix => {
+            // Linking fails if anything exists at the path, even a file
+            // created after any check, so nothing is ever replaced
+            match link_new(staging, path) {
+                Ok(()) => {
+                    fs::remove_file(staging)?;
+                    return Ok(path.to_path_buf());
+                }
+                Err(e) if e.kind() != ErrorKind::AlreadyExists => return Err(e.into()),
+                Err(_) if overwrite == Overwrite::Refuse => {
+                    return Err(DecodeckError::OutputExists {
+                        path: path.display().to_string(),
+                    })
+                }
+                Err(_) => claim_free_name(path)?,
+            }
+        }
+        Overwrite::Replace | Overwrite::Backup => match fs::metadata(path) {
+            Err(e) if e.kind() == ErrorKind::NotFound => path.to_path_buf(),
+            Err(e) => return Err(e.into()),
+            Ok(metadata) => {
+                if overwrite == Overwrite::Backup {
+                    keep_backup(path)?;
+                }
+                fs::set_permissions(staging, metadata.permissions())?;
+                path.to_path_buf()
+            }
+        },
+    };
+    fs::rename(staging, &target)?;
+    Ok(target)
+}
+
+/// Link `from` to the new name `to`, failing if `to` exists
+///
+/// Where hard links are not supported, `from` is copied to a newly created
+/// file instead, which fails the same way.
+fn link_new(from: &Path, to: &Path) -> io::Result<()> {
+    match fs::hard_link(from, to) {
+        Err(e) if e.kind() != ErrorKind::AlreadyExists => {
+            let mut source = fs::File::open(from)?;
+            let mut file = OpenOptions::new().write(true).create_new(true).open(to)?;
+            let copied = io::copy(&mut source, &mut file).and_then(|_| file.sync_all());
+            if copied.is_err() {
+                let _ = fs::remove_file(to);
+            }
+            copied
+        }
+        linked => linked,
+    }
+}
+
+/// Keep the file at `path` as its backup, never replacing an earlier one
+fn keep_backup(path: &Path) -> Result<PathBuf, DecodeckError> {
+    let backup = backup_path(path);
+    for n in 0..=MAX_ATTEMPTS {
+        let candidate = match n {
+            0 => backup.clone(),
+            n => numbered(&backup, n),
+        };
+        match link_new(path, &candidate) {
+            Ok(()) => return Ok(candidate),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(no_free_name(&backup))
+}
+
+/// `name (n).ext` next to `path`
+fn numbered(path: &Path, n: u32) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{} ({}).{}", stem, n, extension.to_string_lossy()),
+        None => format!("{} ({})", stem, n),
+    };
+    path.with_file_name(name)
+}
+
+/// Create an empty `name (N).ext` next to `path`, so no other writer takes
+/// it before the staged file is renamed over it
+fn claim_free_name(path: &Path) -> Result<PathBuf, DecodeckError> {
+    for n in 1..=MAX_ATTEMPTS {
+        let candidate = numbered(path, n);
+        match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&candidate)
+        {
+            Ok(_) => return Ok(candidate),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(no_free_name(path))
+}
+
+fn no_free_name(path: &Path) -> DecodeckError {
+    DecodeckError::InvalidOutputPath {
+        path: path.display().to_string(),
+        reason: "No free file name next to it".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_write_and_refuse() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out.txt");
+        assert_eq!(
+            write_output(&path, b"one", Overwrite::Refuse).unwrap(),
+            path
+        );
+        assert!(matches!(
+            write_output(&path, b"two", Overwrite::Refuse),
+            Err(DecodeckError::OutputExists { .. })
+        ));
+        assert_eq!(fs::read(&path).unwrap(), b"one");
+        // No staging files are left behind
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_replace_with_backup() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out.txt");
+        fs::write(&path, b"old").unwrap();
+        write_output(&path, b"new", Overwrite::Backup).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read(dir.path().join("out.txt.bak")).unwrap(), b"old");
+
+        write_output(&path, b"newer", Overwrite::Replace).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"newer");
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"old");
+
+        // An earlier backup is kept too
+        write_output(&path, b"newest", Overwrite::Backup).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"newest");
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"old");
+        assert_eq!(
+            fs::read(dir.path().join("out.txt (1).bak")).unwrap(),
+            b"newer"
+        );
+    }
+
+    #[test]
+    fn test_link_new() {
+        let dir = TempDir::new().unwrap();
+        let from = dir.path().join("from");
+        fs::write(&from, b"data").unwrap();
+        link_new(&from, &dir.path().join("to")).unwrap();
+        assert_eq!(fs::read(dir.path().join("to")).unwrap(), b"data");
+        assert_eq!(
+            link_new(&from, &dir.path().join("to")).unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+
+        // Nothing is left behind when neither linking nor copying works
+        let missing_dir = dir.path().join("missing").join("from");
+        assert_eq!(
+            link_new(&missing_dir, &dir.path().join("copy"))
+                .unwrap_err()
+                .kind(),
+            ErrorKind::NotFound
+        );
+        assert!(!dir.path().join("copy").exists());
+    }
+
+    #[test]
+    fn test_suffix() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out.txt");
+        fs::write(&path, b"old").unwrap();
+        let first = write_output(&path, b"a", Overwrite::Suffix).unwrap();
+        let second = write_output(&path, b"b", Overwrite::Suffix).unwrap();
+        assert_eq!(first, dir.path().join("out (1).txt"));
+        assert_eq!(second, dir.path().join("out (2).txt"));
+        assert_eq!(fs::read(&path).unwrap(), b"old");
+        assert_eq!(fs::read(&second).unwrap(), b"b");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_refuse_target_created_after_validation() {
+        // Another process creates a dangling symlink once the path has been
+        // validated; it must not be replaced even though nothing can be read
+        // through it
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("out.txt");
+        let staging = stage(&path, b"data").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("missing"), &path).unwrap();
+        assert!(matches!(
+            commit(&path, &staging, Overwrite::Refuse),
+            Err(DecodeckError::OutputExists { .. })
+        ));
+        assert!(fs::symlink_metadata(&path).unwrap().is_symlink());
+    }
+}
@@ -0,0 +1,387 @@
+//! Temporary decode outputs and their history
+//!
+//! Decodes without `--output` are written to uniquely named files under
+//! `$TMP/decodeck`, created exclusively and readable only by their owner, so
+//! concurrent decodes never overwrite each other. Every output is recorded
+//! in `history.jsonl` next to them, which `decodeck history` lists and
+//! `decodeck clean` prunes along with old temporary files.
+//!
+//! The temporary directory is shared with other users, so the output
+//! directory must belong to the current user (its mode is tightened to 0700
+//! if needed), and files in it are never opened through a symlink.
+
+use crate::error::DecodeckError;
+use crate::util;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// History file in the temporary output directory
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Attempts at finding a free name before giving up
+const MAX_ATTEMPTS: u32 = 1000;
+
+/// A decode output recorded in the history
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub path: PathBuf,
+    /// Seconds since the Unix epoch
+    pub created: u64,
+    /// Where the encoded input came from: "argument", "stdin" or a file path
+    pub source: String,
+    pub encoding: String,
+    pub mime_type: String,
+    pub size_bytes: usize,
+    pub is_temporary: bool,
+}
+
+impl HistoryEntry {
+    /// Creation time as an RFC 3339 UTC timestamp
+    pub fn created_at(&self) -> String {
+        util::rfc3339(self.created)
+    }
+}
+
+/// Directory for temporary outputs and the history
+pub fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join("decodeck")
+}
+
+/// Seconds since the Unix epoch
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Write decoded data to a new file in `dir`
+///
+/// Files are named `output-<UTC time>-<SHA-256 prefix><extension>`, with a
+/// counter added if that name is taken. The file is created with `O_EXCL`
+/// and, on Unix, mode 0600; the directory is created with mode 0700.
+pub fn write_temp_output(
+    dir: &Path,
+    data: &[u8],
+    extension: &str,
+) -> Result<PathBuf, DecodeckError> {
+    create_private_dir(dir)?;
+    let stem = format!(
+        "output-{}-{}",
+        util::compact_timestamp(now()),
+        util::hex(&Sha256::digest(data)[..4])
+    );
+
+    for attempt in 0..MAX_ATTEMPTS {
+        let name = match attempt {
+            0 => format!("{}{}", stem, extension),
+            n => format!("{}-{}{}", stem, n, extension),
+        };
+        let path = dir.join(name);
+        match create_private(&path, OpenOptions::new().write(true).create_new(true)) {
+            Ok(mut file) => {
+                file.write_all(data)?;
+                return Ok(path);
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Err(DecodeckError::SystemError {
+        message: format!("No free output name in {}", dir.display()),
+    })
+}
+
+/// Append an entry to the history in `dir`
+pub fn record(dir: &Path, entry: &HistoryEntry) -> Result<(), DecodeckError> {
+    create_private_dir(dir)?;
+    let mut line = serde_json::to_string(entry).map_err(std::io::Error::other)?;
+    line.push('\n');
+    let mut file = create_private(
+        &dir.join(HISTORY_FILE),
+        OpenOptions::new().append(true).create(true),
+    )?;
+    // One write per entry keeps concurrent appends from interleaving
+    file.write_all(line.as_bytes())?;
+    Ok(())
+}
+
+/// Entries of the history in `dir`, oldest first
+///
+/// Lines that cannot be parsed are skipped.
+pub fn load(dir: &Path) -> Result<Vec<HistoryEntry>, DecodeckError> {
+    let file = match no_follow(OpenOptions::new().read(true)).open(dir.join(HISTORY_FILE)) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(entry) = serde_json::from_str(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Remove temporary outputs in `dir` older than `age` and drop old entries
+/// from the history
+///
+/// Files written with `--output` are never removed, only forgotten. With
+/// `dry_run` nothing is changed. Returns the files removed (or that would be).
+pub fn clean(dir: &Path, age: Duration, dry_run: bool) -> Result<Vec<PathBuf>, DecodeckError> {
+    let cutoff = SystemTime::now().checked_sub(age).unwrap_or(UNIX_EPOCH);
+    let cutoff_secs = cutoff
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    let mut removed = Vec::new();
+    match ensure_private_dir(dir) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(removed),
+        Err(e) => return Err(e.into()),
+    }
+    let entries = fs::read_dir(dir)?;
+    for entry in entries {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() || entry.file_name() == HISTORY_FILE {
+            continue;
+        }
+        if metadata.modified()? < cutoff {
+            if !dry_run {
+                fs::remove_file(entry.path())?;
+            }
+            removed.push(entry.path());
+        }
+    }
+    removed.sort();
+
+    let history = load(dir)?;
+    let kept: Vec<&HistoryEntry> = history
+        .iter()
+        .filter(|e| e.created >= cutoff_secs)
+        .collect();
+    if !dry_run && kept.len() < history.len() {
+        let mut contents = String::new();
+        for entry in kept {
+            contents.push_str(&serde_json::to_string(entry).map_err(std::io::Error::other)?);
+            contents.push('\n');
+        }
+        // Replace the history in one step so readers see the old or the new
+        // history. A leftover staging file is removed rather than written
+        // through, in case it is a symlink.
+        let staging = dir.join(format!("{}.tmp", HISTORY_FILE));
+        match fs::remove_file(&staging) {
+            Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+        create_private(&staging, OpenOptions::new().write(true).create_new(true))?
+            .write_all(contents.as_bytes())?;
+        fs::rename(&staging, dir.join(HISTORY_FILE))?;
+    }
+    Ok(removed)
+}
+
+/// Parse an age such as "30m", "12h", "7d" or "2w"; a bare number is seconds
+pub fn parse_age(age: &str) -> Result<Duration, DecodeckError> {
+    let invalid = || DecodeckError::InvalidAge {
+        age: age.to_string(),
+    };
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (number, unit) = age.split_at(split);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    let seconds = match unit.trim().to_lowercase().as_str() {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)?;
+    ensure_private_dir(dir)
+}
+
+/// Refuse a directory that is a symlink or belongs to another user, as one
+/// planted in a shared temporary directory would, and make an owned one
+/// accessible only to its owner
+#[cfg(unix)]
+fn ensure_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let metadata = fs::symlink_metadata(dir)?;
+    // SAFETY: geteuid has no preconditions and always succeeds
+    let uid = unsafe { libc::geteuid() };
+    if !metadata.is_dir() || metadata.uid() != uid {
+        return Err(std::io::Error::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "{} is not a directory owned by the current user",
+                dir.display()
+            ),
+        ));
+    }
+    if metadata.mode() & 0o077 != 0 {
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn ensure_private_dir(dir: &Path) -> std::io::Result<()> {
+    fs::metadata(dir).map(|_| ())
+}
+
+fn create_private(path: &Path, options: &mut OpenOptions) -> std::io::Result<File> {
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(options, 0o600);
+    no_follow(options).open(path)
+}
+
+/// Fail with `ELOOP` instead of opening a symlink
+fn no_follow(options: &mut OpenOptions) -> &mut OpenOptions {
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(options, libc::O_NOFOLLOW);
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn entry(path: PathBuf, created: u64) -> HistoryEntry {
+        HistoryEntry {
+            path,
+            created,
+            source: "argument".to_string(),
+            encoding: "base64".to_string(),
+            mime_type: "text/plain".to_string(),
+            size_bytes: 5,
+            is_temporary: true,
+        }
+    }
+
+    #[test]
+    fn test_temp_outputs_are_unique() {
+        let dir = TempDir::new().unwrap();
+        let first = write_temp_output(dir.path(), b"hello", ".txt").unwrap();
+        let second = write_temp_output(dir.path(), b"hello", ".txt").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read(&first).unwrap(), b"hello");
+        assert_eq!(fs::read(&second).unwrap(), b"hello");
+        let name = first.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("output-") && name.ends_with("-2cf24dba.txt"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&first).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_record_and_clean() {
+        let dir = TempDir::new().unwrap();
+        let recent = write_temp_output(dir.path(), b"new", ".txt").unwrap();
+        let created = now();
+        record(dir.path(), &entry(recent.clone(), created)).unwrap();
+        record(dir.path(), &entry(dir.path().join("gone.txt"), 1_000)).unwrap();
+        fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(HISTORY_FILE))
+            .unwrap()
+            .write_all(b"not json\n")
+            .unwrap();
+        assert_eq!(load(dir.path()).unwrap().len(), 2);
+
+        let old = write_temp_output(dir.path(), b"old", ".bin").unwrap();
+        let week_ago = SystemTime::now() - Duration::from_secs(8 * 86_400);
+        File::options()
+            .write(true)
+            .open(&old)
+            .unwrap()
+            .set_modified(week_ago)
+            .unwrap();
+
+        let age = parse_age("7d").unwrap();
+        assert_eq!(clean(dir.path(), age, true).unwrap(), vec![old.clone()]);
+        assert!(old.exists());
+        assert_eq!(clean(dir.path(), age, false).unwrap(), vec![old.clone()]);
+        assert!(!old.exists() && recent.exists());
+        assert_eq!(load(dir.path()).unwrap(), vec![entry(recent, created)]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_output_directory_made_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        let shared = dir.path().join("decodeck");
+        fs::create_dir(&shared).unwrap();
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o777)).unwrap();
+        write_temp_output(&shared, b"hello", ".txt").unwrap();
+        let mode = fs::metadata(&shared).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        // A symlink planted in place of the directory is refused
+        let linked = dir.path().join("linked");
+        std::os::unix::fs::symlink(&shared, &linked).unwrap();
+        assert!(write_temp_output(&linked, b"hello", ".txt").is_err());
+        assert!(record(&linked, &entry(linked.join("a.txt"), now())).is_err());
+        assert!(clean(&linked, Duration::ZERO, false).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_history_symlinks_not_followed() {
+        let dir = TempDir::new().unwrap();
+        let victim = dir.path().join("victim");
+        fs::write(&victim, "keep\n").unwrap();
+        let private = dir.path().join("decodeck");
+        create_private_dir(&private).unwrap();
+
+        let history = private.join(HISTORY_FILE);
+        std::os::unix::fs::symlink(&victim, &history).unwrap();
+        assert!(record(&private, &entry(private.join("a.txt"), now())).is_err());
+        assert!(load(&private).is_err());
+        fs::remove_file(&history).unwrap();
+
+        // A planted staging file is replaced, not written through
+        record(&private, &entry(private.join("a.txt"), 1_000)).unwrap();
+        let staging = private.join(format!("{}.tmp", HISTORY_FILE));
+        std::os::unix::fs::symlink(&victim, &staging).unwrap();
+        clean(&private, Duration::from_secs(60), false).unwrap();
+        assert_eq!(fs::read_to_string(&victim).unwrap(), "keep\n");
+        assert!(load(&private).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("90").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_age("30m").unwrap(), Duration::from_secs(1_800));
+        assert_eq!(parse_age("2W").unwrap(), Duration::from_secs(1_209_600));
+        assert!(parse_age("7 days").is_err());
+        assert!(parse_age("d").is_err());
+    }
+}
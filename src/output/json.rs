@@ -1,9 +1,18 @@
 //! JSON output formatter
 
 use super::DecodeResult;
+use crate::analysis::archive::ArchiveListing;
+use crate::analysis::hashes::Hashes;
+use crate::analysis::iocs::Ioc;
+use crate::analysis::stats::ByteStats;
+use crate::analysis::strings::ExtractedString;
+use crate::encoding::cipher::{Cipher, CipherFamily};
+use crate::encoding::recipe::Step;
 use crate::encoding::{DetectionConfidence, EncodingType};
+use crate::metadata::risk::Risk;
 use serde::Serialize;
 use std::io::Write;
+use std::path::PathBuf;
 
 /// JSON output structure
 #[derive(Serialize)]
@@ -12,8 +21,24 @@ pub struct JsonOutput<'a> {
     pub output: OutputInfo<'a>,
     pub metadata: MetadataInfo<'a>,
     pub encoding: EncodingOutput,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub layers: Vec<LayerOutput>,
     pub duration_ms: u64,
     pub warnings: &'a [String],
+    #[serde(skip_serializing_if = "Hashes::is_empty")]
+    pub hashes: &'a Hashes,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub risk: Option<&'a Risk>,
+    #[serde(skip_serializing_if = "<[Ioc]>::is_empty")]
+    pub iocs: &'a [Ioc],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<&'a ByteStats>,
+    #[serde(skip_serializing_if = "<[ExtractedString]>::is_empty")]
+    pub strings: &'a [ExtractedString],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<&'a ArchiveListing>,
+    #[serde(skip_serializing_if = "<[PathBuf]>::is_empty")]
+    pub extracted: &'a [PathBuf],
 }
 
 #[derive(Serialize)]
@@ -31,6 +56,10 @@ pub struct MetadataInfo<'a> {
     pub category: &'a str,
     pub is_viewable: bool,
     pub is_playable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<&'static str>,
+    pub confidence: DetectionConfidence,
+    pub rule: &'a str,
 }
 
 #[derive(Serialize)]
@@ -47,6 +76,39 @@ pub struct EncodingOutput {
     pub had_padding: Option<bool>,
 }
 
+/// One layer peeled by a recipe or chain
+#[derive(Serialize)]
+pub struct LayerOutput {
+    pub step: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<EncodingType>,
+    /// Cipher name and recovered key
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub cipher: Option<Cipher>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub brute_force: Option<CipherFamily>,
+}
+
+impl From<&Step> for LayerOutput {
+    fn from(step: &Step) -> Self {
+        LayerOutput {
+            step: step.to_string(),
+            encoding: match step {
+                Step::Decode(encoding) => Some(*encoding),
+                _ => None,
+            },
+            cipher: match step {
+                Step::Cipher(cipher) => Some(*cipher),
+                _ => None,
+            },
+            brute_force: match step {
+                Step::BruteForce(family) => Some(*family),
+                _ => None,
+            },
+        }
+    }
+}
+
 /// Format decode result as JSON
 pub fn format(result: &DecodeResult, writer: &mut impl Write) -> std::io::Result<()> {
     let category = format!("{:?}", result.metadata.category).to_lowercase();
@@ -84,10 +146,21 @@ pub fn format(result: &DecodeResult, writer: &mut impl Write) -> std::io::Result
             category: &category,
             is_viewable: result.metadata.is_viewable,
             is_playable: result.metadata.is_playable,
+            charset: result.metadata.charset.map(|c| c.name()),
+            confidence: result.metadata.confidence,
+            rule: &result.metadata.rule,
         },
         encoding,
+        layers: result.layers.iter().map(LayerOutput::from).collect(),
         duration_ms: result.duration_ms,
         warnings: &result.warnings,
+        hashes: &result.hashes,
+        risk: result.risk.as_ref(),
+        iocs: &result.iocs,
+        stats: result.stats.as_ref(),
+        strings: &result.strings,
+        archive: result.archive.as_ref(),
+        extracted: &result.extracted,
     };
 
     let json = serde_json::to_string_pretty(&output).map_err(std::io::Error::other)?;
@@ -1,11 +1,21 @@
 //! Output handling module
 
+pub mod file;
+pub mod history;
 pub mod json;
+pub mod naming;
 pub mod text;
 
+use crate::analysis::archive::ArchiveListing;
+use crate::analysis::hashes::Hashes;
+use crate::analysis::iocs::Ioc;
+use crate::analysis::stats::ByteStats;
+use crate::analysis::strings::ExtractedString;
 use crate::decoder::EncodedData;
+use crate::encoding::recipe::Step;
 use crate::encoding::EncodingInfo;
 use crate::error::DecodeckError;
+use crate::metadata::risk::Risk;
 use crate::metadata::ContentMetadata;
 use serde::Serialize;
 use std::path::{Path, PathBuf};
@@ -41,10 +51,27 @@ pub struct DecodeResult {
     pub encoding: Option<EncodedData>,
     /// Multi-encoding type information
     pub encoding_info: EncodingInfo,
+    /// Layers peeled by a recipe or chain, outermost first
+    #[serde(skip)]
+    pub layers: Vec<Step>,
     /// Processing duration in milliseconds
     pub duration_ms: u64,
     /// Any warnings generated
     pub warnings: Vec<String>,
+    /// Why the decoded content is risky to open, if it is
+    pub risk: Option<Risk>,
+    /// Indicators of compromise found in the decoded content
+    pub iocs: Vec<Ioc>,
+    /// Entropy and byte statistics of the decoded content
+    pub stats: Option<ByteStats>,
+    /// Strings extracted from the decoded content
+    pub strings: Vec<ExtractedString>,
+    /// Entries of the decoded content, when it is an archive
+    pub archive: Option<ArchiveListing>,
+    /// Files unpacked from the decoded archive
+    pub extracted: Vec<PathBuf>,
+    /// Requested digests and fingerprints of the decoded content
+    pub hashes: Hashes,
 }
 
 /// Format bytes as human-readable string
@@ -65,6 +92,10 @@ pub fn format_size(bytes: usize) -> String {
 }
 
 /// Validate output path for safety
+///
+/// Besides traversal, an existing path must be a regular file: writing
+/// would replace a symbolic link rather than its target, and directories,
+/// devices and FIFOs cannot be replaced by a file.
 pub fn validate_output_path(path: &Path) -> Result<(), DecodeckError> {
     // Reject empty paths
     if path.as_os_str().is_empty() {
@@ -73,6 +104,25 @@ pub fn validate_output_path(path: &Path) -> Result<(), DecodeckError> {
             reason: "Path cannot be empty".to_string(),
         });
     }
+    let invalid = |reason: &str| DecodeckError::InvalidOutputPath {
+        path: path.display().to_string(),
+        reason: reason.to_string(),
+    };
+    if path.as_os_str().to_string_lossy().contains('\0') {
+        return Err(invalid("Path contains a NUL byte"));
+    }
+    if let Ok(metadata) = path.symlink_metadata() {
+        let file_type = metadata.file_type();
+        if file_type.is_symlink() {
+            return Err(invalid("Path is a symbolic link"));
+        }
+        if file_type.is_dir() {
+            return Err(invalid("Path is a directory"));
+        }
+        if !file_type.is_file() {
+            return Err(invalid("Path is not a regular file"));
+        }
+    }
 
     // Check for path traversal attempts
     let path_str = path.to_string_lossy();
@@ -0,0 +1,170 @@
+//! Output file names from templates
+//!
+//! A template such as `{source_stem}_{sha256:8}{ext}` names decoded
+//! artifacts from what is known about them:
+//!
+//! | Placeholder     | Value                                               |
+//! |-----------------|-----------------------------------------------------|
+//! | `{source_stem}` | input file name without extension, else `decoded`   |
+//! | `{ext}`         | detected extension with its dot, e.g. `.png`        |
+//! | `{sha256}`      | SHA-256 of the decoded content; `{sha256:8}` keeps 8 digits |
+//! | `{md5}`         | MD5 of the decoded content; `{md5:8}` keeps 8 digits |
+//! | `{timestamp}`   | UTC time of the decode, e.g. `20260101T120000Z`     |
+//! | `{encoding}`    | encoding of the last layer, e.g. `base64`           |
+//! | `{chain}`       | every layer decoded, e.g. `base64-gzip`             |
+//!
+//! `{{` and `}}` stand for literal braces. Characters that cannot appear in
+//! a file name on common platforms are replaced with `_`.
+
+use crate::error::DecodeckError;
+use crate::util::{compact_timestamp, hex};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+/// Template used with `--output-dir` when none is given
+pub const DEFAULT_TEMPLATE: &str = "{source_stem}_{sha256:8}{ext}";
+
+/// What a name template can refer to
+#[derive(Debug, Clone)]
+pub struct NameContext<'a> {
+    /// Input file name without extension, if the input was a file
+    pub source_stem: Option<&'a str>,
+    /// Extension with its leading dot
+    pub extension: &'a str,
+    pub data: &'a [u8],
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// Layers decoded, outermost first
+    pub layers: Vec<String>,
+}
+
+/// File name for decoded content from a template
+pub fn render(template: &str, context: &NameContext) -> Result<String, DecodeckError> {
+    let invalid = |reason: String| DecodeckError::InvalidTemplate {
+        template: template.to_string(),
+        reason,
+    };
+
+    let mut name = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                name.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                name.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(invalid("unclosed '{'".to_string())),
+                    }
+                }
+                let value = expand(&placeholder, context).map_err(invalid)?;
+                name.push_str(&value);
+            }
+            '}' => return Err(invalid("unmatched '}'".to_string())),
+            c => name.push(c),
+        }
+    }
+
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    if name.trim_matches('.').is_empty() {
+        return Err(invalid("expands to an empty file name".to_string()));
+    }
+    Ok(name)
+}
+
+/// Value of one placeholder
+fn expand(placeholder: &str, context: &NameContext) -> Result<String, String> {
+    let (key, length) = match placeholder.split_once(':') {
+        Some((key, length)) => {
+            let length: usize = length
+                .parse()
+                .map_err(|_| format!("invalid length in '{{{}}}'", placeholder))?;
+            (key, Some(length))
+        }
+        None => (placeholder, None),
+    };
+    let digest = |bytes: &[u8]| -> String {
+        let hex = hex(bytes);
+        match length {
+            Some(length) => hex.chars().take(length).collect(),
+            None => hex,
+        }
+    };
+
+    let value = match key {
+        "sha256" => return Ok(digest(&Sha256::digest(context.data))),
+        "md5" => return Ok(digest(&Md5::digest(context.data))),
+        "source_stem" => context.source_stem.unwrap_or("decoded").to_string(),
+        "ext" => context.extension.to_string(),
+        "timestamp" => compact_timestamp(context.timestamp),
+        "encoding" => context.layers.last().cloned().unwrap_or_default(),
+        "chain" => context.layers.join("-"),
+        _ => return Err(format!("unknown placeholder '{{{}}}'", placeholder)),
+    };
+    if length.is_some() {
+        return Err(format!("'{{{}}}' does not take a length", key));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(source_stem: Option<&str>) -> NameContext<'_> {
+        NameContext {
+            source_stem,
+            extension: ".png",
+            data: b"abc",
+            timestamp: 1_700_000_000,
+            layers: vec!["base64".to_string(), "xor:0x2a".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(DEFAULT_TEMPLATE, &context(Some("payload"))).unwrap(),
+            "payload_ba7816bf.png"
+        );
+        assert_eq!(
+            render("{timestamp}-{chain}{ext}", &context(None)).unwrap(),
+            "20231114T221320Z-base64-xor_0x2a.png"
+        );
+        assert_eq!(
+            render("{{{encoding}}}_{md5:4}", &context(None)).unwrap(),
+            "{xor_0x2a}_9001"
+        );
+        assert_eq!(
+            render("{source_stem}", &context(Some("a/b"))).unwrap(),
+            "a_b"
+        );
+    }
+
+    #[test]
+    fn test_invalid_templates() {
+        assert!(render("{nope}", &context(None)).is_err());
+        assert!(render("{ext:3}", &context(None)).is_err());
+        assert!(render("{sha256:x}", &context(None)).is_err());
+        assert!(render("name}", &context(None)).is_err());
+        assert!(render("..", &context(None)).is_err());
+        let err = render("out_{sha256", &context(None)).unwrap_err();
+        assert!(err.to_string().contains("unclosed '{'"), "{}", err);
+    }
+}
@@ -1,7 +1,10 @@
 //! Text output formatter
 
-use super::DecodeResult;
+use super::{format_size, DecodeResult};
+use crate::analysis::archive::ArchiveListing;
+use crate::analysis::stats::ByteStats;
 use crate::encoding::{DetectionConfidence, EncodingType};
+use crate::metadata::risk::Risk;
 use std::io::Write;
 
 /// Format decode result as human-readable text
@@ -12,8 +15,20 @@ pub fn format(result: &DecodeResult, writer: &mut impl Write) -> std::io::Result
         "Size: {} ({} bytes)",
         result.output.size_formatted, result.output.size_bytes
     )?;
-    writeln!(writer, "Type: {}", result.metadata.mime_type)?;
+    let type_confidence = match result.metadata.confidence {
+        DetectionConfidence::High | DetectionConfidence::Explicit => "high",
+        DetectionConfidence::Medium => "medium",
+        DetectionConfidence::Low => "low",
+    };
+    writeln!(
+        writer,
+        "Type: {} ({}, {} confidence)",
+        result.metadata.mime_type, result.metadata.rule, type_confidence
+    )?;
     writeln!(writer, "Extension: {}", result.metadata.extension)?;
+    if let Some(charset) = result.metadata.charset {
+        writeln!(writer, "Charset: {}", charset)?;
+    }
 
     // Show encoding information
     let encoding_info = &result.encoding_info;
@@ -56,6 +71,66 @@ pub fn format(result: &DecodeResult, writer: &mut impl Write) -> std::io::Result
         )?;
     }
 
+    if !result.hashes.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "Hashes:")?;
+        for (algorithm, value) in result.hashes.entries() {
+            writeln!(writer, "  {:<8} {}", algorithm.name(), value)?;
+        }
+    }
+
+    if let Some(ref risk) = result.risk {
+        writeln!(writer)?;
+        format_risk(risk, writer)?;
+    }
+
+    if !result.iocs.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "IOCs:")?;
+        for ioc in &result.iocs {
+            writeln!(writer, "  {:<12} {}", ioc.kind.name(), ioc.value)?;
+        }
+    }
+
+    if !result.strings.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "Strings:")?;
+        for string in &result.strings {
+            writeln!(
+                writer,
+                "  0x{:08x}  {:<8}  {}",
+                string.offset,
+                string.encoding.name(),
+                string.text
+            )?;
+            if let Some(ref encoded) = string.encoded {
+                writeln!(
+                    writer,
+                    "      {} ({:?}): {}",
+                    encoded.encoding_type, encoded.confidence, encoded.decoded
+                )?;
+            }
+        }
+    }
+
+    if let Some(ref stats) = result.stats {
+        writeln!(writer)?;
+        format_stats(stats, writer)?;
+    }
+
+    if let Some(ref archive) = result.archive {
+        writeln!(writer)?;
+        format_archive(archive, writer)?;
+    }
+
+    if !result.extracted.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "Extracted {} files:", result.extracted.len())?;
+        for path in &result.extracted {
+            writeln!(writer, "  {}", path.display())?;
+        }
+    }
+
     if !result.warnings.is_empty() {
         writeln!(writer)?;
         for warning in &result.warnings {
@@ -65,3 +140,115 @@ pub fn format(result: &DecodeResult, writer: &mut impl Write) -> std::io::Result
 
     Ok(())
 }
+
+/// Format a risk classification, with the header summary of executables
+pub fn format_risk(risk: &Risk, writer: &mut impl Write) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "Risk: {:?} - {} ({})",
+        risk.level,
+        risk.kind.description(),
+        risk.reason
+    )?;
+    let Some(ref summary) = risk.executable else {
+        return Ok(());
+    };
+    writeln!(
+        writer,
+        "  Format:       {} {}-bit {}",
+        summary.format.name(),
+        summary.bits,
+        summary.kind
+    )?;
+    writeln!(writer, "  Architecture: {}", summary.architecture)?;
+    if let Some(entry_point) = summary.entry_point {
+        writeln!(writer, "  Entry point:  0x{:x}", entry_point)?;
+    }
+    if !summary.sections.is_empty() {
+        writeln!(writer, "  Sections:")?;
+        for section in &summary.sections {
+            writeln!(
+                writer,
+                "    {:<16} 0x{:08x}  {} bytes",
+                section.name, section.address, section.size
+            )?;
+        }
+    }
+    Ok(())
+}
+
+/// Format byte statistics as human-readable text
+pub fn format_stats(stats: &ByteStats, writer: &mut impl Write) -> std::io::Result<()> {
+    writeln!(writer, "Statistics:")?;
+    writeln!(writer, "  Entropy:        {:.2} bits/byte", stats.entropy)?;
+    writeln!(
+        writer,
+        "  Printable:      {:.1}% (longest run {} bytes)",
+        stats.printable_ratio * 100.0,
+        stats.longest_printable_run
+    )?;
+    writeln!(writer, "  Chi-square:     {:.1}", stats.chi_square)?;
+    let common: Vec<String> = stats
+        .most_common(5)
+        .iter()
+        .map(|(byte, count)| {
+            format!(
+                "0x{:02x} ({:.1}%)",
+                byte,
+                *count as f64 * 100.0 / stats.size as f64
+            )
+        })
+        .collect();
+    if !common.is_empty() {
+        writeln!(writer, "  Most common:    {}", common.join(", "))?;
+    }
+    writeln!(writer, "  Classification: {}", stats.classification.hint())?;
+
+    if let Some(graph) = stats.entropy_graph() {
+        writeln!(writer)?;
+        writeln!(writer, "Entropy per {} bytes:", stats.window_size)?;
+        writeln!(writer, "{}", graph)?;
+    }
+    Ok(())
+}
+
+/// Format an archive listing as human-readable text
+pub fn format_archive(archive: &ArchiveListing, writer: &mut impl Write) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "Archive ({}): {} entries, {} uncompressed",
+        archive.format.name(),
+        archive.entries.len(),
+        format_size(archive.total_size() as usize)
+    )?;
+    for entry in &archive.entries {
+        let compressed = entry
+            .compressed_size
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let modified = entry.modified.as_deref().unwrap_or("-");
+        let mut name = entry.name.clone();
+        if let Some(ref target) = entry.link_target {
+            name.push_str(&format!(" -> {}", target));
+        }
+        if entry.encrypted {
+            name.push_str(" [encrypted]");
+        }
+        writeln!(
+            writer,
+            "  {:>10}  {:>10}  {:<20}  {}",
+            entry.size, compressed, modified, name
+        )?;
+        if let Some(ref mime_type) = entry.mime_type {
+            writeln!(writer, "      type: {}", mime_type)?;
+        }
+        for finding in &entry.findings {
+            writeln!(
+                writer,
+                "      {} {}: {}",
+                finding.path, finding.encoding, finding.decoded
+            )?;
+        }
+    }
+    Ok(())
+}
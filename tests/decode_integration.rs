@@ -89,3 +89,445 @@ fn decode_invalid_base64_shows_error() {
     let mut cmd = Command::cargo_bin("decodeck").unwrap();
     cmd.arg("decode").arg("Invalid$Base64!").assert().failure();
 }
+
+#[test]
+fn decode_powershell_encoded_command_reports_charset() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("script.txt");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("powershell.exe -NoProfile -enc VwByAGkAdABlAC0ASABvAHMAdAAgAGgAaQA=")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Charset: utf-16le"));
+
+    let content = fs::read(&output_path).expect("Should read output file");
+    assert_eq!(content[..4], [b'W', 0, b'r', 0]);
+}
+
+#[test]
+fn decode_extract_iocs_refangs_indicators() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("payload.txt");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("Y29ubmVjdCB0byAxOTIuMTY4LjEuMTAgb3IgZXZpbFsuXWV4YW1wbGVbLl1jb20=")
+        .arg("--encoding")
+        .arg("base64")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .arg("--extract-iocs")
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""value": "192.168.1.10""#))
+        .stdout(predicate::str::contains(r#""value": "evil.example.com""#));
+}
+
+#[test]
+fn decode_stats_classifies_text() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("payload.txt");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8sIHdvcmxkISBUaGlzIGlzIGEgdGVzdC4=")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .arg("--stats")
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""classification": "text""#))
+        .stdout(predicate::str::contains(r#""longest_printable_run": 29"#));
+}
+
+#[test]
+fn decode_strings_flags_embedded_base64() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("payload.bin");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("f0VMRgIBAQAAAAAAAAAAAAAAAAAAAAAAAAAAAEdldFByb2NBZGRyZXNzAJBrAGUAcgBuAGUAbAAzADIALgBkAGwAbAAAAGFIUjBjRG92TDJWMmFXd3VaWGhoYlhCc1pTOWhMbkJ6TVE9PQD/")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .arg("--strings")
+        .arg("--strings-detect")
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""text": "GetProcAddress""#))
+        .stdout(predicate::str::contains(r#""encoding": "utf-16le""#))
+        .stdout(predicate::str::contains(r#""decoded": "http://evil.example/a.ps1""#));
+}
+
+#[test]
+fn decode_lists_and_extracts_archive() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("payload.zip");
+    let extract_dir = temp_dir.path().join("unpacked");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("UEsDBBQAAAAIAIMYIljyFi5lGQAAABcAAAAPAAAAZG9jcy9yZWFkbWUudHh0y0jNyclXSCvKz1UoyUhVSCxKzsgsS+UCAFBLAQIUAxQAAAAIAIMYIljyFi5lGQAAABcAAAAPAAAAAAAAAAAAAACAAQAAAABkb2NzL3JlYWRtZS50eHRQSwUGAAAAAAEAAQA9AAAARgAAAAAA")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .arg("--inspect-archive")
+        .arg("--extract")
+        .arg(extract_dir.to_str().unwrap())
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Archive (zip): 1 entries"))
+        .stdout(predicate::str::contains("2024-01-02T03:04:06"))
+        .stdout(predicate::str::contains("docs/readme.txt"))
+        .stdout(predicate::str::contains("type: text/plain"))
+        .stdout(predicate::str::contains("Extracted 1 files:"));
+
+    let content = fs::read_to_string(extract_dir.join("docs/readme.txt")).unwrap();
+    assert_eq!(content, "hello from the archive\n");
+}
+
+#[test]
+fn decode_flags_executable_risk() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("payload.bin");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("f0VMRgIBAAAAAAAAAAAAAAIAPgAAAAAAABBAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA==")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""kind": "executable""#))
+        .stdout(predicate::str::contains(r#""level": "high""#))
+        .stdout(predicate::str::contains(r#""architecture": "x86-64""#))
+        .stdout(predicate::str::contains(r#""entry_point": 4198400"#))
+        .stdout(predicate::str::contains(
+            "Decoded content is risky to open: executable (ELF executable for x86-64)",
+        ));
+}
+
+#[test]
+fn decode_uses_user_signature_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("backup");
+    let signatures = temp_dir.path().join("signatures.toml");
+    fs::write(
+        &signatures,
+        "[[signature]]\nname = \"acme\"\nmime_type = \"application/x-acme-backup\"\nextension = \"abk\"\nhex = \"41 43 4d 45 00\"\n",
+    )
+    .unwrap();
+
+    // "ACME\0" followed by binary data
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("QUNNRQABAgP/")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--signatures")
+        .arg(signatures.to_str().unwrap())
+        .arg("--no-interactive")
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""mime_type": "application/x-acme-backup""#))
+        .stdout(predicate::str::contains(r#""extension": ".abk""#))
+        .stdout(predicate::str::contains(r#""rule": "user:acme""#));
+}
+
+#[test]
+fn decode_rejects_invalid_signature_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let signatures = temp_dir.path().join("signatures.toml");
+    fs::write(&signatures, "[[signature]]\nname = \"broken\"\n").unwrap();
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8gV29ybGQh")
+        .arg("--signatures")
+        .arg(signatures.to_str().unwrap())
+        .arg("--no-interactive")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Invalid signature file"));
+}
+
+#[test]
+fn decode_reports_and_verifies_hashes() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("hello.txt");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8gV29ybGQh")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--hash")
+        .arg("md5,ssdeep")
+        .arg("--verify-hash")
+        .arg("7F83B1657FF1FC53B92DC18148A1D65DFC2D4B1FA3D677284ADDD200126D9069")
+        .arg("--no-interactive")
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""md5": "ed076287532e86365e841e92bfc50d8c""#))
+        .stdout(predicate::str::contains(
+            r#""sha256": "7f83b1657ff1fc53b92dc18148a1d65dfc2d4b1fa3d677284addd200126d9069""#,
+        ))
+        .stdout(predicate::str::contains(r#""ssdeep": "3:"#));
+
+    // A mismatch fails before the output is written
+    let mismatch_path = temp_dir.path().join("mismatch.txt");
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8gV29ybGQh")
+        .arg("--output")
+        .arg(mismatch_path.to_str().unwrap())
+        .arg("--verify-hash")
+        .arg("md5:00000000000000000000000000000000")
+        .arg("--no-interactive")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("Hash mismatch: expected MD5"));
+    assert!(!mismatch_path.exists());
+}
+
+#[test]
+fn decode_temp_outputs_are_unique_and_recorded() {
+    let temp_dir = TempDir::new().unwrap();
+    let outputs = temp_dir.path().join("decodeck");
+
+    for data in ["SGVsbG8gV29ybGQh", "SGVsbG8gV29ybGQh"] {
+        let mut cmd = Command::cargo_bin("decodeck").unwrap();
+        cmd.env("TMPDIR", temp_dir.path())
+            .arg("decode")
+            .arg(data)
+            .arg("--no-interactive")
+            .assert()
+            .success();
+    }
+    let written: Vec<_> = fs::read_dir(&outputs)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .filter(|p| p.extension().is_some_and(|e| e == "txt"))
+        .collect();
+    assert_eq!(written.len(), 2);
+    for path in &written {
+        assert_eq!(fs::read_to_string(path).unwrap(), "Hello World!");
+    }
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.env("TMPDIR", temp_dir.path())
+        .arg("history")
+        .arg("--json")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""source": "argument""#))
+        .stdout(predicate::str::contains(r#""mime_type": "text/plain""#));
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.env("TMPDIR", temp_dir.path())
+        .arg("clean")
+        .arg("--older-than")
+        .arg("0")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 2 files"));
+    assert!(written.iter().all(|p| !p.exists()));
+}
+
+#[test]
+fn decode_keeps_existing_output_with_backup_or_suffix() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("hello.txt");
+    fs::write(&output_path, "previous").unwrap();
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8gV29ybGQh")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--backup")
+        .arg("--no-interactive")
+        .assert()
+        .success();
+    assert_eq!(fs::read_to_string(&output_path).unwrap(), "Hello World!");
+    let backup = temp_dir.path().join("hello.txt.bak");
+    assert_eq!(fs::read_to_string(backup).unwrap(), "previous");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("R29vZGJ5ZQ==")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-clobber-suffix")
+        .arg("--no-interactive")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello (1).txt"));
+    let suffixed = temp_dir.path().join("hello (1).txt");
+    assert_eq!(fs::read_to_string(suffixed).unwrap(), "Goodbye");
+    assert_eq!(fs::read_to_string(&output_path).unwrap(), "Hello World!");
+}
+
+#[cfg(unix)]
+#[test]
+fn decode_refuses_to_write_through_symlink() {
+    let temp_dir = TempDir::new().unwrap();
+    let target = temp_dir.path().join("target.txt");
+    fs::write(&target, "untouched").unwrap();
+    let link = temp_dir.path().join("link.txt");
+    std::os::unix::fs::symlink(&target, &link).unwrap();
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8gV29ybGQh")
+        .arg("--output")
+        .arg(link.to_str().unwrap())
+        .arg("--force")
+        .arg("--no-interactive")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("symbolic link"));
+    assert_eq!(fs::read_to_string(&target).unwrap(), "untouched");
+}
+
+#[test]
+fn decode_names_output_in_output_dir() {
+    let temp_dir = TempDir::new().unwrap();
+    let input = temp_dir.path().join("payload.b64");
+    fs::write(&input, "SGVsbG8gV29ybGQh").unwrap();
+    let out_dir = temp_dir.path().join("artifacts");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("--file")
+        .arg(input.to_str().unwrap())
+        .arg("--output-dir")
+        .arg(out_dir.to_str().unwrap())
+        .arg("--no-interactive")
+        .assert()
+        .success();
+    let named = out_dir.join("payload_7f83b165.txt");
+    assert_eq!(fs::read_to_string(named).unwrap(), "Hello World!");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8gV29ybGQh")
+        .arg("--output-dir")
+        .arg(out_dir.to_str().unwrap())
+        .arg("--name-template")
+        .arg("{source_stem}-{encoding}{ext}")
+        .arg("--no-interactive")
+        .assert()
+        .success();
+    assert!(out_dir.join("decoded-base64.txt").exists());
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGVsbG8gV29ybGQh")
+        .arg("--output-dir")
+        .arg(out_dir.to_str().unwrap())
+        .arg("--name-template")
+        .arg("{size}")
+        .arg("--no-interactive")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("unknown placeholder '{size}'"));
+}
+
+#[test]
+fn decode_recipe_brute_forces_xor_key() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("plain.txt");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("S15eS0lBCkteCk5LXUQGCkhYQ0RNCl5CTwpHS1pZ")
+        .arg("--recipe")
+        .arg("base64,xor")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Recipe: base64 → xor:0x2a"));
+
+    let content = fs::read_to_string(&output_path).expect("Should read output file");
+    assert_eq!(content, "attack at dawn, bring the maps");
+}
+
+#[test]
+fn decode_chain_with_ciphers_finds_rot13() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("plain.txt");
+
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("R3V2ZiB2ZiBuIGNyZXNycGd5bCBhYmV6bnkgZnJhZ3JhcHIgbm9iaGcgZ3VyIGpybmd1cmUgZ2Jxbmwu")
+        .arg("--chain")
+        .arg("--ciphers")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Chain: base64 → rot13"));
+}
+
+#[test]
+fn decode_chain_json_reports_cipher_key() {
+    let temp_dir = TempDir::new().unwrap();
+    let output_path = temp_dir.path().join("plain.txt");
+
+    // "attack at dawn and bring the maps" XORed with 0x2a stays printable
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    let output = cmd
+        .arg("decode")
+        .arg("S15eS0lBCkteCk5LXUQKS0ROCkhYQ0RNCl5CTwpHS1pZ")
+        .arg("--chain")
+        .arg("--ciphers")
+        .arg("--json")
+        .arg("--output")
+        .arg(output_path.to_str().unwrap())
+        .arg("--no-interactive")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let layers = json["layers"].as_array().unwrap();
+    assert_eq!(layers.len(), 2);
+    assert_eq!(layers[0]["encoding"], "base64");
+    assert_eq!(layers[1]["cipher"], "xor");
+    assert_eq!(layers[1]["key"], 42);
+    assert_eq!(
+        fs::read(&output_path).unwrap(),
+        b"attack at dawn and bring the maps"
+    );
+}
+
+#[test]
+fn decode_recipe_rejects_unknown_step() {
+    let mut cmd = Command::cargo_bin("decodeck").unwrap();
+    cmd.arg("decode")
+        .arg("SGk=")
+        .arg("--recipe")
+        .arg("base64,vigenere")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("unknown encoding or cipher"));
+}
@@ -1,6 +1,7 @@
 //! Unit tests for interactive module
 
 use decodeck::interactive::InteractivePrompt;
+use decodeck::encoding::DetectionConfidence;
 use decodeck::metadata::{ContentCategory, ContentMetadata};
 
 // T054: Tests for terminal detection
@@ -37,6 +38,9 @@ mod prompt_text {
             category: ContentCategory::Image,
             is_viewable: true,
             is_playable: false,
+            charset: None,
+            confidence: DetectionConfidence::High,
+            rule: "infer".to_string(),
             magic_bytes: None,
         }
     }
@@ -48,6 +52,9 @@ mod prompt_text {
             category: ContentCategory::Audio,
             is_viewable: false,
             is_playable: true,
+            charset: None,
+            confidence: DetectionConfidence::High,
+            rule: "infer".to_string(),
             magic_bytes: None,
         }
     }
@@ -59,6 +66,9 @@ mod prompt_text {
             category: ContentCategory::Other,
             is_viewable: false,
             is_playable: false,
+            charset: None,
+            confidence: DetectionConfidence::Low,
+            rule: "fallback".to_string(),
             magic_bytes: None,
         }
     }
@@ -85,6 +95,41 @@ mod prompt_text {
     }
 }
 
+mod open_policy {
+    use decodeck::interactive::{InteractivePrompt, OpenPolicy};
+    use decodeck::metadata::risk;
+
+    #[test]
+    fn plain_content_is_allowed() {
+        let risk = risk::classify(b"%PDF-1.4\n<< /Type /Catalog >>");
+        assert_eq!(
+            InteractivePrompt::open_policy(risk.as_ref()),
+            OpenPolicy::Allow
+        );
+    }
+
+    #[test]
+    fn pdf_with_javascript_needs_confirmation() {
+        let risk = risk::classify(b"%PDF-1.4\n<< /OpenAction << /S /JavaScript /JS (x) >> >>");
+        assert_eq!(
+            InteractivePrompt::open_policy(risk.as_ref()),
+            OpenPolicy::Confirm
+        );
+    }
+
+    #[test]
+    fn shortcut_is_refused() {
+        let mut lnk = vec![0x4c, 0, 0, 0, 0x01, 0x14, 0x02, 0, 0, 0, 0, 0];
+        lnk.extend([0xc0, 0, 0, 0, 0, 0, 0, 0x46]);
+        lnk.extend(vec![0u8; 56]);
+        let risk = risk::classify(&lnk);
+        assert_eq!(
+            InteractivePrompt::open_policy(risk.as_ref()),
+            OpenPolicy::Refuse
+        );
+    }
+}
+
 // T056: Tests for key handling
 mod key_handling {
     // Key handling tests would require mocking terminal input
@@ -50,6 +50,53 @@ mod mime_detection {
     }
 }
 
+// Layered detection beyond the infer crate
+mod layered_detection {
+    use super::*;
+    use decodeck::encoding::DetectionConfidence;
+
+    #[test]
+    fn infer_match_has_high_confidence() {
+        let png_data = [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00];
+        let metadata = magic::detect(&png_data);
+        assert_eq!(metadata.rule, "infer");
+        assert_eq!(metadata.confidence, DetectionConfidence::High);
+    }
+
+    #[test]
+    fn detect_pcap_from_signature_table() {
+        let pcap_data = [0xD4, 0xC3, 0xB2, 0xA1, 0x02, 0x00, 0x04, 0x00];
+        let metadata = magic::detect(&pcap_data);
+        assert_eq!(metadata.mime_type, "application/vnd.tcpdump.pcap");
+        assert_eq!(metadata.extension, ".pcap");
+        assert_eq!(metadata.rule, "signature:pcap");
+    }
+
+    #[test]
+    fn detect_plain_text() {
+        let metadata = magic::detect(b"Hello World!");
+        assert_eq!(metadata.mime_type, "text/plain");
+        assert_eq!(metadata.extension, ".txt");
+        assert_eq!(metadata.rule, "text:plain");
+        assert_eq!(metadata.category, ContentCategory::Document);
+        assert!(metadata.charset.is_some());
+    }
+
+    #[test]
+    fn detect_json_text() {
+        let metadata = magic::detect(br#"{"id": 7, "tags": ["a", "b"]}"#);
+        assert_eq!(metadata.mime_type, "application/json");
+        assert_eq!(metadata.rule, "text:json");
+    }
+
+    #[test]
+    fn detect_svg_as_viewable_image() {
+        let metadata = magic::detect(br#"<svg xmlns="http://www.w3.org/2000/svg"></svg>"#);
+        assert_eq!(metadata.mime_type, "image/svg+xml");
+        assert!(metadata.is_viewable);
+    }
+}
+
 // T038: Tests for extension mapping
 mod extension_mapping {
     use super::*;